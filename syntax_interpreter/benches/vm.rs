@@ -0,0 +1,49 @@
+// Integration- and recursion-heavy scripts timed on the bytecode machine and by walking the
+// syntax tree. Run with `cargo bench`; a script name as argument runs only that one.
+use std::time::{Duration, Instant};
+
+use syntax_interpreter::evaluator::display_value;
+use syntax_interpreter::interpreter::Interpreter;
+
+const SCRIPTS: &[(&str, &str)] = &[
+    ("integrate", "f(x) = exp(-x^2) * cos(3 * x)\nint(f, -4, 4, 20000)"),
+    ("integrate-lambda", "k = 2\nint((x) => sin(k * x)^2 + x / (1 + x^2), 0, 10, 20000)"),
+    ("integrate-calls", "sq(x) = x * x\ngauss(x) = exp(-sq(x) / 2) / sqrt(2 * pi)\nint(gauss, -5, 5, 20000)"),
+    ("recursion", "tree(n) = n < 2 or (tree(n - 1) and tree(n - 2))\ntree(18)"),
+    ("mutual-recursion", "even(n) = n == 0 or odd(n - 1)\nodd(n) = n != 0 and even(n - 1)\neven(150) and odd(149)"),
+    ("limit", "lim {x -> 0} (sin(x) - x) / x^3"),
+];
+
+// each script runs this many times in each mode; the fastest run counts
+const RUNS: usize = 5;
+
+fn time(src: &str, vm: bool) -> (Duration, String) {
+    let mut best: Duration = Duration::MAX;
+    let mut result: String = String::new();
+    for _ in 0..RUNS {
+        let mut ms = Interpreter::new();
+        ms.env().shared.vm.set_enabled(vm);
+        let start: Instant = Instant::now();
+        let v = ms.eval_str(src);
+        best = best.min(start.elapsed());
+        result = match v {
+            Ok(v) => display_value(&v),
+            Err(msg) => format!("ERROR: {}", msg),
+        };
+    }
+    (best, result)
+}
+
+fn main() {
+    // cargo bench passes --bench; anything else selects scripts by name
+    let only: Vec<String> = std::env::args().skip(1).filter(|a| !a.starts_with("--")).collect();
+    println!("{:<18} {:>12} {:>12} {:>8}", "script", "tree walk", "bytecode", "speedup");
+    for (name, src) in SCRIPTS {
+        if !only.is_empty() && !only.iter().any(|o| o == name) { continue; }
+        let (walked, expected) = time(src, false);
+        let (compiled, result) = time(src, true);
+        assert_eq!(result, expected, "{} gives a different result on the bytecode machine", name);
+        println!("{:<18} {:>10.2}ms {:>10.2}ms {:>7.1}x", name, walked.as_secs_f64() * 1e3, compiled.as_secs_f64() * 1e3,
+            walked.as_secs_f64() / compiled.as_secs_f64());
+    }
+}
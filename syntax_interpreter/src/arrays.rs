@@ -0,0 +1,245 @@
+// Array built-ins: functional helpers (map, filter, reduce, ...) and constructors (range, linspace, ...)
+
+use std::cmp::Ordering;
+
+use crate::evaluator::{call_value, truth, values_equal, Env, Value};
+
+pub const NAMES: &[&str] = &[
+    "map", "filter", "reduce", "zip", "enumerate", "sort", "unique", "reverse",
+    "len", "range", "linspace", "concat", "flatten",
+];
+
+/// Dispatch an array built-in. Returns `None` when `name` is not one of [`NAMES`].
+pub fn call(name: &str, args: &[Value], env: &mut Env) -> Option<Value> {
+    if !NAMES.contains(&name) { return None; }
+    Some(dispatch(name, args, env).unwrap_or_else(|msg| Value::Str(format!("ERROR: {}", msg))))
+}
+
+fn dispatch(name: &str, args: &[Value], env: &mut Env) -> Result<Value, String> {
+    match name {
+        "map" => map(args, env),
+        "filter" => filter(args, env),
+        "reduce" => reduce(args, env),
+        "zip" => zip(args),
+        "enumerate" => {
+            let a: &[Value] = array_arg(name, args, 0)?;
+            Ok(Value::Array(a.iter().enumerate().map(|(i, v)| Value::Array(vec![Value::Number(i as f64), v.clone()])).collect()))
+        }
+        "sort" => sort(args, env),
+        "unique" => {
+            let a: &[Value] = array_arg(name, args, 0)?;
+            let mut out: Vec<Value> = Vec::new();
+            for v in a {
+                if !out.iter().any(|u| values_equal(u, v)) { out.push(v.clone()); }
+            }
+            Ok(Value::Array(out))
+        }
+        "reverse" => {
+            let a: &[Value] = array_arg(name, args, 0)?;
+            Ok(Value::Array(a.iter().rev().cloned().collect()))
+        }
+        "len" => match args.first() {
+            Some(Value::Array(a) | Value::Set(a)) => Ok(Value::Number(a.len() as f64)),
+            Some(Value::Str(s)) => Ok(Value::Number(s.chars().count() as f64)),
+            _ => Err("len expects an array, a set or a string".to_string()),
+        },
+        "range" => range(args, env),
+        "linspace" => linspace(args, env),
+        "concat" => {
+            let mut out: Vec<Value> = Vec::new();
+            for a in args {
+                match a {
+                    Value::Array(items) => out.extend(items.iter().cloned()),
+                    other => out.push(other.clone()),
+                }
+            }
+            Ok(Value::Array(out))
+        }
+        "flatten" => {
+            let a: &[Value] = array_arg(name, args, 0)?;
+            let depth: usize = match args.get(1) {
+                Some(Value::Number(d)) if *d >= 0.0 => *d as usize,
+                None => usize::MAX,
+                _ => return Err("flatten: depth must be a non-negative number".to_string()),
+            };
+            let mut out: Vec<Value> = Vec::new();
+            flatten_into(a, depth, &mut out);
+            Ok(Value::Array(out))
+        }
+        _ => Err(format!("function '{}' does not exist or is not implemented", name)),
+    }
+}
+
+fn array_arg<'a>(name: &str, args: &'a [Value], i: usize) -> Result<&'a [Value], String> {
+    match args.get(i) {
+        Some(Value::Array(a)) => Ok(a),
+        _ => Err(format!("{}: argument {} must be an array", name, i + 1)),
+    }
+}
+
+fn number_arg(name: &str, args: &[Value], i: usize) -> Result<f64, String> {
+    match args.get(i) {
+        Some(Value::Number(n)) => Ok(*n),
+        _ => Err(format!("{}: argument {} must be a number", name, i + 1)),
+    }
+}
+
+fn map(args: &[Value], env: &mut Env) -> Result<Value, String> {
+    // map(f, a [, b, ...]): apply f element-wise, recursing into nested arrays like the arithmetic operators
+    if args.len() < 2 {
+        return Err("map expects at least 2 arguments (function, array [, array...])".to_string());
+    }
+    map_rec(&args[0], &args[1..], env)
+}
+
+fn map_rec(f: &Value, items: &[Value], env: &mut Env) -> Result<Value, String> {
+    let mut len: Option<usize> = None;
+    for v in items {
+        if let Value::Array(a) = v {
+            match len {
+                Some(n) if n != a.len() => return Err("map: arrays must have the same shape".to_string()),
+                _ => len = Some(a.len()),
+            }
+        }
+    }
+    let Some(len) = len else { return Ok(call_value(f, items, env)) };
+    let mut out: Vec<Value> = Vec::with_capacity(len);
+    for i in 0..len {
+        // scalars are broadcast against every element
+        let row: Vec<Value> = items.iter().map(|v| match v {
+            Value::Array(a) => a[i].clone(),
+            other => other.clone(),
+        }).collect();
+        out.push(map_rec(f, &row, env)?);
+    }
+    Ok(Value::Array(out))
+}
+
+fn filter(args: &[Value], env: &mut Env) -> Result<Value, String> {
+    // filter(b, a, x) with coefficient arrays is the signal filter
+    if let [Value::Array(_), Value::Array(_), Value::Array(_)] = args { return crate::signal::filter(args); }
+    let (Some(pred), Some(Value::Array(a))) = (args.first(), args.get(1)) else {
+        return Err("filter expects 2 arguments (predicate, array), or (b, a, x) to filter a signal".to_string());
+    };
+    let mut out: Vec<Value> = Vec::new();
+    for v in a {
+        if truth(&call_value(pred, std::slice::from_ref(v), env), "filter: the predicate's result")? { out.push(v.clone()); }
+    }
+    Ok(Value::Array(out))
+}
+
+fn reduce(args: &[Value], env: &mut Env) -> Result<Value, String> {
+    // reduce(f, a [, init]): left fold; without init the first element is the seed
+    let (Some(f), Some(Value::Array(a))) = (args.first(), args.get(1)) else {
+        return Err("reduce expects at least 2 arguments (function, array [, init])".to_string());
+    };
+    let mut rest = a.iter();
+    let mut acc: Value = match args.get(2) {
+        Some(init) => init.clone(),
+        None => match rest.next() {
+            Some(first) => first.clone(),
+            None => return Err("reduce of an empty array with no initial value".to_string()),
+        },
+    };
+    for v in rest {
+        acc = call_value(f, &[acc, v.clone()], env);
+    }
+    Ok(acc)
+}
+
+fn zip(args: &[Value]) -> Result<Value, String> {
+    let mut arrays: Vec<&Vec<Value>> = Vec::with_capacity(args.len());
+    for a in args {
+        match a {
+            Value::Array(items) => arrays.push(items),
+            _ => return Err("zip expects array arguments".to_string()),
+        }
+    }
+    let Some(len) = arrays.first().map(|a| a.len()) else { return Ok(Value::Array(Vec::new())) };
+    if arrays.iter().any(|a| a.len() != len) {
+        return Err("zip: arrays must have the same length".to_string());
+    }
+    Ok(Value::Array((0..len).map(|i| Value::Array(arrays.iter().map(|a| a[i].clone()).collect())).collect()))
+}
+
+fn sort(args: &[Value], env: &mut Env) -> Result<Value, String> {
+    // sort(a [, key]): ascending, stable; key maps each element to the value compared
+    let Some(Value::Array(a)) = args.first() else {
+        return Err("sort expects an array as its first argument".to_string());
+    };
+    let keys: Vec<Value> = match args.get(1) {
+        Some(key) => a.iter().map(|v| call_value(key, std::slice::from_ref(v), env)).collect(),
+        None => a.clone(),
+    };
+    let mut idx: Vec<usize> = (0..a.len()).collect();
+    let mut incomparable: bool = false;
+    idx.sort_by(|&x, &y| compare_values(&keys[x], &keys[y]).unwrap_or_else(|| { incomparable = true; Ordering::Equal }));
+    if incomparable {
+        return Err("sort: elements cannot be compared".to_string());
+    }
+    Ok(Value::Array(idx.into_iter().map(|i| a[i].clone()).collect()))
+}
+
+fn compare_values(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x.partial_cmp(y),
+        (Value::Str(x), Value::Str(y)) => Some(x.cmp(y)),
+        (Value::Array(x), Value::Array(y)) => {
+            for (u, v) in x.iter().zip(y.iter()) {
+                match compare_values(u, v)? {
+                    Ordering::Equal => continue,
+                    other => return Some(other),
+                }
+            }
+            Some(x.len().cmp(&y.len()))
+        }
+        _ => None,
+    }
+}
+
+fn range(args: &[Value], env: &Env) -> Result<Value, String> {
+    // range(n) -> [0, n); range(a, b [, step]) -> [a, b)
+    let (start, stop, step) = match args.len() {
+        1 => (0.0, number_arg("range", args, 0)?, 1.0),
+        2 => (number_arg("range", args, 0)?, number_arg("range", args, 1)?, 1.0),
+        3 => (number_arg("range", args, 0)?, number_arg("range", args, 1)?, number_arg("range", args, 2)?),
+        _ => return Err("range expects 1 to 3 arguments (n) or (start, stop [, step])".to_string()),
+    };
+    if step == 0.0 || !step.is_finite() {
+        return Err("range: step must be a non-zero number".to_string());
+    }
+    let count: f64 = ((stop - start) / step).ceil();
+    if !count.is_finite() {
+        return Err("range: bounds must be finite".to_string());
+    }
+    let count: usize = count.max(0.0) as usize;
+    env.shared.sandbox.check_len(count)?;
+    Ok(Value::Array((0..count).map(|i| Value::Number(start + i as f64 * step)).collect()))
+}
+
+fn linspace(args: &[Value], env: &Env) -> Result<Value, String> {
+    // linspace(a, b, n): n evenly spaced points including both ends
+    if args.len() != 3 {
+        return Err("linspace expects 3 arguments (start, stop, n)".to_string());
+    }
+    let a: f64 = number_arg("linspace", args, 0)?;
+    let b: f64 = number_arg("linspace", args, 1)?;
+    let n: f64 = number_arg("linspace", args, 2)?;
+    if n < 0.0 || n.fract() != 0.0 {
+        return Err("linspace: n must be a non-negative integer".to_string());
+    }
+    let n: usize = n as usize;
+    env.shared.sandbox.check_len(n)?;
+    if n == 1 { return Ok(Value::Array(vec![Value::Number(a)])); }
+    let h: f64 = (b - a) / (n as f64 - 1.0);
+    Ok(Value::Array((0..n).map(|i| Value::Number(if i + 1 == n { b } else { a + i as f64 * h })).collect()))
+}
+
+fn flatten_into(items: &[Value], depth: usize, out: &mut Vec<Value>) {
+    for v in items {
+        match v {
+            Value::Array(inner) if depth > 0 => flatten_into(inner, depth - 1, out),
+            other => out.push(other.clone()),
+        }
+    }
+}
@@ -1,11 +1,17 @@
 // AST module for representing parsed expressions/statements
 
+pub use crate::modules::ImportKind;
+pub use crate::logging::Level;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum AstNode {
     Program(Vec<AstNode>),
     Empty,
     Number(f64),
+    Bool(bool),
     Str(String),
+    // "f(2) = {f(2)}": literal text and interpolated expressions, in order
+    Template(Vec<AstNode>),
     Constant(String),
     Variable(String),
     UnaryOp {
@@ -25,12 +31,49 @@ pub enum AstNode {
         name: String,
         params: Vec<String>,
         body: Box<AstNode>,
+        // @memo: results are remembered by argument values
+        memo: bool,
     },
     FunctionCall {
         name: String,
         args: Vec<AstNode>,
     },
+    // a call on a computed function, e.g. g.area(2)
+    CallExpr {
+        callee: Box<AstNode>,
+        args: Vec<AstNode>,
+    },
+    Lambda {
+        params: Vec<String>,
+        body: Box<AstNode>,
+    },
     Array(Vec<AstNode>),
+    // (a, b, c)
+    Tuple(Vec<AstNode>),
+    // {x: 1, y: 2}
+    Record(Vec<(String, AstNode)>),
+    // p.x
+    Field {
+        expr: Box<AstNode>,
+        name: String,
+    },
+    // (q, r) = divmod(7, 2)
+    Destructure {
+        names: Vec<String>,
+        expr: Box<AstNode>,
+    },
+    Set(Vec<AstNode>),
+    // {var in source | condition}
+    SetBuilder {
+        var: String,
+        source: Box<AstNode>,
+        condition: Box<AstNode>,
+    },
+    // a < b <= c: every operand is evaluated once, left to right, stopping at the first false link
+    Chain {
+        operands: Vec<AstNode>,
+        ops: Vec<BinaryOpKind>,
+    },
     Conditional {
         condition: Box<AstNode>,
         body: Box<AstNode>,
@@ -49,14 +92,41 @@ pub enum AstNode {
     Lim {
         var: String,
         to: Box<AstNode>,
+        side: LimitSide,
+        expr: Box<AstNode>,
+    },
+    Series {
+        var: String,
+        at: Box<AstNode>,
+        order: Option<Box<AstNode>>,
         expr: Box<AstNode>,
     },
-    Import(String),
+    Import {
+        path: String,
+        kind: ImportKind,
+    },
+    // format fixed 3, format snap 1e-9, format fraction off, ...
+    Format {
+        setting: String,
+        arg: Option<Box<AstNode>>,
+    },
     Print(Vec<AstNode>),
-    Log(Vec<AstNode>),
+    // log("WARN", "x is", x): a message at a level
+    Log {
+        level: Level,
+        args: Vec<AstNode>,
+    },
     Error(String),
 }
 
+// which side `lim` approaches its target from: x -> a, x -> a- or x -> a+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LimitSide {
+    Both,
+    Below,
+    Above,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum UnaryOpKind {
     Negate = 0,
@@ -79,4 +149,12 @@ pub enum BinaryOpKind {
     Gte = 9,
     Lte = 10,
     Mod = 11,
+    And = 12,
+    Or = 13,
+    Xor = 14,
+    In = 15,
+    Union = 16,
+    Intersect = 17,
+    Difference = 18,
+    Range = 19,
 }
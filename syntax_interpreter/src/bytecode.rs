@@ -0,0 +1,482 @@
+// Bytecode for function bodies: a compiler from the syntax tree to instructions over numbered
+// local slots, and a stack machine that runs them without building an environment for each call
+
+use std::cell::{Cell, OnceCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::ast::{AstNode, BinaryOpKind, UnaryOpKind};
+use crate::evaluator::{binary, call_builtin, call_function, call_value, compare, field, invoke, is_builtin, is_plain_builtin, truth, unary, Env, Function, Value};
+use crate::modules::ModuleScope;
+
+/// Whether function bodies run on the bytecode machine (the default) or walk their syntax trees
+/// as before. Both give the same results; the switch exists for comparing them.
+#[derive(Clone)]
+pub struct Machine(Rc<Cell<bool>>);
+
+impl Default for Machine {
+    fn default() -> Self {
+        Machine(Rc::new(Cell::new(true)))
+    }
+}
+
+impl Machine {
+    pub fn set_enabled(&self, on: bool) {
+        self.0.set(on);
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.0.get()
+    }
+}
+
+/// One instruction. Jumps hold the index of the instruction to continue at.
+#[derive(Debug, Clone, PartialEq)]
+enum Op {
+    // push a constant
+    Const(usize),
+    // push a parameter
+    Load(usize),
+    // push a name that is not local: a function, a built-in or an error
+    Global(usize),
+    Unary(UnaryOpKind),
+    Binary(BinaryOpKind),
+    // the left side of `or` (or of `and`): when it decides the result, push it and jump
+    ShortCircuit { or: bool, to: usize },
+    // the right side of `or` (or of `and`) as true or false
+    Truth { or: bool },
+    // {condition}: run the body only when the condition holds, else push nothing (unit) and jump
+    Guard(usize),
+    // one link of a < b <= c: keep the right operand while the links hold, else push false and jump
+    Link(BinaryOpKind, usize),
+    // a call whose callee is an error is not made; the error is its value
+    SkipError(usize),
+    Pop,
+    // call by name, after the local or captured value of that name if it holds a function
+    Call { name: usize, argc: usize, local: Local, plain: bool },
+    // call the value below the arguments
+    CallValue(usize),
+    Array(usize),
+    Tuple(usize),
+    Field(usize),
+}
+
+/// What a name called inside a function may refer to besides the functions in scope.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Local {
+    None,
+    Slot(usize),
+    Const(usize),
+}
+
+/// A compiled function body.
+#[derive(Debug)]
+pub struct Chunk {
+    code: Vec<Op>,
+    consts: Vec<Value>,
+    names: Vec<String>,
+    // the parameters, in order
+    slots: Vec<String>,
+    // the function's captured variables, for calls that need a full environment
+    captured: HashMap<String, Value>,
+}
+
+/// The bytecode of a function, compiled on its first call and shared by its copies.
+#[derive(Clone, Default)]
+pub struct CodeCache(Rc<OnceCell<Option<Rc<Chunk>>>>);
+
+impl std::fmt::Debug for CodeCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state: &str = match self.0.get() {
+            None => "not compiled",
+            Some(None) => "not compilable",
+            Some(Some(_)) => "compiled",
+        };
+        write!(f, "CodeCache({})", state)
+    }
+}
+
+/// The bytecode of `f`, or `None` when the machine is switched off or the body uses something
+/// only the tree-walking evaluator supports.
+pub fn compiled(f: &Function, vm: &Machine) -> Option<Rc<Chunk>> {
+    if !vm.enabled() { return None; }
+    f.code.0.get_or_init(|| compile(&f.params, &f.body, &f.captured).map(Rc::new)).clone()
+}
+
+fn compile(params: &[String], body: &AstNode, captured: &HashMap<String, Value>) -> Option<Chunk> {
+    let constants: HashMap<String, Value> = crate::evaluator::constants();
+    let mut c: Compiler = Compiler {
+        constants: &constants,
+        chunk: Chunk { code: Vec::new(), consts: Vec::new(), names: Vec::new(), slots: params.to_vec(), captured: captured.clone() },
+    };
+    c.expr(body)?;
+    Some(c.chunk)
+}
+
+struct Compiler<'a> {
+    // e, pi and the other names every environment starts with
+    constants: &'a HashMap<String, Value>,
+    chunk: Chunk,
+}
+
+impl Compiler<'_> {
+    /// Emit code leaving the value of `node` on the stack; `None` if it cannot be compiled.
+    fn expr(&mut self, node: &AstNode) -> Option<()> {
+        match node {
+            AstNode::Number(n) => self.push_const(Value::Number(*n)),
+            AstNode::Bool(b) => self.push_const(Value::Bool(*b)),
+            AstNode::Str(s) => self.push_const(Value::Str(s.clone())),
+            AstNode::Variable(name) => match self.local(name) {
+                Local::Slot(i) => { self.emit(Op::Load(i)); }
+                Local::Const(i) => { self.emit(Op::Const(i)); }
+                Local::None => {
+                    let i: usize = self.name(name);
+                    self.emit(Op::Global(i));
+                }
+            },
+            AstNode::Constant(name) => match self.local(name) {
+                // a constant shadowed by a parameter is left to the evaluator
+                Local::Slot(_) => return None,
+                Local::Const(i) => { self.emit(Op::Const(i)); }
+                Local::None => self.push_const(Value::Str(format!("ERROR: constant '{}' does not exist", name))),
+            },
+            AstNode::UnaryOp { op, expr } => {
+                self.expr(expr)?;
+                self.emit(Op::Unary(op.clone()));
+            }
+            AstNode::BinaryOp { op: op @ (BinaryOpKind::And | BinaryOpKind::Or), left, right } => {
+                let or: bool = *op == BinaryOpKind::Or;
+                self.expr(left)?;
+                let jump: usize = self.emit(Op::ShortCircuit { or, to: 0 });
+                self.expr(right)?;
+                self.emit(Op::Truth { or });
+                self.patch(jump);
+            }
+            AstNode::BinaryOp { op, left, right } => {
+                self.expr(left)?;
+                self.expr(right)?;
+                self.emit(Op::Binary(op.clone()));
+            }
+            AstNode::Chain { operands, ops } => {
+                self.expr(&operands[0])?;
+                let mut jumps: Vec<usize> = Vec::new();
+                for (op, operand) in ops.iter().zip(&operands[1..]) {
+                    self.expr(operand)?;
+                    jumps.push(self.emit(Op::Link(op.clone(), 0)));
+                }
+                self.emit(Op::Pop);
+                self.push_const(Value::Bool(true));
+                for j in jumps { self.patch(j); }
+            }
+            AstNode::Conditional { condition, body } => {
+                self.expr(condition)?;
+                let jump: usize = self.emit(Op::Guard(0));
+                self.expr(body)?;
+                self.patch(jump);
+            }
+            AstNode::FunctionCall { name, args } => {
+                for a in args { self.expr(a)?; }
+                let local: Local = self.local(name);
+                let name_index: usize = self.name(name);
+                self.emit(Op::Call { name: name_index, argc: args.len(), local, plain: is_plain_builtin(name) });
+            }
+            AstNode::CallExpr { callee, args } => {
+                self.expr(callee)?;
+                let jump: usize = self.emit(Op::SkipError(0));
+                for a in args { self.expr(a)?; }
+                self.emit(Op::CallValue(args.len()));
+                self.patch(jump);
+            }
+            AstNode::Array(items) | AstNode::Tuple(items) => {
+                for item in items { self.expr(item)?; }
+                self.emit(if matches!(node, AstNode::Array(_)) { Op::Array(items.len()) } else { Op::Tuple(items.len()) });
+            }
+            AstNode::Field { expr, name } => {
+                self.expr(expr)?;
+                let i: usize = self.name(name);
+                self.emit(Op::Field(i));
+            }
+            _ => return None,
+        }
+        Some(())
+    }
+
+    // where a variable of the function lives: a slot, a constant (captured values and e, pi, ...) or neither
+    fn local(&mut self, name: &str) -> Local {
+        if let Some(i) = self.chunk.slots.iter().rposition(|s| s == name) { return Local::Slot(i); }
+        match self.chunk.captured.get(name).or_else(|| self.constants.get(name)) {
+            Some(v) => Local::Const(self.constant(v.clone())),
+            None => Local::None,
+        }
+    }
+
+    fn emit(&mut self, op: Op) -> usize {
+        self.chunk.code.push(op);
+        self.chunk.code.len() - 1
+    }
+
+    fn push_const(&mut self, v: Value) {
+        let i: usize = self.constant(v);
+        self.emit(Op::Const(i));
+    }
+
+    fn constant(&mut self, v: Value) -> usize {
+        self.chunk.consts.push(v);
+        self.chunk.consts.len() - 1
+    }
+
+    fn name(&mut self, name: &str) -> usize {
+        match self.chunk.names.iter().position(|n| n == name) {
+            Some(i) => i,
+            None => {
+                self.chunk.names.push(name.to_string());
+                self.chunk.names.len() - 1
+            }
+        }
+    }
+
+    // point the jump at `at` to the next instruction
+    fn patch(&mut self, at: usize) {
+        let next: usize = self.chunk.code.len();
+        match &mut self.chunk.code[at] {
+            Op::ShortCircuit { to, .. } | Op::Guard(to) | Op::Link(_, to) | Op::SkipError(to) => *to = next,
+            _ => {}
+        }
+    }
+}
+
+/// Run `chunk` with its parameters bound to `args`, one for each. Functions it calls by name are
+/// looked up in `module` for a function defined in a module, else in `env`. Sharing `env`'s
+/// functions, output and limits is what lets a call skip building an environment of its own.
+pub fn run(chunk: &Chunk, module: Option<&ModuleScope>, args: &[Value], env: &mut Env) -> Value {
+    let mut frame: Frame = Frame { chunk, module, slots: args, stack: Vec::new() };
+    frame.run(env).unwrap_or_else(error)
+}
+
+// a compiled function ready to be called: its code and the module it was defined in
+type Callee = (Rc<Chunk>, Option<ModuleScope>);
+
+fn callee(f: &Function, argc: usize, vm: &Machine) -> Option<Callee> {
+    // a memoised function is called through its cache
+    if argc != f.params.len() || f.memo.is_some() { return None; }
+    compiled(f, vm).map(|code| (code, f.module.clone()))
+}
+
+// a function found by name: compiled, or a copy to evaluate by walking its body
+enum Target {
+    Compiled(Callee),
+    Walked(Box<Function>),
+}
+
+impl Target {
+    fn of(f: &Function, argc: usize, vm: &Machine) -> Target {
+        match callee(f, argc, vm) {
+            Some(code) => Target::Compiled(code),
+            None => Target::Walked(Box::new(f.clone())),
+        }
+    }
+}
+
+fn call_compiled((code, module): &Callee, args: &[Value], env: &mut Env) -> Value {
+    if let Err(msg) = env.shared.sandbox.enter() { return error(msg); }
+    let result: Value = run(code, module.as_ref(), args, env);
+    env.shared.sandbox.leave();
+    result
+}
+
+fn error(msg: String) -> Value {
+    Value::Str(format!("ERROR: {}", msg))
+}
+
+struct Frame<'a> {
+    chunk: &'a Chunk,
+    module: Option<&'a ModuleScope>,
+    slots: &'a [Value],
+    stack: Vec<Value>,
+}
+
+impl Frame<'_> {
+    // Err carries the message of an error that ends the call, such as an exceeded limit
+    fn run(&mut self, env: &mut Env) -> Result<Value, String> {
+        let code: &[Op] = &self.chunk.code;
+        let mut pc: usize = 0;
+        while pc < code.len() {
+            env.shared.sandbox.step()?;
+            match &code[pc] {
+                Op::Const(i) => self.stack.push(self.chunk.consts[*i].clone()),
+                Op::Load(i) => self.stack.push(self.slots[*i].clone()),
+                Op::Global(i) => {
+                    let v: Value = self.global(&self.chunk.names[*i], env);
+                    self.push_checked(v, env)?;
+                }
+                Op::Unary(op) => {
+                    let v: Value = self.pop();
+                    self.push_checked(unary(op, v), env)?;
+                }
+                Op::Binary(op) => {
+                    let r: Value = self.pop();
+                    let l: Value = self.pop();
+                    self.push_checked(binary(op, l, r, &env.shared.sandbox, env.shared.format.get()), env)?;
+                }
+                Op::ShortCircuit { or, to } => {
+                    let v: Value = match self.pop() {
+                        Value::Bool(b) if b == *or => Value::Bool(b),
+                        Value::Bool(_) => {
+                            pc += 1;
+                            continue;
+                        }
+                        other => error(truth(&other, &format!("left side of '{}'", if *or { "or" } else { "and" })).unwrap_err()),
+                    };
+                    self.stack.push(v);
+                    pc = *to;
+                    continue;
+                }
+                Op::Truth { or } => {
+                    let v: Value = match self.pop() {
+                        Value::Bool(b) => Value::Bool(b),
+                        other => error(truth(&other, &format!("right side of '{}'", if *or { "or" } else { "and" })).unwrap_err()),
+                    };
+                    self.stack.push(v);
+                }
+                Op::Guard(to) => {
+                    let v: Value = match truth(&self.pop(), "condition") {
+                        Ok(true) => {
+                            pc += 1;
+                            continue;
+                        }
+                        Ok(false) => Value::Unit,
+                        Err(msg) => error(msg),
+                    };
+                    self.stack.push(v);
+                    pc = *to;
+                    continue;
+                }
+                Op::Link(op, to) => {
+                    let next: Value = self.pop();
+                    let prev: Value = self.pop();
+                    match compare(op.clone(), prev, next.clone()) {
+                        Value::Bool(true) => self.stack.push(next),
+                        other => {
+                            self.stack.push(other);
+                            pc = *to;
+                            continue;
+                        }
+                    }
+                }
+                Op::SkipError(to) => {
+                    if matches!(self.stack.last(), Some(Value::Str(s)) if s.starts_with("ERROR:")) {
+                        pc = *to;
+                        continue;
+                    }
+                }
+                Op::Pop => {
+                    self.pop();
+                }
+                Op::Call { name, argc, local, plain } => {
+                    let args: Vec<Value> = self.stack.split_off(self.stack.len() - argc);
+                    let v: Value = self.call(&self.chunk.names[*name], *local, *plain, &args, env);
+                    self.push_checked(v, env)?;
+                }
+                Op::CallValue(argc) => {
+                    let args: Vec<Value> = self.stack.split_off(self.stack.len() - argc);
+                    let f: Value = self.pop();
+                    let v: Value = match &f {
+                        Value::Function(g) => self.call_function_value(g, &args, env),
+                        // a built-in is called by name, which depends on the variables in scope
+                        Value::Builtin(_) => self.in_scope(env, |env| call_value(&f, &args, env)),
+                        _ => call_value(&f, &args, env),
+                    };
+                    self.push_checked(v, env)?;
+                }
+                Op::Array(n) => {
+                    let items: Vec<Value> = self.stack.split_off(self.stack.len() - n);
+                    self.push_checked(Value::Array(items), env)?;
+                }
+                Op::Tuple(n) => {
+                    let items: Vec<Value> = self.stack.split_off(self.stack.len() - n);
+                    self.stack.push(Value::Tuple(items));
+                }
+                Op::Field(i) => {
+                    let v: Value = self.pop();
+                    self.push_checked(field(v, &self.chunk.names[*i]), env)?;
+                }
+            }
+            pc += 1;
+        }
+        Ok(self.pop())
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().unwrap_or(Value::Unit)
+    }
+
+    // push a value that may be an array over the size limit, or follow a limit exceeded while computing it
+    fn push_checked(&mut self, v: Value, env: &Env) -> Result<(), String> {
+        if let Value::Array(items) = &v { env.shared.sandbox.check_len(items.len())?; }
+        if let Some(msg) = env.shared.sandbox.exceeded() { return Err(msg); }
+        self.stack.push(v);
+        Ok(())
+    }
+
+    // a name that is not a variable of the function, looked up as the evaluator would
+    fn global(&self, name: &str, env: &mut Env) -> Value {
+        let f: Option<Function> = match self.module {
+            Some(scope) => scope.with(name, Function::clone),
+            None => env.funcs.get(name).cloned(),
+        };
+        if let Some(f) = f { return Value::Function(f); }
+        if is_builtin(name) || env.shared.natives.contains(name) { return Value::Builtin(name.to_string()); }
+        if let Some(f) = crate::modules::prelude(name, env) { return Value::Function(f); }
+        Value::Str(format!("ERROR: variable '{}' does not exist", name))
+    }
+
+    // a call by name: the functions in scope, then a variable holding a function, then the rest
+    fn call(&self, name: &str, local: Local, plain: bool, args: &[Value], env: &mut Env) -> Value {
+        let found: Option<Target> = match self.module {
+            Some(scope) => scope.with(name, |f| Target::of(f, args.len(), &env.shared.vm)),
+            None => env.funcs.get(name).map(|f| Target::of(f, args.len(), &env.shared.vm)),
+        };
+        match found {
+            Some(Target::Compiled(code)) => return call_compiled(&code, args, env),
+            Some(Target::Walked(f)) => return invoke(&f, args, env),
+            None => {}
+        }
+        let value: Option<&Value> = match local {
+            Local::Slot(i) => Some(&self.slots[i]),
+            Local::Const(i) => Some(&self.chunk.consts[i]),
+            Local::None => None,
+        };
+        match value {
+            Some(Value::Function(f)) => return self.call_function_value(f, args, env),
+            Some(p @ Value::Polynomial(_)) => return call_value(p, args, env),
+            Some(Value::Builtin(b)) if b != name => return self.in_scope(env, |env| call_function(b, args, env)),
+            _ => {}
+        }
+        if plain && !env.shared.natives.contains(name) { return call_builtin(name, args).unwrap_or(Value::Unit); }
+        self.in_scope(env, |env| call_function(name, args, env))
+    }
+
+    fn call_function_value(&self, f: &Function, args: &[Value], env: &mut Env) -> Value {
+        // a function from the script called inside a module function sees the module's functions
+        if self.module.is_some() && f.module.is_none() { return self.in_scope(env, |env| invoke(f, args, env)); }
+        match callee(f, args.len(), &env.shared.vm) {
+            Some(code) => call_compiled(&code, args, env),
+            None => invoke(f, args, env),
+        }
+    }
+
+    // Run `f` in the environment the tree-walking evaluator would have given this call: the
+    // function's variables and, in a module, the module's functions. Built-ins that look names up
+    // (such as f^-1) need it; calls between compiled functions do not.
+    fn in_scope<R>(&self, env: &mut Env, f: impl FnOnce(&mut Env) -> R) -> R {
+        let mut vars: HashMap<String, Value> = crate::evaluator::constants();
+        vars.extend(self.chunk.captured.iter().map(|(k, v)| (k.clone(), v.clone())));
+        for (name, v) in self.chunk.slots.iter().zip(self.slots) { vars.insert(name.clone(), v.clone()); }
+        let vars: HashMap<String, Value> = std::mem::replace(&mut env.vars, vars);
+        let funcs: Option<HashMap<String, Function>> = self.module.map(|scope| std::mem::replace(&mut env.funcs, scope.funcs()));
+        let result: R = f(env);
+        env.vars = vars;
+        if let Some(funcs) = funcs { env.funcs = funcs; }
+        result
+    }
+}
@@ -0,0 +1,286 @@
+// Derivatives of expressions and functions: d/dx, prime notation and the vector calculus built-ins
+
+use crate::ast::AstNode;
+use crate::evaluator::{call_scope, call_value, eval, Env, Value};
+use crate::symbolic;
+
+pub const NAMES: &[&str] = &["deriv", "grad", "jacobian", "hessian", "div", "curl"];
+
+/// Dispatch a vector calculus built-in. Returns `None` when `name` is not one of [`NAMES`].
+pub fn call(name: &str, args: &[Value], env: &mut Env) -> Option<Value> {
+    if !NAMES.contains(&name) { return None; }
+    Some(dispatch(name, args, env).unwrap_or_else(|msg| Value::Str(format!("ERROR: {}", msg))))
+}
+
+fn dispatch(name: &str, args: &[Value], env: &mut Env) -> Result<Value, String> {
+    if name == "deriv" {
+        // Numerical derivative: deriv(f, x [, h])
+        let (f, x, h) = match args {
+            [f, Value::Number(x)] => (f, *x, 1e-6),
+            [f, Value::Number(x), Value::Number(h)] => (f, *x, *h),
+            _ => return Err("deriv expects at least 2 arguments (function, x [, h])".to_string()),
+        };
+        if !matches!(f, Value::Function(_) | Value::Builtin(_)) {
+            return Err("deriv: first argument must be a function".to_string());
+        }
+        let d: f64 = central_difference(|t| number(&call_value(f, &[Value::Number(t)], env)), x, h);
+        return if d.is_finite() { Ok(Value::Number(d)) } else { Err("deriv: function did not return numbers".to_string()) };
+    }
+    let [f, point] = args else {
+        return Err(format!("{} expects (f, point)", name));
+    };
+    match name {
+        "grad" => {
+            let t: Target = Target::new(name, f, env)?;
+            let p: Vec<f64> = point_arg(name, point, t.params.len())?;
+            Ok(vector(t.gradient(name, &t.body, &p)?))
+        }
+        "hessian" => {
+            let t: Target = Target::new(name, f, env)?;
+            let p: Vec<f64> = point_arg(name, point, t.params.len())?;
+            let n: usize = p.len();
+            // only the upper triangle is computed; the matrix is symmetric by construction
+            let upper: Vec<Vec<f64>> = (0..n)
+                .map(|i| (i..n).map(|j| t.second_partial(name, i, j, &p)).collect::<Result<_, _>>())
+                .collect::<Result<_, _>>()?;
+            Ok(matrix((0..n).map(|i| (0..n).map(|j| if j >= i { upper[i][j - i] } else { upper[j][i - j] }).collect()).collect()))
+        }
+        "jacobian" => Ok(matrix(jacobian(name, f, point, env)?)),
+        "div" => {
+            let j: Vec<Vec<f64>> = jacobian(name, f, point, env)?;
+            if j.len() != j[0].len() {
+                return Err("div: the field must have as many components as variables".to_string());
+            }
+            Ok(Value::Number((0..j.len()).map(|i| j[i][i]).sum()))
+        }
+        "curl" => {
+            let j: Vec<Vec<f64>> = jacobian(name, f, point, env)?;
+            match (j.len(), j[0].len()) {
+                // scalar curl of a plane field
+                (2, 2) => Ok(Value::Number(j[1][0] - j[0][1])),
+                (3, 3) => Ok(vector(vec![j[2][1] - j[1][2], j[0][2] - j[2][0], j[1][0] - j[0][1]])),
+                _ => Err("curl: the field must be 2- or 3-dimensional".to_string()),
+            }
+        }
+        _ => Err(format!("function '{}' does not exist or is not implemented", name)),
+    }
+}
+
+/// `d^n/dx^n expr`, evaluated at the current value of `var` (0 if it is unset).
+pub fn derivative_expr(var: &str, order: usize, expr: &AstNode, env: &mut Env) -> Value {
+    let x0: f64 = match env.vars.get(var) { Some(Value::Number(v)) => *v, _ => 0.0 };
+    let at = |body: &AstNode, x: f64| -> f64 {
+        let mut local: Env = env.clone();
+        local.vars.insert(var.to_string(), Value::Number(x));
+        number(&eval(body, &mut local))
+    };
+    if let Some(d) = nth_derivative(expr, var, order, env) {
+        let v: f64 = at(&d, x0);
+        if v.is_finite() { return Value::Number(v); }
+    }
+    Value::Number(numeric_derivative(|x| at(expr, x), x0, order))
+}
+
+/// `f'(a)`, `f''(a)`, ... (`var` is `None`) or `d/dx f(a, b)`: the partial derivative of `f`
+/// with respect to its parameter named `var`, evaluated at the arguments.
+/// When `f` has no parameter of that name the call is differentiated as an expression in `var`.
+pub fn derivative_call(name: &str, args: &[AstNode], var: Option<&str>, order: usize, env: &mut Env) -> Value {
+    let call: AstNode = AstNode::FunctionCall { name: name.to_string(), args: args.to_vec() };
+    if order == 0 { return eval(&call, env); }
+    let f: Value = eval(&AstNode::Variable(name.to_string()), env);
+    let t: Target = match Target::new(name, &f, env) {
+        Ok(t) => t,
+        Err(msg) => return Value::Str(format!("ERROR: {}", msg)),
+    };
+    let index: usize = match var {
+        Some(v) => match t.params.iter().position(|p| p == v) {
+            Some(i) => i,
+            None => return derivative_expr(v, order, &call, env),
+        },
+        None if t.params.len() == 1 => 0,
+        None => return Value::Str(format!("ERROR: {}' is ambiguous for a function of several variables; use d/dx {}(...)", name, name)),
+    };
+    let argv: Vec<Value> = args.iter().map(|a| eval(a, env)).collect();
+    let Ok(point) = argv.iter().map(|v| match v { Value::Number(x) => Ok(*x), _ => Err(()) }).collect::<Result<Vec<f64>, _>>() else {
+        return Value::Str(format!("ERROR: derivative of '{}': arguments must be numbers", name));
+    };
+    if point.len() != t.params.len() {
+        return Value::Str(format!("ERROR: '{}' expects {} arguments", name, t.params.len()));
+    }
+    if let Some(d) = nth_derivative(&t.body, &t.params[index], order, &t.scope) {
+        let v: f64 = t.value_at(&d, &point);
+        if v.is_finite() { return Value::Number(v); }
+    }
+    Value::Number(numeric_derivative(|x| t.value_at(&t.body, &with(&point, index, x)), point[index], order))
+}
+
+fn nth_derivative(body: &AstNode, var: &str, order: usize, env: &Env) -> Option<AstNode> {
+    let mut d: AstNode = body.clone();
+    for _ in 0..order {
+        d = symbolic::differentiate(&d, var, env)?;
+    }
+    Some(d)
+}
+
+/// (f(x + h) - f(x - h)) / 2h, the first derivative behind `deriv` and the optimisers.
+pub fn central_difference(mut f: impl FnMut(f64) -> f64, x: f64, h: f64) -> f64 {
+    (f(x + h) - f(x - h)) / (2.0 * h)
+}
+
+/// Gradient of a function of several numbers by central differences, with steps scaled to each coordinate.
+pub fn numeric_gradient(mut f: impl FnMut(&[f64]) -> f64, x: &[f64]) -> Vec<f64> {
+    (0..x.len()).map(|i| central_difference(|t| f(&with(x, i, t)), x[i], f64::EPSILON.cbrt() * x[i].abs().max(1.0))).collect()
+}
+
+/// n-th derivative by the central difference sum_k (-1)^k C(n, k) f(x + (n/2 - k) h) / h^n.
+pub fn numeric_derivative(mut f: impl FnMut(f64) -> f64, x: f64, n: usize) -> f64 {
+    if n == 0 { return f(x); }
+    // balances truncation error (h^2) against rounding error (eps / h^n)
+    let h: f64 = f64::EPSILON.powf(1.0 / (n as f64 + 2.0)) * x.abs().max(1.0);
+    let mut sum: f64 = 0.0;
+    let mut binom: f64 = 1.0;
+    for k in 0..=n {
+        let sign: f64 = if k % 2 == 0 { 1.0 } else { -1.0 };
+        sum += sign * binom * f(x + (n as f64 / 2.0 - k as f64) * h);
+        binom = binom * (n - k) as f64 / (k + 1) as f64;
+    }
+    sum / h.powi(n as i32)
+}
+
+/// A function value opened up into parameter names, body and the scope its body runs in.
+struct Target {
+    params: Vec<String>,
+    body: AstNode,
+    scope: Env,
+}
+
+impl Target {
+    fn new(name: &str, f: &Value, env: &Env) -> Result<Self, String> {
+        match f {
+            Value::Function(func) => Ok(Target { params: func.params.clone(), body: func.body.clone(), scope: call_scope(func, env) }),
+            // built-ins are functions of a single argument
+            Value::Builtin(b) => Ok(Target {
+                params: vec!["x".to_string()],
+                body: AstNode::FunctionCall { name: b.clone(), args: vec![AstNode::Variable("x".to_string())] },
+                scope: env.clone(),
+            }),
+            _ => Err(format!("{}: '{}' is not a function", name, crate::evaluator::display_value(f))),
+        }
+    }
+
+    fn bind(&self, point: &[f64]) -> Env {
+        let mut local: Env = self.scope.clone();
+        for (p, x) in self.params.iter().zip(point) {
+            local.vars.insert(p.clone(), Value::Number(*x));
+        }
+        local
+    }
+
+    fn value_at(&self, body: &AstNode, point: &[f64]) -> f64 {
+        number(&eval(body, &mut self.bind(point)))
+    }
+
+    /// d(body)/d(params[i]) at point: symbolic when possible, else a central difference.
+    fn partial(&self, name: &str, body: &AstNode, i: usize, point: &[f64]) -> Result<f64, String> {
+        if let Some(d) = symbolic::differentiate(body, &self.params[i], &self.scope) {
+            let v: f64 = self.value_at(&d, point);
+            if v.is_finite() { return Ok(v); }
+        }
+        finite(name, numeric_derivative(|x| self.value_at(body, &with(point, i, x)), point[i], 1))
+    }
+
+    fn gradient(&self, name: &str, body: &AstNode, point: &[f64]) -> Result<Vec<f64>, String> {
+        (0..self.params.len()).map(|i| self.partial(name, body, i, point)).collect()
+    }
+
+    fn second_partial(&self, name: &str, i: usize, j: usize, point: &[f64]) -> Result<f64, String> {
+        let (pi, pj) = (&self.params[i], &self.params[j]);
+        if let Some(d) = symbolic::differentiate(&self.body, pi, &self.scope).and_then(|d| symbolic::differentiate(&d, pj, &self.scope)) {
+            let v: f64 = self.value_at(&d, point);
+            if v.is_finite() { return Ok(v); }
+        }
+        if i == j {
+            return finite(name, numeric_derivative(|x| self.value_at(&self.body, &with(point, i, x)), point[i], 2));
+        }
+        // mixed partial from the four-point stencil
+        let (hi, hj) = (f64::EPSILON.powf(0.25) * point[i].abs().max(1.0), f64::EPSILON.powf(0.25) * point[j].abs().max(1.0));
+        let f = |si: f64, sj: f64| self.value_at(&self.body, &with(&with(point, i, point[i] + si * hi), j, point[j] + sj * hj));
+        finite(name, (f(1.0, 1.0) - f(1.0, -1.0) - f(-1.0, 1.0) + f(-1.0, -1.0)) / (4.0 * hi * hj))
+    }
+}
+
+/// Rows of partial derivatives of each component of a vector field, given as a function
+/// returning an array or as an array of functions.
+fn jacobian(name: &str, field: &Value, point: &Value, env: &Env) -> Result<Vec<Vec<f64>>, String> {
+    if let Value::Array(fs) = field {
+        if fs.is_empty() { return Err(format!("{}: the field has no components", name)); }
+        return fs.iter().map(|f| {
+            let t: Target = Target::new(name, f, env)?;
+            let p: Vec<f64> = point_arg(name, point, t.params.len())?;
+            t.gradient(name, &t.body, &p)
+        }).collect();
+    }
+    let t: Target = Target::new(name, field, env)?;
+    let p: Vec<f64> = point_arg(name, point, t.params.len())?;
+    if let AstNode::Array(components) = &t.body {
+        if components.is_empty() { return Err(format!("{}: the field has no components", name)); }
+        return components.iter().map(|c| t.gradient(name, c, &p)).collect();
+    }
+    // the components are only known after evaluation: difference whole vectors
+    let eval_at = |q: &[f64]| -> Result<Vec<f64>, String> {
+        match eval(&t.body, &mut t.bind(q)) {
+            Value::Array(items) if !items.is_empty() => Ok(items.iter().map(number).collect()),
+            _ => Err(format!("{}: the field must return an array of numbers", name)),
+        }
+    };
+    let m: usize = eval_at(&p)?.len();
+    let mut rows: Vec<Vec<f64>> = vec![vec![0.0; p.len()]; m];
+    for j in 0..p.len() {
+        let h: f64 = f64::EPSILON.cbrt() * p[j].abs().max(1.0);
+        let (up, down) = (eval_at(&with(&p, j, p[j] + h))?, eval_at(&with(&p, j, p[j] - h))?);
+        if up.len() != m || down.len() != m {
+            return Err(format!("{}: the field changes length", name));
+        }
+        for i in 0..m {
+            rows[i][j] = finite(name, (up[i] - down[i]) / (2.0 * h))?;
+        }
+    }
+    Ok(rows)
+}
+
+fn point_arg(name: &str, point: &Value, dims: usize) -> Result<Vec<f64>, String> {
+    let coords: Vec<f64> = match point {
+        Value::Number(x) => vec![*x],
+        Value::Array(items) => items.iter().map(|v| match v {
+            Value::Number(x) => Ok(*x),
+            _ => Err(format!("{}: point coordinates must be numbers", name)),
+        }).collect::<Result<_, _>>()?,
+        _ => return Err(format!("{}: point must be a number or an array of numbers", name)),
+    };
+    if coords.len() != dims {
+        return Err(format!("{}: the function takes {} arguments but the point has {} coordinates", name, dims, coords.len()));
+    }
+    Ok(coords)
+}
+
+fn with(point: &[f64], i: usize, x: f64) -> Vec<f64> {
+    let mut p: Vec<f64> = point.to_vec();
+    p[i] = x;
+    p
+}
+
+fn number(v: &Value) -> f64 {
+    match v { Value::Number(x) => *x, _ => f64::NAN }
+}
+
+fn finite(name: &str, x: f64) -> Result<f64, String> {
+    if x.is_finite() { Ok(x) } else { Err(format!("{}: the function is not differentiable at this point", name)) }
+}
+
+fn vector(v: Vec<f64>) -> Value {
+    Value::Array(v.into_iter().map(Value::Number).collect())
+}
+
+fn matrix(rows: Vec<Vec<f64>>) -> Value {
+    Value::Array(rows.into_iter().map(vector).collect())
+}
@@ -0,0 +1,232 @@
+// Number formatting for displayed values: notation modes, snapping, fractions and aligned matrices
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use crate::evaluator::Value;
+
+/// How numbers are written out by `show` and `pretty`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NumberFormat {
+    pub mode: Mode,
+    // round to the shortest decimal within this relative distance, so 0.1 + 0.2 shows as 0.3
+    pub snap: Option<f64>,
+    // show nearly-rational results as p/q
+    pub fractions: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    // shortest round-trip form, scientific for very large and very small magnitudes
+    Auto,
+    // a fixed number of decimals
+    Fixed(usize),
+    // d.ddd e±x with the given number of significant digits
+    Scientific(usize),
+    // like scientific, with the exponent a multiple of 3
+    Engineering(usize),
+    // the given number of significant digits, trailing zeros dropped
+    Significant(usize),
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        NumberFormat { mode: Mode::Auto, snap: None, fractions: false }
+    }
+}
+
+// tolerance used by `format snap` without an argument
+const DEFAULT_SNAP: f64 = 1e-9;
+// largest denominator shown by `format fraction`
+const MAX_DENOMINATOR: u64 = 1000;
+
+/// The number format set by `format` or `--format`.
+#[derive(Clone, Default)]
+pub struct SharedFormat(Rc<Cell<NumberFormat>>);
+
+impl SharedFormat {
+    pub fn get(&self) -> NumberFormat {
+        self.0.get()
+    }
+
+    pub fn set(&self, format: NumberFormat) {
+        self.0.set(format);
+    }
+
+    /// Apply `format <name> [setting]`: a notation mode (auto, fixed, sci, eng, sig),
+    /// `snap [tolerance|off]`, `fraction [on|off]`, or `reset`.
+    pub fn apply(&self, name: &str, setting: Setting) -> Result<(), String> {
+        let mut f: NumberFormat = self.get();
+        let digits = |default: usize| -> Result<usize, String> {
+            match setting {
+                Setting::None => Ok(default),
+                Setting::Number(n) if n.fract() == 0.0 && (0.0..=17.0).contains(&n) => Ok(n as usize),
+                _ => Err(format!("format {}: the number of digits must be a whole number from 0 to 17", name)),
+            }
+        };
+        match name {
+            "auto" => f.mode = Mode::Auto,
+            "fixed" => f.mode = Mode::Fixed(digits(6)?),
+            "sci" => f.mode = Mode::Scientific(digits(6)?.max(1)),
+            "eng" => f.mode = Mode::Engineering(digits(6)?.max(1)),
+            "sig" => f.mode = Mode::Significant(digits(6)?.max(1)),
+            "snap" => f.snap = match setting {
+                Setting::None | Setting::Switch(true) => Some(DEFAULT_SNAP),
+                Setting::Switch(false) => None,
+                Setting::Number(t) if t > 0.0 => Some(t),
+                Setting::Number(_) => return Err("format snap: the tolerance must be positive".to_string()),
+            },
+            "fraction" => f.fractions = match setting {
+                Setting::None | Setting::Switch(true) => true,
+                Setting::Switch(false) => false,
+                Setting::Number(_) => return Err("format fraction expects on or off".to_string()),
+            },
+            "reset" => f = NumberFormat::default(),
+            _ => return Err(format!("format: unknown setting '{}' (expected auto, fixed, sci, eng, sig, snap, fraction or reset)", name)),
+        }
+        self.set(f);
+        Ok(())
+    }
+}
+
+/// The argument of a `format` directive or of the `--format` flag.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Setting {
+    None,
+    Number(f64),
+    Switch(bool),
+}
+
+/// A number written in the format `f`.
+pub fn number(x: f64, f: NumberFormat) -> String {
+    if !x.is_finite() { return x.to_string(); }
+    let x: f64 = match f.snap { Some(tol) => snap(x, tol), None => x };
+    if f.fractions {
+        if let Some((p, q)) = fraction(x) { return if q == 1 { p.to_string() } else { format!("{}/{}", p, q) }; }
+    }
+    match f.mode {
+        Mode::Auto => {
+            let a: f64 = x.abs();
+            if a != 0.0 && !(1e-6..1e16).contains(&a) { format!("{:e}", x) } else { x.to_string() }
+        }
+        Mode::Fixed(d) => format!("{:.*}", d, x),
+        Mode::Scientific(s) => format!("{:.*e}", s - 1, x),
+        Mode::Engineering(s) => engineering(x, s),
+        Mode::Significant(s) => significant(x, s),
+    }
+}
+
+/// A complex number a+bi in the format `f`, with the sign of the imaginary part folded in.
+pub fn complex(re: f64, im: f64, f: NumberFormat) -> String {
+    let sign: char = if im.is_sign_negative() && !im.is_nan() { '-' } else { '+' };
+    format!("{}{}{}i", number(re, f), sign, number(im.abs(), f))
+}
+
+/// `v` as text, with its numbers written in the format `f`; `display_value` uses the default format.
+pub fn show(v: &Value, f: NumberFormat) -> String {
+    let all = |items: &[Value]| -> String { items.iter().map(|x| show(x, f)).collect::<Vec<String>>().join(", ") };
+    match v {
+        Value::Number(n) => number(*n, f),
+        Value::Bool(b) => b.to_string(),
+        Value::Complex(c) => complex(c.re, c.im, f),
+        Value::Str(s) => s.clone(),
+        Value::Array(items) => format!("[{}]", all(items)),
+        Value::Tuple(items) => format!("({})", all(items)),
+        Value::Set(items) => format!("{{{}}}", all(items)),
+        Value::Record(fields) => {
+            let parts: Vec<String> = fields.iter().map(|(k, v)| format!("{}: {}", k, show(v, f))).collect();
+            format!("{{{}}}", parts.join(", "))
+        }
+        Value::Function(func) => format!("<function:{} params>", func.params.len()),
+        Value::Builtin(name) => format!("<builtin:{}>", name),
+        Value::Polynomial(p) => p.display(f),
+        Value::Unit => "()".to_string(),
+    }
+}
+
+/// Like `show`, but a matrix (an array of equally long rows of numbers) is laid out
+/// one row per line with its columns aligned.
+pub fn pretty(v: &Value, f: NumberFormat) -> String {
+    let Value::Array(rows) = v else { return show(v, f) };
+    let cells: Option<Vec<Vec<String>>> = rows.iter().map(|row| match row {
+        Value::Array(items) if items.iter().all(|x| matches!(x, Value::Number(_) | Value::Complex(_))) => {
+            Some(items.iter().map(|x| show(x, f)).collect())
+        }
+        _ => None,
+    }).collect();
+    let Some(cells) = cells.filter(|c| c.len() > 1 && c.iter().all(|r| !r.is_empty() && r.len() == c[0].len())) else {
+        return show(v, f);
+    };
+    let widths: Vec<usize> = (0..cells[0].len())
+        .map(|j| cells.iter().map(|r| r[j].chars().count()).max().unwrap_or(0))
+        .collect();
+    let lines: Vec<String> = cells.iter().map(|r| {
+        let padded: Vec<String> = r.iter().zip(&widths).map(|(c, w)| format!("{:>w$}", c, w = w)).collect();
+        format!("[{}]", padded.join(", "))
+    }).collect();
+    format!("[{}]", lines.join(",\n "))
+}
+
+/// The shortest decimal within `tol` (relative to the magnitude, at least absolute) of x.
+fn snap(x: f64, tol: f64) -> f64 {
+    let limit: f64 = tol * x.abs().max(1.0);
+    for d in 0..=15 {
+        let scale: f64 = 10f64.powi(d);
+        let r: f64 = (x * scale).round() / scale;
+        if (x - r).abs() <= limit { return if r == 0.0 { 0.0 } else { r }; }
+    }
+    x
+}
+
+/// p/q with the smallest denominator up to MAX_DENOMINATOR that matches x to about 1e-9.
+fn fraction(x: f64) -> Option<(i64, u64)> {
+    if x.abs() >= 1e12 { return None; }
+    // continued-fraction convergents
+    let (mut h0, mut h1): (f64, f64) = (0.0, 1.0);
+    let (mut k0, mut k1): (f64, f64) = (1.0, 0.0);
+    let mut r: f64 = x;
+    for _ in 0..32 {
+        let a: f64 = r.floor();
+        (h0, h1) = (h1, a * h1 + h0);
+        (k0, k1) = (k1, a * k1 + k0);
+        if k1 > MAX_DENOMINATOR as f64 { return None; }
+        if (x - h1 / k1).abs() <= 1e-9 * x.abs().max(1.0) { return Some((h1 as i64, k1 as u64)); }
+        let frac: f64 = r - a;
+        if frac == 0.0 { return None; }
+        r = 1.0 / frac;
+    }
+    None
+}
+
+fn engineering(x: f64, sig: usize) -> String {
+    if x == 0.0 { return format!("{:.*}e0", sig - 1, 0.0); }
+    let mut exp: i32 = (x.abs().log10().floor() as i32).div_euclid(3) * 3;
+    let mut mantissa: f64 = x / 10f64.powi(exp);
+    // digits left of the point (1 to 3) take their share of the significant digits
+    let int_digits: usize = mantissa.abs().log10().floor() as usize + 1;
+    let mut text: String = format!("{:.*}", sig.saturating_sub(int_digits), mantissa);
+    // rounding may carry into the next group, e.g. 999.96 -> 1000.0
+    if text.trim_start_matches('-').split('.').next().is_some_and(|i| i.len() > 3) {
+        exp += 3;
+        mantissa /= 1000.0;
+        text = format!("{:.*}", sig - 1, mantissa);
+    }
+    format!("{}e{}", text, exp)
+}
+
+fn significant(x: f64, sig: usize) -> String {
+    if x == 0.0 { return "0".to_string(); }
+    let exp: i32 = format!("{:.*e}", sig - 1, x).rsplit('e').next().and_then(|e| e.parse().ok()).unwrap_or(0);
+    // like %g: plain notation unless the exponent is far from the digits shown
+    if exp < -5 || exp >= sig as i32 {
+        let text: String = format!("{:.*e}", sig - 1, x);
+        let (mantissa, e) = text.split_once('e').unwrap_or((&text, "0"));
+        return format!("{}e{}", trim_zeros(mantissa), e);
+    }
+    let decimals: usize = (sig as i32 - 1 - exp).max(0) as usize;
+    trim_zeros(&format!("{:.*}", decimals, x))
+}
+
+fn trim_zeros(s: &str) -> String {
+    if s.contains('.') { s.trim_end_matches('0').trim_end_matches('.').to_string() } else { s.to_string() }
+}
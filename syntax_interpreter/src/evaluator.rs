@@ -1,15 +1,34 @@
 use std::collections::HashMap;
 use num_complex::Complex64;
 
-use crate::ast::{AstNode, UnaryOpKind, BinaryOpKind};
+use crate::ast::{AstNode, UnaryOpKind, BinaryOpKind, Level};
+use crate::poly::Polynomial;
+use crate::modules::{ModuleScope, SharedModules};
+use crate::random::SharedRng;
+use crate::interpreter::Natives;
+use crate::output::Output;
+use crate::logging::Logger;
+use crate::sandbox::Sandbox;
+use crate::bytecode::{CodeCache, Machine};
+use crate::memo::{Memo, MemoSettings};
+use crate::display::{NumberFormat, SharedFormat};
 
 #[derive(Debug, Clone)]
 pub enum Value {
     Number(f64),
+    Bool(bool),
     Complex(Complex64),
     Str(String),
     Array(Vec<Value>),
+    // fixed-size group such as (q, r) = divmod(7, 2)
+    Tuple(Vec<Value>),
+    // distinct elements; numbers kept in ascending order
+    Set(Vec<Value>),
+    // named fields in insertion order: {x: 1, y: 2}, or results such as those of statistical tests
+    Record(Vec<(String, Value)>),
+    Polynomial(Polynomial),
     Function(Function),
+    Builtin(String),
     Unit,
 }
 
@@ -17,6 +36,14 @@ pub enum Value {
 pub struct Function {
     pub params: Vec<String>,
     pub body: AstNode,
+    // free variables captured when an anonymous function is created
+    pub captured: HashMap<String, Value>,
+    // for a function defined in an imported module, the functions it can call
+    pub module: Option<ModuleScope>,
+    // its bytecode, compiled on the first call and shared by its copies
+    pub code: CodeCache,
+    // its remembered results, for a function marked @memo (or any function, with --memo)
+    pub memo: Option<Memo>,
 }
 
 
@@ -24,29 +51,78 @@ pub struct Function {
 pub struct Env {
     pub vars: HashMap<String, Value>,
     pub funcs: HashMap<String, Function>,
+    pub shared: Shared,
+}
+
+/// The state of one run of the interpreter, which the environments of its function calls and
+/// of the modules it imports use as well. Every field is a handle, so a clone refers to the
+/// same state: a seed, a limit or a `format` set anywhere applies to the whole run.
+#[derive(Clone, Default)]
+pub struct Shared {
+    pub rng: SharedRng,
+    // loaded modules and the chain of imports in progress
+    pub modules: SharedModules,
+    // functions registered from Rust through the embedding API
+    pub natives: Natives,
+    // where print, log and error reports go
+    pub output: Output,
+    // the level, format and file of log statements
+    pub logger: Logger,
+    // resource limits and what the run has used
+    pub sandbox: Sandbox,
+    // how print, log and string conversions write numbers
+    pub format: SharedFormat,
+    // whether function bodies run as bytecode
+    pub vm: Machine,
+    // --memo and the generation of remembered results
+    pub memo: MemoSettings,
 }
 
 impl Env {
-    pub fn new() -> Self { Self { vars: HashMap::new(), funcs: HashMap::new() } }
+    pub fn new() -> Self {
+        Self { vars: HashMap::new(), funcs: HashMap::new(), shared: Shared::default() }
+    }
     pub fn with_builtins() -> Self {
-        let mut env: Env = Self::new();
-    // constants
-    (&mut env.vars).insert("e".to_string(), Value::Number(std::f64::consts::E));
-    (&mut env.vars).insert("pi".to_string(), Value::Number(std::f64::consts::PI));
+        Self::with_shared(Shared::default())
+    }
+    /// An environment with the built-in constants that uses `shared` rather than state of its own,
+    /// as the environment of a function call or an imported module does.
+    pub fn with_shared(shared: Shared) -> Self {
+        Self { vars: constants(), funcs: HashMap::new(), shared }
+    }
+}
+
+/// The built-in constants, which every environment starts with.
+pub fn constants() -> HashMap<String, Value> {
+    let mut vars: HashMap<String, Value> = HashMap::new();
+    vars.insert("e".to_string(), Value::Number(std::f64::consts::E));
+    vars.insert("pi".to_string(), Value::Number(std::f64::consts::PI));
+    vars.insert("inf".to_string(), Value::Number(f64::INFINITY));
     // i: 1D imaginary unit (standard complex)
-    (&mut env.vars).insert("i".to_string(), Value::Complex(Complex64::new(0.0, 1.0)));
+    vars.insert("i".to_string(), Value::Complex(Complex64::new(0.0, 1.0)));
     // j: 2D imaginary unit (distinct from i)
-    (&mut env.vars).insert("j".to_string(), Value::Complex(Complex64::new(0.0, 2.0)));
+    vars.insert("j".to_string(), Value::Complex(Complex64::new(0.0, 2.0)));
     // k: 3D imaginary unit (distinct from i, j)
-    (&mut env.vars).insert("k".to_string(), Value::Complex(Complex64::new(0.0, 3.0)));
-    env
-    }
+    vars.insert("k".to_string(), Value::Complex(Complex64::new(0.0, 3.0)));
+    vars
 }
 
+/// Evaluate `ast` in `env`, counting it against the environment's limits.
 pub fn eval(ast: &AstNode, env: &mut Env) -> Value {
+    if let Err(msg) = env.shared.sandbox.step() { return Value::Str(format!("ERROR: {}", msg)); }
+    let v: Value = eval_node(ast, env);
+    if let Value::Array(items) | Value::Set(items) = &v {
+        if let Err(msg) = env.shared.sandbox.check_len(items.len()) { return Value::Str(format!("ERROR: {}", msg)); }
+    }
+    // a limit exceeded while evaluating the parts of this node makes its value meaningless
+    if let Some(msg) = env.shared.sandbox.exceeded() { return Value::Str(format!("ERROR: {}", msg)); }
+    v
+}
+
+fn eval_node(ast: &AstNode, env: &mut Env) -> Value {
     match ast {
         AstNode::Error(msg) => {
-            eprintln!("ERROR: {}", msg);
+            env.shared.output.error(&format!("ERROR: {}", msg));
             Value::Unit
         }
         AstNode::Program(stmts) => {
@@ -68,7 +144,18 @@ pub fn eval(ast: &AstNode, env: &mut Env) -> Value {
         }
         AstNode::Empty => Value::Unit,
         AstNode::Number(n) => Value::Number(*n),
-    AstNode::Str(s) => Value::Str(s.clone()),
+        AstNode::Bool(b) => Value::Bool(*b),
+        AstNode::Str(s) => Value::Str(s.clone()),
+        AstNode::Template(parts) => {
+            let mut out: String = String::new();
+            for part in parts {
+                match eval(part, env) {
+                    Value::Str(s) if s.starts_with("ERROR:") && !matches!(part, AstNode::Str(_)) => return Value::Str(s),
+                    v => out.push_str(&crate::display::show(&v, env.shared.format.get())),
+                }
+            }
+            Value::Str(out)
+        }
         AstNode::Constant(name) => {
             match (&(*env).vars).get(name) {
                 Some(val) => val.clone(),
@@ -76,10 +163,12 @@ pub fn eval(ast: &AstNode, env: &mut Env) -> Value {
             }
         },
         AstNode::Variable(name) => {
-            match (&(*env).vars).get(name) {
-                Some(val) => val.clone(),
-                None => Value::Str(format!("ERROR: variable '{}' does not exist", name)),
-            }
+            // variables shadow functions; a bare function name evaluates to the function itself
+            if let Some(val) = env.vars.get(name) { return val.clone(); }
+            if let Some(f) = env.funcs.get(name) { return Value::Function(f.clone()); }
+            if is_builtin(name) || env.shared.natives.contains(name) { return Value::Builtin(name.clone()); }
+            if let Some(f) = crate::modules::prelude(name, env) { return Value::Function(f); }
+            Value::Str(format!("ERROR: variable '{}' does not exist", name))
         },
         AstNode::Assignment { name, expr } => {
             let val: Value = eval(expr, env);
@@ -88,60 +177,103 @@ pub fn eval(ast: &AstNode, env: &mut Env) -> Value {
         }
         AstNode::UnaryOp { op, expr } => {
             let v: Value = eval(expr, env);
-            match op {
-                UnaryOpKind::Negate => num_neg(v),
-                UnaryOpKind::Not => bool_not(v),
-                UnaryOpKind::Abs => num_abs(v),
-                UnaryOpKind::Factorial => num_factorial(v),
-            }
+            unary(op, v)
         }
+        AstNode::BinaryOp { op: op @ (BinaryOpKind::And | BinaryOpKind::Or), left, right } => logical(op, left, right, env),
         AstNode::BinaryOp { op, left, right } => {
             let l: Value = eval(left, env);
             let r: Value = eval(right, env);
-            match op {
-                BinaryOpKind::Add => lift_bin(l, r, |x,y| x+y),
-                BinaryOpKind::Sub => lift_bin(l, r, |x,y| x-y),
-                BinaryOpKind::Mul => lift_mul(l, r),
-                BinaryOpKind::Div => lift_bin(l, r, |x,y| x/y),
-                BinaryOpKind::Pow => lift_bin(l, r, |x,y| x.powf(y)),
-                BinaryOpKind::Mod => lift_bin(l, r, |x,y| x % y),
-                BinaryOpKind::Eq | BinaryOpKind::NotEq | BinaryOpKind::Gt | BinaryOpKind::Lt | BinaryOpKind::Gte | BinaryOpKind::Lte =>
-                    compare(op.clone(), l, r),
+            binary(op, l, r, &env.shared.sandbox, env.shared.format.get())
+        }
+        AstNode::Chain { operands, ops } => {
+            // 0 < x <= 1 holds when every link does; stop at the first one that fails
+            let mut prev: Value = eval(&operands[0], env);
+            for (op, operand) in ops.iter().zip(&operands[1..]) {
+                let next: Value = eval(operand, env);
+                match compare(op.clone(), prev, next.clone()) {
+                    Value::Bool(true) => prev = next,
+                    other => return other,
+                }
             }
+            Value::Bool(true)
         }
-        AstNode::Lim { var, to, expr } => {
-            // Numeric limit: lim {var -> val} expr
-            // Use symmetric difference quotient with decreasing h
-            let var_name = var.clone();
-            let to_val = match eval(to, env) { Value::Number(n) => n, _ => return Value::Unit };
-            let mut last = std::f64::NAN;
-            let mut h = 1e-3;
-            let mut stable_count = 0;
-            for _ in 0..20 {
-                let mut env1 = env.clone();
-                env1.vars.insert(var_name.clone(), Value::Number(to_val + h));
-                let up = eval(expr, &mut env1);
-                let mut env2 = env.clone();
-                env2.vars.insert(var_name.clone(), Value::Number(to_val - h));
-                let down = eval(expr, &mut env2);
-                let v = match (up, down) {
-                    (Value::Number(a), Value::Number(b)) => 0.5 * (a + b),
-                    _ => return Value::Unit,
-                };
-                if (v - last).abs() < 1e-8 {
-                    stable_count += 1;
-                    if stable_count > 2 { return Value::Number(v); }
-                } else {
-                    stable_count = 0;
+        AstNode::Lim { var, to, side, expr } => crate::limits::limit(var, to, *side, expr, env),
+        AstNode::Series { var, at, order, expr } => crate::series::expand(var, at, order.as_deref(), expr, env),
+        AstNode::Set(items) => {
+            let values: Vec<Value> = items.iter().map(|e| eval(e, env)).collect();
+            // a set of an error would read as a value; the error is passed on instead
+            match values.iter().find(|v| is_error(v)) {
+                Some(err) => err.clone(),
+                None => crate::sets::from_values(values),
+            }
+        }
+        AstNode::SetBuilder { var, source, condition } => {
+            let items: Vec<Value> = match eval(source, env) {
+                Value::Set(items) | Value::Array(items) => items,
+                other => return Value::Str(format!("ERROR: set-builder expects a set, an array or a range after 'in', got {}", display_value(&other))),
+            };
+            // bind the variable to each element in turn, then put back whatever it shadowed
+            let shadowed: Option<Value> = env.vars.get(var).cloned();
+            let mut kept: Vec<Value> = Vec::new();
+            for item in items {
+                env.vars.insert(var.clone(), item.clone());
+                match truth(&eval(condition, env), "set-builder condition") {
+                    Ok(true) => kept.push(item),
+                    Ok(false) => {}
+                    Err(msg) => {
+                        kept.clear();
+                        kept.push(Value::Str(format!("ERROR: {}", msg)));
+                        break;
+                    }
                 }
-                last = v;
-                h *= 0.5;
             }
-            Value::Number(last)
+            match shadowed {
+                Some(v) => env.vars.insert(var.clone(), v),
+                None => env.vars.remove(var),
+            };
+            match kept.as_slice() {
+                [Value::Str(msg)] if msg.starts_with("ERROR: ") => kept.remove(0),
+                _ => crate::sets::from_values(kept),
+            }
         }
         AstNode::Array(items) => Value::Array((&**items).iter().map(|e: &AstNode| -> Value { eval(e, env) }).collect()),
-        AstNode::FunctionDef { name, params, body } => {
-            let f: Function = Function { params: params.clone(), body: (*body.clone()) };
+        AstNode::Tuple(items) => Value::Tuple(items.iter().map(|e| eval(e, env)).collect()),
+        AstNode::Record(fields) => {
+            let mut out: Vec<(String, Value)> = Vec::with_capacity(fields.len());
+            for (name, expr) in fields {
+                if out.iter().any(|(k, _)| k == name) {
+                    return Value::Str(format!("ERROR: field '{}' appears twice in the record", name));
+                }
+                out.push((name.clone(), eval(expr, env)));
+            }
+            Value::Record(out)
+        }
+        AstNode::Field { expr, name } => field(eval(expr, env), name),
+        AstNode::Destructure { names, expr } => {
+            let val: Value = eval(expr, env);
+            let items: &[Value] = match &val {
+                Value::Tuple(items) | Value::Array(items) => items,
+                Value::Str(s) if s.starts_with("ERROR:") => return val,
+                other => return Value::Str(format!("ERROR: cannot unpack {} into {} names", display_value(other), names.len())),
+            };
+            if items.len() != names.len() {
+                return Value::Str(format!("ERROR: cannot unpack {} values into {} names", items.len(), names.len()));
+            }
+            for (name, item) in names.iter().zip(items) {
+                env.vars.insert(name.clone(), item.clone());
+            }
+            val
+        }
+        AstNode::Lambda { params, body } => {
+            let mut captured: HashMap<String, Value> = HashMap::new();
+            collect_free_vars(body, params, env, &mut captured);
+            Value::Function(Function { params: params.clone(), body: (**body).clone(), captured, module: None, code: CodeCache::default(), memo: None })
+        }
+        AstNode::FunctionDef { name, params, body, memo } => {
+            let memo: Option<Memo> = (*memo || env.shared.memo.auto()).then(|| Memo::new(name, *memo, &env.shared.memo));
+            let f: Function = Function { params: params.clone(), body: (*body.clone()), captured: HashMap::new(), module: None, code: CodeCache::default(), memo };
+            // results remembered before may have used a function this one replaces
+            env.shared.memo.invalidate();
             (&mut (*env).funcs).insert(name.clone(), (&f).clone());
             Value::Function(f)
         }
@@ -149,151 +281,384 @@ pub fn eval(ast: &AstNode, env: &mut Env) -> Value {
             let argv: Vec<Value> = (&**args).iter().map(|a: &AstNode| -> Value { eval(a, env) }).collect();
             call_function(&**name, &**&argv, env)
         }
-        AstNode::Import(path) => {
-            // simple import: read file as lines and evaluate each line in current env
-            if let Ok(content) = std::fs::read_to_string(path) {
-                for line in (&*content).lines() {
-                    let tokens: Vec<crate::lexer::Token> = crate::lexer::tokenize(line);
-                    let ast: AstNode = crate::parser::parse(&**&tokens);
-                    let _ = eval(&ast, env);
-                }
+        AstNode::Format { setting, arg } => {
+            let arg: crate::display::Setting = match arg.as_deref() {
+                None => crate::display::Setting::None,
+                Some(AstNode::Bool(b)) => crate::display::Setting::Switch(*b),
+                Some(e) => match eval(e, env) {
+                    Value::Number(n) => crate::display::Setting::Number(n),
+                    other => return Value::Str(format!("ERROR: format {} expects a number, got {}", setting, display_value(&other))),
+                },
+            };
+            match env.shared.format.apply(setting, arg) {
+                Ok(()) => Value::Unit,
+                Err(msg) => Value::Str(format!("ERROR: {}", msg)),
             }
-            Value::Unit
+        }
+        AstNode::Import { path, kind } => match crate::modules::import(path, kind, env).inspect(|()| env.shared.memo.invalidate()) {
+            Ok(()) => Value::Unit,
+            Err(msg) => {
+                env.shared.output.error(&format!("ERROR: {}", msg));
+                Value::Str(format!("ERROR: {}", msg))
+            }
+        },
+        AstNode::CallExpr { callee, args } => {
+            let f: Value = eval(callee, env);
+            if matches!(&f, Value::Str(s) if s.starts_with("ERROR:")) { return f; }
+            let args: Vec<Value> = args.iter().map(|a| eval(a, env)).collect();
+            call_value(&f, &args, env)
         }
         AstNode::Print(args) => {
             let vals: Vec<Value> = (&**args).iter().map(|a: &AstNode| -> Value { eval(a, env) }).collect();
-            let out: String = (&*(&*vals).iter().map(|v: &Value| -> String { display_value(v) }).collect::<Vec<_>>()).join(" ");
-            println!("{}", out);
+            let out: String = vals.iter().map(|v| crate::display::pretty(v, env.shared.format.get())).collect::<Vec<_>>().join(" ");
+            // a run stopped by a limit reports that once, instead of printing it
+            if env.shared.sandbox.exceeded().is_none() { env.shared.output.print(&out); }
             Value::Unit
         }
-        AstNode::Log(args) => {
+        AstNode::Log { level, args } => {
             let vals: Vec<Value> = (&**args).iter().map(|a: &AstNode| -> Value { eval(a, env) }).collect();
-            let out: String = (&*(&*vals).iter().map(|v: &Value| -> String { display_value(v) }).collect::<Vec<_>>()).join(" ");
-            eprintln!("{}", out);
-            Value::Unit
-        }
-        AstNode::DerivativeCall { name, args, var: _, order } => {
-            // numeric derivative of single-arg function for now
-            let n = *order;
-            if n == 0 { return eval(&AstNode::FunctionCall { name: name.clone(), args: args.clone() }, env); }
-            if args.len() != 1 { return Value::Unit; }
-            let x: f64 = match eval(&args[0], env) { Value::Number(v) => v, _ => return Value::Unit };
-            let h: f64 = 1e-5;
-            let func_name: String = name.clone();
-            let mut df = |xx: f64| -> f64 {
-                let v: Value = call_function(&**&func_name, &[Value::Number(xx)], env);
-                match v { Value::Number(n) => n, _ => f64::NAN }
-            };
-            let mut f1 = |xx: f64| (df(xx + h) - df(xx - h)) / (2.0 * h);
-            if n == 1 { return Value::Number((&mut f1)(x)); }
-            // second derivative
-            let mut f2 = |xx: f64| (df(xx + h) - 2.0*df(xx) + df(xx - h)) / (h*h);
-            if n == 2 { return Value::Number((&mut f2)(x)); }
-            // higher order not implemented
+            let out: String = vals.iter().map(|v| crate::display::pretty(v, env.shared.format.get())).collect::<Vec<_>>().join(" ");
+            env.shared.logger.log(*level, &out, &env.shared.output, env.shared.sandbox.allows_clock());
             Value::Unit
         }
+        AstNode::DerivativeCall { name, args, var, order } => crate::calculus::derivative_call(name, args, var.as_deref(), *order, env),
         AstNode::Conditional { condition, body } => {
-            let c: Value = eval(condition, env);
-            if is_true(&c) { eval(body, env) } else { Value::Unit }
-        }
-        AstNode::DerivativeExpr { var, order, expr } => {
-            // numeric derivative of expression w.r.t variable name
-            let var_name: String = var.clone();
-            let n = *order;
-            if n == 0 { return eval(expr, env); }
-            let x0: f64 = match (&(*env).vars).get(&var_name).cloned().unwrap_or(Value::Number(0.0)) { Value::Number(v) => v, _ => 0.0 };
-            let h: f64 = 1e-5;
-            let f = |xx: f64, env: &mut Env| -> f64 {
-                (&mut (*env).vars).insert((&var_name).clone(), Value::Number(xx));
-                match eval(expr, env) { Value::Number(v) => v, _ => f64::NAN }
-            };
-            if n == 1 { return Value::Number(((&f)(x0 + h, env) - (&f)(x0 - h, env)) / (2.0*h)); }
-            if n == 2 { return Value::Number(((&f)(x0 + h, env) - 2.0*(&f)(x0, env) + (&f)(x0 - h, env)) / (h*h)); }
-            // higher order: repeat first derivative n times
-            let mut res: f64 = ((&f)(x0 + h, env) - (&f)(x0 - h, env)) / (2.0*h);
-            for _ in 2..=n {
-                let g = |xx: f64, env: &mut Env| (f(xx + h, env) - f(xx - h, env)) / (2.0*h);
-                res = (&g)(x0, env);
+            match truth(&eval(condition, env), "condition") {
+                Ok(true) => eval(body, env),
+                Ok(false) => Value::Unit,
+                Err(msg) => Value::Str(format!("ERROR: {}", msg)),
             }
-            Value::Number(res)
         }
+        AstNode::DerivativeExpr { var, order, expr } => crate::calculus::derivative_expr(var, *order, expr, env),
     }
 }
 
-fn is_true(v: &Value) -> bool {
+/// The value of a condition. Only `true` and `false` are accepted; an error already raised while
+/// computing `v` is passed on as it is.
+pub(crate) fn truth(v: &Value, what: &str) -> Result<bool, String> {
     match v {
-        Value::Number(n) => *n != 0.0,
-        Value::Complex(c) => (*c).norm() != 0.0,
-        Value::Array(a) => !a.is_empty(),
-        Value::Str(s) => !s.is_empty(),
-        Value::Function(_) => true,
-        Value::Unit => false,
+        Value::Bool(b) => Ok(*b),
+        Value::Str(s) if s.starts_with("ERROR: ") => Err(s["ERROR: ".len()..].to_string()),
+        other => Err(format!("{} must be true or false, got {}", what, display_value(other))),
+    }
+}
+
+/// `and`/`or` (also `&&`/`||`): the right side is only evaluated when it decides the result.
+fn logical(op: &BinaryOpKind, left: &AstNode, right: &AstNode, env: &mut Env) -> Value {
+    let name: &str = if *op == BinaryOpKind::And { "and" } else { "or" };
+    let l: bool = match truth(&eval(left, env), &format!("left side of '{}'", name)) {
+        Ok(b) => b,
+        Err(msg) => return Value::Str(format!("ERROR: {}", msg)),
+    };
+    if l == (*op == BinaryOpKind::Or) { return Value::Bool(l); }
+    match truth(&eval(right, env), &format!("right side of '{}'", name)) {
+        Ok(b) => Value::Bool(b),
+        Err(msg) => Value::Str(format!("ERROR: {}", msg)),
+    }
+}
+
+/// The value of a unary operator applied to `v`.
+pub(crate) fn unary(op: &UnaryOpKind, v: Value) -> Value {
+    match op {
+        UnaryOpKind::Negate => num_neg(v),
+        UnaryOpKind::Not => bool_not(v),
+        UnaryOpKind::Abs => num_abs(v),
+        UnaryOpKind::Factorial => num_factorial(v),
+    }
+}
+
+/// The value of a binary operator other than `and`/`or` applied to evaluated operands.
+pub(crate) fn binary(op: &BinaryOpKind, l: Value, r: Value, sandbox: &Sandbox, format: NumberFormat) -> Value {
+    // an error in either operand is passed on as it is
+    if is_error(&l) { return l; }
+    if is_error(&r) { return r; }
+    match op {
+        // operators that do not depend on the kind of operand
+        BinaryOpKind::Xor => return match (truth(&l, "left side of 'xor'"), truth(&r, "right side of 'xor'")) {
+            (Ok(a), Ok(b)) => Value::Bool(a != b),
+            (Err(msg), _) | (_, Err(msg)) => Value::Str(format!("ERROR: {}", msg)),
+        },
+        BinaryOpKind::In => return match crate::sets::contains(&r, &l) {
+            Some(found) => Value::Bool(found),
+            None => Value::Str(format!("ERROR: 'in' expects a set or an array on the right, got {}", display_value(&r))),
+        },
+        BinaryOpKind::Range => return range(l, r, sandbox),
+        BinaryOpKind::Add if matches!(l, Value::Str(_)) || matches!(r, Value::Str(_)) => return crate::strings::concat(l, r, format),
+        BinaryOpKind::Union | BinaryOpKind::Intersect | BinaryOpKind::Difference => return crate::sets::binary(op, l, r),
+        _ => {}
+    }
+    if matches!(op, BinaryOpKind::Add | BinaryOpKind::Sub | BinaryOpKind::Mul | BinaryOpKind::Div | BinaryOpKind::Pow | BinaryOpKind::Mod) {
+        if let Some(b) = [&l, &r].into_iter().find(|v| has_bool(v)) {
+            return Value::Str(format!("ERROR: cannot use {} in arithmetic", display_value(b)));
+        }
+    }
+    if matches!(l, Value::Polynomial(_)) || matches!(r, Value::Polynomial(_)) {
+        return crate::poly::binary(op, l, r, sandbox);
+    }
+    if matches!(l, Value::Set(_)) || matches!(r, Value::Set(_)) {
+        return crate::sets::binary(op, l, r);
+    }
+    if has_complex(&l) || has_complex(&r) {
+        return complex_bin(op, l, r);
+    }
+    match op {
+        BinaryOpKind::Add => lift_bin(l, r, |x,y| x+y),
+        BinaryOpKind::Sub => lift_bin(l, r, |x,y| x-y),
+        BinaryOpKind::Mul => lift_mul(l, r),
+        BinaryOpKind::Div => lift_bin(l, r, |x,y| x/y),
+        BinaryOpKind::Pow => lift_bin(l, r, |x,y| x.powf(y)),
+        BinaryOpKind::Mod => lift_bin(l, r, |x,y| x % y),
+        BinaryOpKind::Eq | BinaryOpKind::NotEq | BinaryOpKind::Gt | BinaryOpKind::Lt | BinaryOpKind::Gte | BinaryOpKind::Lte =>
+            compare(op.clone(), l, r),
+        // handled before looking at the operands
+        BinaryOpKind::And | BinaryOpKind::Or | BinaryOpKind::Xor | BinaryOpKind::In | BinaryOpKind::Range
+        | BinaryOpKind::Union | BinaryOpKind::Intersect | BinaryOpKind::Difference => Value::Unit,
     }
 }
 
-fn call_function(name: &str, args: &[Value], env: &mut Env) -> Value {
+pub(crate) fn field(v: Value, name: &str) -> Value {
+    match v {
+        Value::Record(fields) => match fields.into_iter().find(|(k, _)| k == name) {
+            Some((_, val)) => val,
+            None => Value::Str(format!("ERROR: record has no field '{}'", name)),
+        },
+        Value::Str(s) if s.starts_with("ERROR:") => Value::Str(s),
+        other => Value::Str(format!("ERROR: cannot read field '{}' of {}", name, display_value(&other))),
+    }
+}
+
+/// Structural equality used by built-ins that compare values (e.g. `unique`).
+pub(crate) fn values_equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x == y,
+        (Value::Bool(x), Value::Bool(y)) => x == y,
+        (Value::Complex(x), Value::Complex(y)) => x == y,
+        (Value::Number(x), Value::Complex(y)) | (Value::Complex(y), Value::Number(x)) => y.im == 0.0 && y.re == *x,
+        (Value::Str(x), Value::Str(y)) => x == y,
+        (Value::Array(x), Value::Array(y)) | (Value::Tuple(x), Value::Tuple(y)) => x.len() == y.len() && x.iter().zip(y.iter()).all(|(u, v)| values_equal(u, v)),
+        // sets are equal when they have the same elements, in any order
+        (Value::Set(x), Value::Set(y)) => x.len() == y.len() && x.iter().all(|u| y.iter().any(|v| values_equal(u, v))),
+        (Value::Record(x), Value::Record(y)) => x.len() == y.len() && x.iter().zip(y.iter()).all(|((ka, va), (kb, vb))| ka == kb && values_equal(va, vb)),
+        (Value::Builtin(x), Value::Builtin(y)) => x == y,
+        (Value::Polynomial(x), Value::Polynomial(y)) => x == y,
+        (Value::Unit, Value::Unit) => true,
+        _ => false,
+    }
+}
+
+fn collect_free_vars(node: &AstNode, bound: &[String], env: &Env, out: &mut HashMap<String, Value>) {
+    match node {
+        AstNode::Variable(name) if !bound.contains(name) && !out.contains_key(name) => {
+            if let Some(v) = env.vars.get(name) { out.insert(name.clone(), v.clone()); }
+        }
+        AstNode::UnaryOp { expr, .. } => collect_free_vars(expr, bound, env, out),
+        AstNode::BinaryOp { left, right, .. } => {
+            collect_free_vars(left, bound, env, out);
+            collect_free_vars(right, bound, env, out);
+        }
+        AstNode::FunctionCall { args, .. } | AstNode::DerivativeCall { args, .. } | AstNode::Array(args) | AstNode::Set(args)
+        | AstNode::Template(args)
+        | AstNode::Chain { operands: args, .. } | AstNode::Tuple(args) => {
+            for a in args { collect_free_vars(a, bound, env, out); }
+        }
+        AstNode::Lambda { params, body } => {
+            let mut inner: Vec<String> = bound.to_vec();
+            inner.extend(params.iter().cloned());
+            collect_free_vars(body, &inner, env, out);
+        }
+        AstNode::Record(fields) => {
+            for (_, e) in fields { collect_free_vars(e, bound, env, out); }
+        }
+        AstNode::Field { expr, .. } => collect_free_vars(expr, bound, env, out),
+        AstNode::CallExpr { callee, args } => {
+            collect_free_vars(callee, bound, env, out);
+            for a in args { collect_free_vars(a, bound, env, out); }
+        }
+        AstNode::Conditional { condition, body } => {
+            collect_free_vars(condition, bound, env, out);
+            collect_free_vars(body, bound, env, out);
+        }
+        AstNode::DerivativeExpr { var, expr, .. } | AstNode::Lim { var, expr, .. } => {
+            let mut inner: Vec<String> = bound.to_vec();
+            inner.push(var.clone());
+            collect_free_vars(expr, &inner, env, out);
+            if let AstNode::Lim { to, .. } = node { collect_free_vars(to, bound, env, out); }
+        }
+        AstNode::SetBuilder { var, source, condition } => {
+            let mut inner: Vec<String> = bound.to_vec();
+            inner.push(var.clone());
+            collect_free_vars(condition, &inner, env, out);
+            collect_free_vars(source, bound, env, out);
+        }
+        AstNode::Series { var, at, order, expr } => {
+            let mut inner: Vec<String> = bound.to_vec();
+            inner.push(var.clone());
+            collect_free_vars(expr, &inner, env, out);
+            collect_free_vars(at, bound, env, out);
+            if let Some(o) = order { collect_free_vars(o, bound, env, out); }
+        }
+        _ => {}
+    }
+}
+
+pub(crate) fn call_function(name: &str, args: &[Value], env: &mut Env) -> Value {
     // user-defined first
     // fn to_hex(s: &str) -> String {
     //     s.as_bytes().iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(" ")
     // }
     // println!("DEBUG: Looking up function name: '{}' [{}], registered: {:?}, env ptr: {:p}", name, to_hex(name), env.funcs.keys().map(|k| format!("{} [{}]", k, to_hex(k))).collect::<Vec<_>>(), env);
     if let Some(f) = (&(*env).funcs).get(name).cloned() {
-        let mut local: Env = Env::with_builtins();
-        // Inherit user-defined functions from parent environment
-        local.funcs = env.funcs.clone();
-        for (i, p) in (&*f.params).iter().enumerate() {
-            if let Some(v) = args.get(i) { (&mut local.vars).insert(p.clone(), v.clone()); }
+        return invoke(&f, args, env);
+    }
+    // variables holding functions, e.g. square = (x) => x^2
+    match env.vars.get(name).cloned() {
+        Some(Value::Function(f)) => return invoke(&f, args, env),
+        Some(Value::Polynomial(p)) => return call_value(&Value::Polynomial(p), args, env),
+        Some(Value::Builtin(b)) if b != name => return call_function(&b, args, env),
+        _ => {}
+    }
+    // functions registered by an embedding program
+    if let Some(native) = env.shared.natives.get(name) { return native.call(name, args); }
+    if name == "int" { return integrate(args, env); }
+    if name == "print" {
+        let parts: Vec<String> = args.iter().map(|v| crate::display::pretty(v, env.shared.format.get())).collect();
+        if env.shared.sandbox.exceeded().is_none() { env.shared.output.print(&parts.join(" ")); }
+        return Value::Unit;
+    }
+    // log("WARN", ...) in an expression logs as the statement does; log(100) is the logarithm
+    if name == "log" {
+        if let Some(Value::Str(first)) = args.first().filter(|v| !matches!(v, Value::Str(s) if s.starts_with("ERROR:"))) {
+            let level: Option<Level> = if args.len() > 1 { Level::parse(first) } else { None };
+            let rest: &[Value] = if level.is_some() { &args[1..] } else { args };
+            let out: String = rest.iter().map(|v| crate::display::pretty(v, env.shared.format.get())).collect::<Vec<_>>().join(" ");
+            env.shared.logger.log(level.unwrap_or(Level::Info), &out, &env.shared.output, env.shared.sandbox.allows_clock());
+            return Value::Unit;
         }
-        return eval(&f.body, &mut local);
-    } else {
     }
+    // the standard library, for names not implemented in Rust
+    if !is_builtin(name) {
+        if let Some(f) = crate::modules::prelude(name, env) { return invoke(&f, args, env); }
+    }
+    // built-ins that need to call back into the evaluator
+    if let Some(b) = crate::inverse::call(name, args, env) { return b; }
+    if let Some(b) = crate::arrays::call(name, args, env) { return b; }
+    if let Some(b) = crate::stats::call(name, args, env) { return b; }
+    if let Some(b) = crate::random::call(name, args, env) { return b; }
+    if let Some(b) = crate::poly::call(name, args, env) { return b; }
+    if let Some(b) = crate::series::call(name, args, env) { return b; }
+    if let Some(b) = crate::calculus::call(name, args, env) { return b; }
+    if let Some(b) = crate::optimize::call(name, args, env) { return b; }
+    if let Some(b) = crate::signal::call(name, args, env) { return b; }
+    if let Some(b) = crate::sets::call(name, args, env) { return b; }
+    if let Some(b) = crate::strings::call(name, args, env) { return b; }
     // built-ins
     if let Some(b) = call_builtin(name, args) { return b; }
     Value::Unit
 }
 
-fn call_builtin(name: &str, args: &[Value]) -> Option<Value> {
+/// int(f, a, b, [n]): numeric integration of f(x) from a to b, n intervals (default 1000).
+fn integrate(args: &[Value], env: &mut Env) -> Value {
+    if args.len() < 3 {
+        return Value::Str("ERROR: int expects at least 3 arguments (function, a, b, [n])".to_string());
+    }
+    let f: &Value = &args[0];
+    let (Value::Number(a), Value::Number(b)) = (&args[1], &args[2]) else { return Value::Unit };
+    let n: usize = match args.get(3) { Some(Value::Number(n)) => *n as usize, _ => 1000 };
+    if !matches!(f, Value::Function(_) | Value::Builtin(_)) {
+        return Value::Str("ERROR: int: first argument must be a function".to_string());
+    }
+    let h: f64 = (b - a) / n as f64;
+    let mut sum: f64 = 0.0;
+    for i in 0..n {
+        // samples that are not numbers are skipped
+        if let Value::Number(y) = call_value(f, &[Value::Number(a + i as f64 * h)], env) { sum += y; }
+    }
+    Value::Number(sum * h)
+}
+
+pub(crate) fn invoke(f: &Function, args: &[Value], env: &mut Env) -> Value {
+    match &f.memo {
+        Some(memo) => memo.call(f, args, env, |env| run(f, args, env)),
+        None => run(f, args, env),
+    }
+}
+
+fn run(f: &Function, args: &[Value], env: &mut Env) -> Value {
+    if let Err(msg) = env.shared.sandbox.enter() { return Value::Str(format!("ERROR: {}", msg)); }
+    let result: Value = match crate::bytecode::compiled(f, &env.shared.vm) {
+        // a compiled body keeps its parameters in slots instead of a new environment
+        Some(code) if args.len() == f.params.len() => crate::bytecode::run(&code, f.module.as_ref(), args, env),
+        _ => {
+            let mut local: Env = call_scope(f, env);
+            for (p, v) in f.params.iter().zip(args.iter()) {
+                local.vars.insert(p.clone(), v.clone());
+            }
+            eval(&f.body, &mut local)
+        }
+    };
+    env.shared.sandbox.leave();
+    result
+}
+
+/// The environment the body of `f` runs in, before its parameters are bound.
+pub(crate) fn call_scope(f: &Function, env: &Env) -> Env {
+    let mut local: Env = Env::with_shared(env.shared.clone());
+    // user-defined functions are inherited, except that a module function sees its own module's
+    local.funcs = match &f.module {
+        Some(scope) => scope.funcs(),
+        None => env.funcs.clone(),
+    };
+    for (k, v) in &f.captured { local.vars.insert(k.clone(), v.clone()); }
+    local
+}
+
+/// Call a function value (user-defined, anonymous or built-in) with already evaluated arguments.
+pub fn call_value(f: &Value, args: &[Value], env: &mut Env) -> Value {
+    match f {
+        Value::Function(func) => invoke(func, args, env),
+        Value::Builtin(name) => call_function(name, args, env),
+        Value::Polynomial(p) => match args {
+            [x] => crate::poly::apply(p, x),
+            _ => Value::Str("ERROR: a polynomial takes exactly one argument".to_string()),
+        },
+        other => Value::Str(format!("ERROR: '{}' is not a function", display_value(other))),
+    }
+}
+
+const BUILTINS: &[&str] = &[
+    "clamp", "round", "trunc", "floor", "ceil", "int",
+    "sin", "cos", "tan", "sec", "csc", "cot", "asin", "acos", "atan", "asec", "acsc", "acot",
+    "sinh", "cosh", "tanh", "sech", "csch", "coth", "asinh", "acosh", "atanh", "asech", "acsch", "acoth",
+    "ln", "log", "erf", "erfc", "divmod", "gcd", "lcm", "print",
+    "sqrt", "exp", "abs", "real", "imag", "re", "im", "conj", "arg", "sum", "prod", "min", "max",
+];
+
+/// Whether `name` refers to a function implemented in Rust rather than in the script.
+pub fn is_builtin(name: &str) -> bool {
+    BUILTINS.contains(&name) || in_builtin_module(name)
+}
+
+/// Whether `name` is a built-in whose result depends only on its arguments, so that it can be
+/// called without an environment.
+pub(crate) fn is_plain_builtin(name: &str) -> bool {
+    BUILTINS.contains(&name) && !matches!(name, "print" | "int" | "log") && !in_builtin_module(name)
+}
+
+fn in_builtin_module(name: &str) -> bool {
+    crate::arrays::NAMES.contains(&name) || crate::stats::NAMES.contains(&name)
+        || crate::random::NAMES.contains(&name)
+        || crate::poly::NAMES.contains(&name) || crate::series::NAMES.contains(&name)
+        || crate::calculus::NAMES.contains(&name) || crate::optimize::NAMES.contains(&name)
+        || crate::signal::NAMES.contains(&name) || crate::sets::NAMES.contains(&name)
+        || crate::strings::NAMES.contains(&name)
+}
+
+pub(crate) fn call_builtin(name: &str, args: &[Value]) -> Option<Value> {
     let n1 = |v: &Value| -> Option<f64> { if let Value::Number(x) = v { Some(*x) } else { None } };
     let map1 = |f: fn(f64)->f64| args.get(0).and_then(n1).map(|x| Value::Number(f(x)));
     // Note: 'a' before a trig function means 'arc', i.e., inverse trig, not area.
     // For example: asin = arc-sin (inverse sine), not area-sin.
     match name {
-        // Numerical derivative: deriv(f, x [, h])
-        "deriv" => {
-            if args.len() < 2 {
-                return Some(Value::Str("ERROR: deriv expects at least 2 arguments (function, x [, h])".to_string()));
-            }
-            let f: &Value = &args[0];
-            let x: f64 = match &args[1] {
-                Value::Number(n) => *n,
-                _ => return Some(Value::Str("ERROR: deriv: x must be a number".to_string())),
-            };
-            let h: f64 = if args.len() > 2 {
-                match &args[2] {
-                    Value::Number(n) => *n,
-                    _ => 1e-6,
-                }
-            } else { 1e-6 };
-            // Only support closures for now
-            if let Value::Function(func) = f {
-                // Single-variable function: f(x)
-                let mut env: Env = Env::with_builtins();
-                let param: String = (&*(*func).params).get(0).cloned().unwrap_or("x".to_string());
-                // f(x+h)
-                (&mut env.vars).insert((&param).clone(), Value::Number(x + h));
-                let y1: Value = eval(&(*func).body, &mut env);
-                // f(x-h)
-                let mut env2: Env = Env::with_builtins();
-                (&mut env2.vars).insert((&param).clone(), Value::Number(x - h));
-                let y0: Value = eval(&(*func).body, &mut env2);
-                match (y1, y0) {
-                    (Value::Number(y1n), Value::Number(y0n)) => Some(Value::Number((y1n - y0n) / (2.0 * h))),
-                    _ => Some(Value::Str("ERROR: deriv: function did not return numbers".to_string())),
-                }
-            } else {
-                Some(Value::Str("ERROR: deriv: first argument must be a function".to_string()))
-            }
-        },
         // New built-in functions
         "clamp" => {
             // clamp(x, min, max): clamp x between min and max
@@ -317,49 +682,6 @@ fn call_builtin(name: &str, args: &[Value]) -> Option<Value> {
         "ceil" => {
             args.get(0).and_then(|v| if let Value::Number(x) = v { Some(Value::Number(x.ceil())) } else { None })
         },
-        "rand" => {
-            use rand::prelude::*;
-            let mut rng = rand::rng();
-            if args.len() == 0 {
-                // rand() -> [0,1)
-                Some(Value::Number(rng.random::<f64>()))
-            } else if args.len() == 2 {
-                // rand(min, max) -> [min, max)
-                let min = match &args[0] { Value::Number(n) => *n, _ => return Some(Value::Unit) };
-                let max = match &args[1] { Value::Number(n) => *n, _ => return Some(Value::Unit) };
-                Some(Value::Number(rng.random_range(min..max)))
-            } else {
-                Some(Value::Str("ERROR: rand expects 0 or 2 arguments (min, max)".to_string()))
-            }
-        },
-        "int" => {
-            // int(f, a, b, [n]): numeric integration of f(x) from a to b, n intervals (default 1000)
-            if args.len() < 3 {
-                return Some(Value::Str("ERROR: int expects at least 3 arguments (function, a, b, [n])".to_string()));
-            }
-            let f = &args[0];
-            let a = match &args[1] { Value::Number(n) => *n, _ => return Some(Value::Unit) };
-            let b = match &args[2] { Value::Number(n) => *n, _ => return Some(Value::Unit) };
-            let n = if args.len() > 3 {
-                match &args[3] { Value::Number(n) => *n as usize, _ => 1000 }
-            } else { 1000 };
-            if let Value::Function(func) = f {
-                let param = func.params.get(0).cloned().unwrap_or("x".to_string());
-                let mut env = Env::with_builtins();
-                let h = (b - a) / n as f64;
-                let mut sum = 0.0;
-                for i in 0..n {
-                    let x = a + i as f64 * h;
-                    env.vars.insert(param.clone(), Value::Number(x));
-                    if let Value::Number(y) = eval(&func.body, &mut env) {
-                        sum += y;
-                    }
-                }
-                Some(Value::Number(sum * h))
-            } else {
-                Some(Value::Str("ERROR: int: first argument must be a function".to_string()))
-            }
-        },
         "sin" => return (&map1)(f64::sin),
         "cos" => return (&map1)(f64::cos),
         "tan" => return (&map1)(f64::tan),
@@ -388,11 +710,91 @@ fn call_builtin(name: &str, args: &[Value]) -> Option<Value> {
         "log" => return (&map1)(|x: f64| -> f64 { x.log10() }),
         "erf" => return (&map1)(statrs::function::erf::erf),
         "erfc" => return (&map1)(statrs::function::erf::erfc),
-        "print" => {
-            // print variadic: convert to strings and return ()
-            let parts: Vec<String> = args.iter().map(|v: &Value| -> String { display_value(v) }).collect();
-            println!("{}", parts.join(" "));
-            return Some(Value::Unit)
+        "sqrt" | "exp" => Some(match args {
+            // the square root of a negative number is imaginary
+            [Value::Number(x)] if name == "sqrt" && *x < 0.0 => Value::Complex(Complex64::new(0.0, (-x).sqrt())),
+            [Value::Number(x)] => Value::Number(if name == "sqrt" { x.sqrt() } else { x.exp() }),
+            [Value::Complex(c)] => Value::Complex(if name == "sqrt" { c.sqrt() } else { c.exp() }),
+            _ => Value::Str(format!("ERROR: {} expects 1 argument (number)", name)),
+        }),
+        "abs" => Some(match args {
+            [v] => num_abs(v.clone()),
+            _ => Value::Str("ERROR: abs expects 1 argument".to_string()),
+        }),
+        "real" | "re" | "imag" | "im" | "conj" | "arg" => {
+            let z: Complex64 = match args {
+                [Value::Number(x)] => Complex64::new(*x, 0.0),
+                [Value::Complex(c)] => *c,
+                _ => return Some(Value::Str(format!("ERROR: {} expects 1 argument (number)", name))),
+            };
+            Some(match name {
+                "real" | "re" => Value::Number(z.re),
+                "imag" | "im" => Value::Number(z.im),
+                "arg" => Value::Number(z.arg()),
+                _ if z.im == 0.0 => Value::Number(z.re),
+                _ => Value::Complex(z.conj()),
+            })
+        }
+        "sum" | "prod" => {
+            let items: &[Value] = match args {
+                [Value::Array(items) | Value::Set(items)] => items,
+                _ => return Some(Value::Str(format!("ERROR: {} expects 1 argument (array)", name))),
+            };
+            let (op, start) = if name == "sum" { (BinaryOpKind::Add, 0.0) } else { (BinaryOpKind::Mul, 1.0) };
+            let mut acc: Value = Value::Number(start);
+            for v in items {
+                acc = match (&acc, v) {
+                    (Value::Number(a), Value::Number(b)) => Value::Number(if name == "sum" { a + b } else { a * b }),
+                    (Value::Number(_) | Value::Complex(_), Value::Number(_) | Value::Complex(_)) => complex_bin(&op, acc, v.clone()),
+                    _ => return Some(Value::Str(format!("ERROR: {}: array must contain only numbers, got {}", name, display_value(v)))),
+                };
+            }
+            Some(acc)
+        }
+        "min" | "max" => {
+            // min(a, b, ...) or min(array)
+            let items: &[Value] = match args {
+                [Value::Array(items) | Value::Set(items)] => items,
+                _ => args,
+            };
+            let mut best: Option<f64> = None;
+            for v in items {
+                let Value::Number(x) = v else {
+                    return Some(Value::Str(format!("ERROR: {} expects numbers, got {}", name, display_value(v))));
+                };
+                best = Some(match best {
+                    None => *x,
+                    Some(b) if name == "min" => b.min(*x),
+                    Some(b) => b.max(*x),
+                });
+            }
+            Some(best.map(Value::Number).unwrap_or_else(|| Value::Str(format!("ERROR: {} of nothing", name))))
+        }
+        "divmod" => {
+            // divmod(a, b): the tuple (q, r) with a = q*b + r, where r = a mod b
+            match args {
+                [Value::Number(a), Value::Number(b)] if *b != 0.0 => {
+                    let r: f64 = a % b;
+                    Some(Value::Tuple(vec![Value::Number(((a - r) / b).round()), Value::Number(r)]))
+                }
+                [Value::Number(_), Value::Number(_)] => Some(Value::Str("ERROR: divmod: division by zero".to_string())),
+                _ => Some(Value::Str("ERROR: divmod expects 2 arguments (a, b)".to_string())),
+            }
+        }
+        "gcd" | "lcm" => {
+            // gcd(a, b), lcm(a, b) of whole numbers, by Euclid's algorithm
+            let (a, b): (f64, f64) = match args {
+                [Value::Number(a), Value::Number(b)] if a.fract() == 0.0 && b.fract() == 0.0 => (a.abs(), b.abs()),
+                [Value::Number(_), Value::Number(_)] => return Some(Value::Str(format!("ERROR: {} expects whole numbers", name))),
+                _ => return Some(Value::Str(format!("ERROR: {} expects 2 arguments (a, b)", name))),
+            };
+            let (mut x, mut y) = (a, b);
+            while y != 0.0 { (x, y) = (y, x % y); }
+            Some(Value::Number(match name {
+                "gcd" => x,
+                _ if x == 0.0 => 0.0,
+                _ => a / x * b,
+            }))
         }
         _ => {
             // Linkage error: function does not exist
@@ -401,9 +803,32 @@ fn call_builtin(name: &str, args: &[Value]) -> Option<Value> {
     }
 }
 
-fn num_neg(v: Value) -> Value { match v { Value::Number(n) => Value::Number(-n), _ => Value::Unit } }
-fn bool_not(v: Value) -> Value { Value::Number(if is_true(&v) {0.0} else {1.0}) }
-fn num_abs(v: Value) -> Value { match v { Value::Number(n) => Value::Number(n.abs()), Value::Complex(c) => Value::Number(c.norm()), _ => Value::Unit } }
+fn num_neg(v: Value) -> Value {
+    match v {
+        Value::Number(n) => Value::Number(-n),
+        Value::Polynomial(p) => Value::Polynomial(p.scale(-1.0)),
+        Value::Complex(c) => Value::Complex(-c),
+        Value::Array(items) => Value::Array(items.into_iter().map(num_neg).collect()),
+        _ => Value::Unit,
+    }
+}
+fn bool_not(v: Value) -> Value {
+    match truth(&v, "operand of 'not'") {
+        Ok(b) => Value::Bool(!b),
+        Err(msg) => Value::Str(format!("ERROR: {}", msg)),
+    }
+}
+fn num_abs(v: Value) -> Value {
+    match v {
+        Value::Number(n) => Value::Number(n.abs()),
+        Value::Complex(c) => Value::Number(c.norm()),
+        // |X| of a spectrum is the array of magnitudes
+        Value::Array(items) => Value::Array(items.into_iter().map(num_abs).collect()),
+        // |S| is the cardinality of a set
+        Value::Set(items) => Value::Number(items.len() as f64),
+        _ => Value::Unit,
+    }
+}
 fn num_factorial(v: Value) -> Value {
     match v {
         Value::Number(n) if n >= 0.0 && n.fract() == 0.0 => {
@@ -417,6 +842,72 @@ fn num_factorial(v: Value) -> Value {
 }
 
 
+fn is_error(v: &Value) -> bool {
+    matches!(v, Value::Str(s) if s.starts_with("ERROR:"))
+}
+
+// a bool anywhere in a value given to arithmetic, which is a type error rather than a number
+fn has_bool(v: &Value) -> bool {
+    match v {
+        Value::Bool(_) => true,
+        Value::Array(items) => items.iter().any(has_bool),
+        _ => false,
+    }
+}
+
+pub(crate) fn has_complex(v: &Value) -> bool {
+    match v {
+        Value::Complex(_) => true,
+        Value::Array(items) => items.iter().any(has_complex),
+        _ => false,
+    }
+}
+
+/// Arithmetic when either side is complex, element-wise over arrays like [`lift_bin`].
+fn complex_bin(op: &BinaryOpKind, a: Value, b: Value) -> Value {
+    match (a, b) {
+        (Value::Array(ax), Value::Array(by)) => {
+            if ax.len() != by.len() { return Value::Unit; }
+            Value::Array(ax.into_iter().zip(by).map(|(x, y)| complex_bin(op, x, y)).collect())
+        }
+        (Value::Array(ax), y) => Value::Array(ax.into_iter().map(|x| complex_bin(op, x, y.clone())).collect()),
+        (x, Value::Array(by)) => Value::Array(by.into_iter().map(|y| complex_bin(op, x.clone(), y)).collect()),
+        (x, y) => {
+            let as_complex = |v: &Value| match v {
+                Value::Number(n) => Some(Complex64::new(*n, 0.0)),
+                Value::Complex(c) => Some(*c),
+                _ => None,
+            };
+            let (Some(l), Some(r)) = (as_complex(&x), as_complex(&y)) else { return Value::Unit };
+            match op {
+                BinaryOpKind::Add => Value::Complex(l + r),
+                BinaryOpKind::Sub => Value::Complex(l - r),
+                BinaryOpKind::Mul => Value::Complex(l * r),
+                BinaryOpKind::Div => Value::Complex(l / r),
+                // whole powers by repeated multiplication, so i^2 is exactly -1
+                BinaryOpKind::Pow if r.im == 0.0 && r.re.fract() == 0.0 && r.re.abs() <= i32::MAX as f64 => Value::Complex(l.powi(r.re as i32)),
+                BinaryOpKind::Pow => Value::Complex(l.powc(r)),
+                BinaryOpKind::Eq => Value::Bool(l == r),
+                BinaryOpKind::NotEq => Value::Bool(l != r),
+                _ => Value::Unit,
+            }
+        }
+    }
+}
+
+/// a..b: the numbers a, a + 1, ... up to and including b.
+fn range(a: Value, b: Value, sandbox: &Sandbox) -> Value {
+    let (Value::Number(from), Value::Number(to)) = (&a, &b) else {
+        return Value::Str(format!("ERROR: a range needs numbers, got {}..{}", display_value(&a), display_value(&b)));
+    };
+    if !from.is_finite() || !to.is_finite() {
+        return Value::Str("ERROR: a range needs finite ends".to_string());
+    }
+    let count: usize = if to >= from { (to - from).floor() as usize + 1 } else { 0 };
+    if let Err(msg) = sandbox.check_len(count) { return Value::Str(format!("ERROR: {}", msg)); }
+    Value::Array((0..count).map(|k| Value::Number(from + k as f64)).collect())
+}
+
 fn bin_num(a: Value, b: Value, f: fn(f64,f64)->f64) -> Value {
     match (a, b) {
         (Value::Number(x), Value::Number(y)) => Value::Number(f(x,y)),
@@ -424,9 +915,16 @@ fn bin_num(a: Value, b: Value, f: fn(f64,f64)->f64) -> Value {
     }
 }
 
-fn compare(op: BinaryOpKind, a: Value, b: Value) -> Value {
-    let (l, r) = match (a, b) {
-        (Value::Number(x), Value::Number(y)) => (x, y),
+pub(crate) fn compare(op: BinaryOpKind, a: Value, b: Value) -> Value {
+    if is_error(&a) { return a; }
+    if is_error(&b) { return b; }
+    let (l, r) = match (&a, &b) {
+        (Value::Number(x), Value::Number(y)) => (*x, *y),
+        // values of other kinds can be tested for equality but not ordered
+        _ if op == BinaryOpKind::Eq => return Value::Bool(values_equal(&a, &b)),
+        _ if op == BinaryOpKind::NotEq => return Value::Bool(!values_equal(&a, &b)),
+        (Value::Bool(_), _) => return Value::Str(format!("ERROR: cannot order {}", display_value(&a))),
+        (_, Value::Bool(_)) => return Value::Str(format!("ERROR: cannot order {}", display_value(&b))),
         _ => return Value::Unit,
     };
     let res: bool = match op {
@@ -438,7 +936,7 @@ fn compare(op: BinaryOpKind, a: Value, b: Value) -> Value {
     BinaryOpKind::Lte => l <= r,
         _ => false,
     };
-    Value::Number(if res {1.0} else {0.0})
+    Value::Bool(res)
 }
 
 fn lift_bin(a: Value, b: Value, f: fn(f64,f64)->f64) -> Value {
@@ -542,15 +1040,5 @@ fn matrix_mul(a: &Vec<Vec<f64>>, b: &Vec<Vec<f64>>) -> Option<Vec<Vec<f64>>> {
 }
 
 pub fn display_value(v: &Value) -> String {
-    match v {
-        Value::Number(n) => n.to_string(),
-        Value::Complex(c) => format!("{}+{}i", c.re, c.im),
-        Value::Str(s) => s.clone(),
-        Value::Array(a) => {
-            let parts: Vec<String> = (&**a).iter().map(|x: &Value| -> String { display_value(x) }).collect();
-            format!("[{}]", parts.join(", "))
-        }
-        Value::Function(f) => format!("<function:{} params>", f.params.len()),
-        Value::Unit => "()".to_string(),
-    }
+    crate::display::show(v, NumberFormat::default())
 }
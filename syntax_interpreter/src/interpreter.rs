@@ -0,0 +1,312 @@
+// Embedding API: an interpreter that Rust programs can drive, extend with native functions
+// and read results and printed output back from
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use num_complex::Complex64;
+
+use crate::ast::AstNode;
+use crate::evaluator::{display_value, eval, Env, Value};
+use crate::output::{Buffer, Output, OutputSink, Stream};
+use crate::sandbox::{Capabilities, Limits};
+
+type Native = Rc<dyn Fn(&[Value]) -> Result<Value, String>>;
+
+/// A function implemented in Rust by the embedding program.
+#[derive(Clone)]
+pub struct NativeFn {
+    pub arity: usize,
+    f: Native,
+}
+
+impl NativeFn {
+    pub fn call(&self, name: &str, args: &[Value]) -> Value {
+        if args.len() != self.arity {
+            let plural: &str = if self.arity == 1 { "" } else { "s" };
+            return Value::Str(format!("ERROR: {} expects {} argument{}, got {}", name, self.arity, plural, args.len()));
+        }
+        if let Some(err) = args.iter().find(|a| matches!(a, Value::Str(s) if s.starts_with("ERROR:"))) {
+            return err.clone();
+        }
+        (self.f)(args).unwrap_or_else(|msg| Value::Str(format!("ERROR: {}: {}", name, msg)))
+    }
+}
+
+/// The native functions registered through [`Interpreter::register_fn`].
+#[derive(Clone, Default)]
+pub struct Natives(Rc<RefCell<HashMap<String, NativeFn>>>);
+
+impl Natives {
+    pub fn get(&self, name: &str) -> Option<NativeFn> {
+        self.0.borrow().get(name).cloned()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.borrow().contains_key(name)
+    }
+
+    pub fn insert(&self, name: &str, f: NativeFn) {
+        self.0.borrow_mut().insert(name.to_string(), f);
+    }
+}
+
+/// An MS interpreter for use from Rust. Variables and functions persist between calls to
+/// [`Interpreter::eval_str`], and output is collected rather than written to the terminal.
+///
+/// ```
+/// use syntax_interpreter::interpreter::Interpreter;
+///
+/// let mut ms = Interpreter::new();
+/// ms.register_fn("twice", 1, |args| Ok((2.0 * f64::try_from(&args[0])?).into()));
+/// ms.set_var("r", 3.0);
+/// assert_eq!(f64::try_from(ms.eval_str("twice(r) + 1").unwrap()), Ok(7.0));
+/// ```
+pub struct Interpreter {
+    env: Env,
+    // what the script wrote, unless another sink was set
+    buffer: Buffer,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        let buffer: Buffer = Buffer::default();
+        let mut env: Env = Env::with_builtins();
+        env.shared.output = Output::new(buffer.clone());
+        Interpreter { env, buffer }
+    }
+
+    /// Run `src` and return the value of its last statement. The first statement that fails
+    /// to parse, evaluates to an error or exceeds a limit stops the run, and its message is returned.
+    /// Steps and time are counted from the start of each call.
+    pub fn eval_str(&mut self, src: &str) -> Result<Value, String> {
+        // variables from earlier calls are bound, so dy/dx divides when dy was set before
+        let ast: AstNode = crate::parser::parse_with(&crate::lexer::tokenize(src), self.env.vars.keys().map(String::as_str));
+        let stmts: Vec<AstNode> = match ast {
+            AstNode::Program(stmts) => stmts,
+            other => vec![other],
+        };
+        if let Some(AstNode::Error(msg)) = stmts.iter().find(|s| matches!(s, AstNode::Error(_))) {
+            return Err(msg.clone());
+        }
+        self.env.shared.sandbox.reset();
+        // functions are defined before anything runs, as in a script
+        for stmt in stmts.iter().filter(|s| matches!(s, AstNode::FunctionDef { .. })) { eval(stmt, &mut self.env); }
+        let mut last: Value = Value::Unit;
+        for stmt in stmts.iter().filter(|s| !matches!(s, AstNode::FunctionDef { .. })) {
+            last = eval(stmt, &mut self.env);
+            // a limit stops the run even when the statement itself swallowed the error, as print does
+            if let Some(msg) = self.env.shared.sandbox.exceeded() { return Err(msg); }
+            if let Value::Str(s) = &last {
+                if let Some(msg) = s.strip_prefix("ERROR:") { return Err(msg.trim_start().to_string()); }
+            }
+        }
+        Ok(last)
+    }
+
+    pub fn set_var(&mut self, name: &str, value: impl Into<Value>) {
+        self.env.vars.insert(name.to_string(), value.into());
+    }
+
+    pub fn get_var(&self, name: &str) -> Option<Value> {
+        self.env.vars.get(name).cloned()
+    }
+
+    /// `v` as text, with numbers in the format the script set with `format`.
+    pub fn display(&self, v: &Value) -> String {
+        crate::display::show(v, self.env.shared.format.get())
+    }
+
+    /// Limit the steps, call depth, time and array sizes of later runs.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.env.shared.sandbox.set_limits(limits);
+    }
+
+    /// Restrict the files and clock that later runs may use.
+    pub fn set_capabilities(&mut self, capabilities: Capabilities) {
+        self.env.shared.sandbox.set_capabilities(capabilities);
+    }
+
+    /// Look for imports in `dirs`, after the importing file's directory, instead of in MS_PATH.
+    pub fn set_search_path(&mut self, dirs: impl IntoIterator<Item = PathBuf>) {
+        self.env.shared.modules.set_search_path(dirs);
+    }
+
+    /// Make `f` callable from MS as `name`, taking exactly `arity` arguments. An `Err` becomes an
+    /// MS error prefixed by the name. Functions defined by the script take precedence.
+    pub fn register_fn<F>(&mut self, name: &str, arity: usize, f: F)
+    where
+        F: Fn(&[Value]) -> Result<Value, String> + 'static,
+    {
+        self.env.shared.natives.insert(name, NativeFn { arity, f: Rc::new(f) });
+        self.env.shared.memo.invalidate();
+    }
+
+    /// Send what the script writes to `sink` instead of collecting it.
+    pub fn set_output(&mut self, sink: impl OutputSink + 'static) {
+        self.env.shared.output = Output::new(sink);
+    }
+
+    /// The text printed since the last call.
+    pub fn take_output(&mut self) -> String {
+        self.buffer.take_text(Stream::Print)
+    }
+
+    /// Everything written since the last call, including log messages and errors, in order.
+    pub fn take_messages(&mut self) -> Vec<(Stream, String)> {
+        self.buffer.take()
+    }
+
+    pub fn env(&self) -> &Env {
+        &self.env
+    }
+
+    pub fn env_mut(&mut self) -> &mut Env {
+        &mut self.env
+    }
+}
+
+impl From<f64> for Value {
+    fn from(x: f64) -> Self {
+        Value::Number(x)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(x: i64) -> Self {
+        Value::Number(x as f64)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+impl From<Complex64> for Value {
+    fn from(z: Complex64) -> Self {
+        Value::Complex(z)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::Str(s.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::Str(s)
+    }
+}
+
+impl From<()> for Value {
+    fn from(_: ()) -> Self {
+        Value::Unit
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(items: Vec<T>) -> Self {
+        Value::Array(items.into_iter().map(Into::into).collect())
+    }
+}
+
+impl TryFrom<&Value> for f64 {
+    type Error = String;
+    fn try_from(v: &Value) -> Result<Self, String> {
+        match v {
+            Value::Number(x) => Ok(*x),
+            other => Err(format!("expected a number, got {}", display_value(other))),
+        }
+    }
+}
+
+impl TryFrom<&Value> for bool {
+    type Error = String;
+    fn try_from(v: &Value) -> Result<Self, String> {
+        match v {
+            Value::Bool(b) => Ok(*b),
+            other => Err(format!("expected true or false, got {}", display_value(other))),
+        }
+    }
+}
+
+impl TryFrom<&Value> for Complex64 {
+    type Error = String;
+    fn try_from(v: &Value) -> Result<Self, String> {
+        match v {
+            Value::Number(x) => Ok(Complex64::new(*x, 0.0)),
+            Value::Complex(z) => Ok(*z),
+            other => Err(format!("expected a complex number, got {}", display_value(other))),
+        }
+    }
+}
+
+impl TryFrom<&Value> for String {
+    type Error = String;
+    fn try_from(v: &Value) -> Result<Self, String> {
+        match v {
+            Value::Str(s) => Ok(s.clone()),
+            other => Err(format!("expected a string, got {}", display_value(other))),
+        }
+    }
+}
+
+impl TryFrom<&Value> for Vec<f64> {
+    type Error = String;
+    fn try_from(v: &Value) -> Result<Self, String> {
+        match v {
+            Value::Array(items) => items.iter().map(f64::try_from).collect(),
+            other => Err(format!("expected an array of numbers, got {}", display_value(other))),
+        }
+    }
+}
+
+impl TryFrom<Value> for f64 {
+    type Error = String;
+    fn try_from(v: Value) -> Result<Self, String> {
+        f64::try_from(&v)
+    }
+}
+
+impl TryFrom<Value> for bool {
+    type Error = String;
+    fn try_from(v: Value) -> Result<Self, String> {
+        bool::try_from(&v)
+    }
+}
+
+impl TryFrom<Value> for Complex64 {
+    type Error = String;
+    fn try_from(v: Value) -> Result<Self, String> {
+        Complex64::try_from(&v)
+    }
+}
+
+impl TryFrom<Value> for String {
+    type Error = String;
+    fn try_from(v: Value) -> Result<Self, String> {
+        match v {
+            Value::Str(s) => Ok(s),
+            other => String::try_from(&other),
+        }
+    }
+}
+
+impl TryFrom<Value> for Vec<f64> {
+    type Error = String;
+    fn try_from(v: Value) -> Result<Self, String> {
+        Vec::<f64>::try_from(&v)
+    }
+}
@@ -0,0 +1,130 @@
+// Numeric inverses of user functions: f^-1(y) solves f(x) = y
+
+use crate::ast::{AstNode, BinaryOpKind};
+use crate::evaluator::{call_value, display_value, eval, Env, Value};
+
+// grid points used to look for sign changes of f(x) - y across the domain
+const GRID: usize = 2000;
+
+/// Evaluate `f^-1(y)`. Returns `None` when `name` is not an inverse (does not end in `^-1`).
+pub fn call(name: &str, args: &[Value], env: &mut Env) -> Option<Value> {
+    let base: &str = name.strip_suffix("^-1")?;
+    Some(solve(base, args, env).unwrap_or_else(|msg| Value::Str(format!("ERROR: {}", msg))))
+}
+
+fn solve(base: &str, args: &[Value], env: &mut Env) -> Result<Value, String> {
+    let name: String = format!("{}^-1", base);
+    // look the name up the way a call would: defined functions before variables
+    let f: Value = match env.funcs.get(base) {
+        Some(func) => Value::Function(func.clone()),
+        None => eval(&AstNode::Variable(base.to_string()), env),
+    };
+    let Value::Function(func) = &f else {
+        return Err(format!("{}: '{}' is not a user-defined function", name, base));
+    };
+    let [param] = func.params.as_slice() else {
+        return Err(format!("{}: only functions of one variable can be inverted", name));
+    };
+    let [Value::Number(y)] = args else {
+        return Err(format!("{} expects a single number", name));
+    };
+    let y: f64 = *y;
+    let (lo, hi) = domain(&func.body, param, env);
+    let mut g = |x: f64| -> f64 {
+        match call_value(&f, &[Value::Number(x)], env) { Value::Number(v) => v - y, _ => f64::NAN }
+    };
+
+    // sample the domain, mapping an infinite end onto a finite parameter range
+    let to_x = |t: f64| -> f64 {
+        match (lo.is_finite(), hi.is_finite()) {
+            (true, true) => lo + (hi - lo) * t,
+            (true, false) => lo + t / (1.0 - t),
+            (false, true) => hi - (1.0 - t) / t,
+            (false, false) => (2.0 * t - 1.0) / (1.0 - (2.0 * t - 1.0).powi(2)),
+        }
+    };
+    let xs: Vec<f64> = (0..=GRID).map(|i| to_x(i as f64 / GRID as f64)).filter(|x| x.is_finite()).collect();
+    let gs: Vec<f64> = xs.iter().map(|x| g(*x)).collect();
+    let scale: f64 = y.abs().max(1.0);
+    let mut roots: Vec<f64> = Vec::new();
+    for i in 0..xs.len() {
+        if gs[i] == 0.0 {
+            roots.push(xs[i]);
+        } else if i + 1 < xs.len() && gs[i] * gs[i + 1] < 0.0 {
+            let x: f64 = bisect(&mut g, xs[i], xs[i + 1], gs[i]);
+            // a sign change across a pole is not a solution
+            if g(x).abs() <= 1e-6 * scale { roots.push(x); }
+        } else if i > 0 && i + 1 < xs.len() && gs[i - 1] * gs[i] > 0.0 && gs[i] * gs[i + 1] > 0.0
+            && gs[i].abs() <= gs[i - 1].abs() && gs[i].abs() <= gs[i + 1].abs() {
+            // f may touch y without crossing it, as x^2 does at 0
+            let x: f64 = closest(&mut g, xs[i - 1], xs[i + 1]);
+            if g(x).abs() <= 1e-9 * scale { roots.push(x); }
+        }
+    }
+    roots.dedup_by(|a, b| (*a - *b).abs() <= 1e-7 * a.abs().max(1.0));
+    let roots: Vec<f64> = roots.into_iter().map(|x| {
+        // prefer a whole number when it solves the equation at least as well
+        if g(x.round()).abs() <= g(x).abs() { x.round() } else { x }
+    }).collect();
+    match roots.as_slice() {
+        [x] => Ok(Value::Number(*x)),
+        [] => Err(format!("{}: {} is not a value of {} on its domain", name, display_value(&Value::Number(y)), base)),
+        many => {
+            let mut shown: Vec<String> = many.iter().take(5).map(|x| format!("{} = {}", param, display_value(&Value::Number(*x)))).collect();
+            if many.len() > 5 { shown.push("...".to_string()); }
+            Err(format!(
+                "{}: {} is not invertible here, {}({}) = {} has {} solutions ({}); restrict its domain with a {{condition}}",
+                name, base, base, param, display_value(&Value::Number(y)), many.len(), shown.join(", "),
+            ))
+        }
+    }
+}
+
+/// Bounds on `param` read from the function's `{condition}`, e.g. `{x >= 0}` gives [0, inf).
+fn domain(body: &AstNode, param: &str, env: &mut Env) -> (f64, f64) {
+    let (mut lo, mut hi) = (f64::NEG_INFINITY, f64::INFINITY);
+    let AstNode::Conditional { condition, .. } = body else { return (lo, hi) };
+    let AstNode::BinaryOp { op, left, right } = &**condition else { return (lo, hi) };
+    let is_param = |n: &AstNode| matches!(n, AstNode::Variable(v) if v == param);
+    // normalise to `param op bound`
+    let (op, bound) = match (is_param(left), is_param(right)) {
+        (true, false) => (op.clone(), &**right),
+        (false, true) => (match op {
+            BinaryOpKind::Gt => BinaryOpKind::Lt,
+            BinaryOpKind::Gte => BinaryOpKind::Lte,
+            BinaryOpKind::Lt => BinaryOpKind::Gt,
+            BinaryOpKind::Lte => BinaryOpKind::Gte,
+            other => other.clone(),
+        }, &**left),
+        _ => return (lo, hi),
+    };
+    let Value::Number(b) = eval(bound, env) else { return (lo, hi) };
+    match op {
+        BinaryOpKind::Gt | BinaryOpKind::Gte => lo = b,
+        BinaryOpKind::Lt | BinaryOpKind::Lte => hi = b,
+        _ => {}
+    }
+    (lo, hi)
+}
+
+fn bisect(g: &mut impl FnMut(f64) -> f64, mut a: f64, mut b: f64, mut ga: f64) -> f64 {
+    for _ in 0..200 {
+        let m: f64 = 0.5 * (a + b);
+        if m <= a || m >= b { break; }
+        let gm: f64 = g(m);
+        if gm == 0.0 { return m; }
+        if (gm < 0.0) == (ga < 0.0) { a = m; ga = gm; } else { b = m; }
+    }
+    0.5 * (a + b)
+}
+
+/// Golden-section search for the point of [a, b] where |g| is smallest.
+fn closest(g: &mut impl FnMut(f64) -> f64, mut a: f64, mut b: f64) -> f64 {
+    let r: f64 = (5f64.sqrt() - 1.0) / 2.0;
+    for _ in 0..200 {
+        let (c, d) = (b - r * (b - a), a + r * (b - a));
+        if g(c).abs() < g(d).abs() { b = d; } else { a = c; }
+        if b - a <= 1e-15 * a.abs().max(1.0) { break; }
+    }
+    0.5 * (a + b)
+}
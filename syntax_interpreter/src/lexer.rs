@@ -11,7 +11,10 @@ pub enum TokenKind {
     Number,     // 1
     String,     // 2
     DocString,  // 3
+    Template,   // start of an interpolated string: its text and {expr} parts follow
+    TemplateEnd,
     Assign,     // =
+    FatArrow,   // =>
     Plus,       // +
     Minus,      // -
     Star,       // *
@@ -28,6 +31,10 @@ pub enum TokenKind {
     Pipe,       // |
     Prime,      // '
     Colon,      // :
+    DotDot,     // ..
+    Dot,        // . (field access)
+    AndAnd,     // &&
+    Backslash,  // \ (set difference)
     Greater,    // >
     Less,       // <
     GreaterEq,  // >=
@@ -37,6 +44,7 @@ pub enum TokenKind {
     Semicolon,  // ;
     Newline,    // \n
     Mod,
+    At,         // @ (annotation)
     EOF,
     Unknown,
 }
@@ -51,8 +59,8 @@ pub fn tokenize(input: &str) -> Vec<Token> {
         } else if c.is_whitespace() {
             (&mut chars).next();
         } else if c == '#' {
-            // comment to end of line
-            while let Some(ch) = (&mut chars).next() { if ch == '\n' { break; } }
+            // comment to end of line; the newline itself still ends the statement
+            while chars.next_if(|ch| *ch != '\n').is_some() {}
         } else if (&c).is_ascii_digit() || (c == '.' && (&mut (&chars).clone()).nth(1).map_or(false, |n: char| (&n).is_ascii_digit())) {
             // Number (integer or float)
             let mut num: String = String::new();
@@ -61,7 +69,8 @@ pub fn tokenize(input: &str) -> Vec<Token> {
                 if (&d).is_ascii_digit() {
                     (&mut num).push(d);
                     (&mut chars).next();
-                } else if d == '.' && !dot_seen {
+                } else if d == '.' && !dot_seen && chars.clone().nth(1) != Some('.') {
+                    // a second '.' makes this the range operator 1..5
                     dot_seen = true;
                     (&mut num).push(d);
                     (&mut chars).next();
@@ -98,18 +107,28 @@ pub fn tokenize(input: &str) -> Vec<Token> {
                 }
                 (&mut tokens).push(Token { kind: TokenKind::DocString, lexeme: buf });
             } else {
-                // normal string
+                // normal string, possibly with {expr} parts
                 (&mut chars).next(); // consume opening
-                let mut buf: String = String::new();
-                while let Some(ch) = (&mut chars).next() {
-                    if ch == '"' { break; }
-                    if ch == '\\' {
-                        if let Some(esc) = (&mut chars).next() { (&mut buf).push(esc); } else { break; }
-                    } else {
-                        (&mut buf).push(ch);
+                let parts: Vec<StringPart> = scan_string(&mut chars);
+                match parts.as_slice() {
+                    [] => tokens.push(Token { kind: TokenKind::String, lexeme: String::new() }),
+                    [StringPart::Text(text)] => tokens.push(Token { kind: TokenKind::String, lexeme: text.clone() }),
+                    _ => {
+                        // "a {x} b" becomes Template "a " { x } " b" TemplateEnd
+                        tokens.push(Token { kind: TokenKind::Template, lexeme: String::new() });
+                        for part in parts {
+                            match part {
+                                StringPart::Text(text) => tokens.push(Token { kind: TokenKind::String, lexeme: text }),
+                                StringPart::Expr(src) => {
+                                    tokens.push(Token { kind: TokenKind::LBrace, lexeme: "{".to_string() });
+                                    tokens.extend(tokenize(&src).into_iter().filter(|t| t.kind != TokenKind::EOF));
+                                    tokens.push(Token { kind: TokenKind::RBrace, lexeme: "}".to_string() });
+                                }
+                            }
+                        }
+                        tokens.push(Token { kind: TokenKind::TemplateEnd, lexeme: String::new() });
                     }
                 }
-                (&mut tokens).push(Token { kind: TokenKind::String, lexeme: buf });
             }
         } else if c.is_ascii_alphabetic() || c == '_' {
             // Identifier or keyword
@@ -137,6 +156,9 @@ pub fn tokenize(input: &str) -> Vec<Token> {
                     if let Some('=') = (&mut chars).peek().copied() {
                         (&mut chars).next();
                         (&mut tokens).push(Token { kind: TokenKind::Equal, lexeme: "==".to_string() });
+                    } else if let Some('>') = chars.peek().copied() {
+                        chars.next();
+                        tokens.push(Token { kind: TokenKind::FatArrow, lexeme: "=>".to_string() });
                     } else {
                         (&mut tokens).push(Token { kind: TokenKind::Assign, lexeme: "=".to_string() });
                     }
@@ -183,6 +205,23 @@ pub fn tokenize(input: &str) -> Vec<Token> {
                 '|' => { (&mut chars).next(); (&mut tokens).push(Token { kind: TokenKind::Pipe, lexeme: "|".to_string() }); }
                 '\'' => { (&mut chars).next(); (&mut tokens).push(Token { kind: TokenKind::Prime, lexeme: "'".to_string() }); }
                 ':' => { (&mut chars).next(); (&mut tokens).push(Token { kind: TokenKind::Colon, lexeme: ":".to_string() }); }
+                '.' if chars.clone().nth(1) == Some('.') => {
+                    chars.next(); chars.next();
+                    tokens.push(Token { kind: TokenKind::DotDot, lexeme: "..".to_string() });
+                }
+                '.' => { chars.next(); tokens.push(Token { kind: TokenKind::Dot, lexeme: ".".to_string() }); }
+                '&' if chars.clone().nth(1) == Some('&') => {
+                    chars.next(); chars.next();
+                    tokens.push(Token { kind: TokenKind::AndAnd, lexeme: "&&".to_string() });
+                }
+                '\\' => { chars.next(); tokens.push(Token { kind: TokenKind::Backslash, lexeme: "\\".to_string() }); }
+                '@' => { chars.next(); tokens.push(Token { kind: TokenKind::At, lexeme: "@".to_string() }); }
+                // set symbols read as the keywords they stand for
+                '∪' | '∩' | '∈' => {
+                    chars.next();
+                    let word: &str = match c { '∪' => "union", '∩' => "intersect", _ => "in" };
+                    tokens.push(Token { kind: TokenKind::Identifier, lexeme: word.to_string() });
+                }
                 _ => {
                     (&mut tokens).push(Token { kind: TokenKind::Unknown, lexeme: (&c).to_string() });
                     (&mut chars).next();
@@ -193,3 +232,80 @@ pub fn tokenize(input: &str) -> Vec<Token> {
     (&mut tokens).push(Token { kind: TokenKind::EOF, lexeme: String::new() });
     tokens
 }
+
+
+enum StringPart {
+    Text(String),
+    Expr(String),
+}
+
+/// Read a string literal up to its closing quote, translating escapes and splitting out
+/// `{expr}` interpolations. `{{`, `{}` and `{:...}` stay literal so that format strings
+/// such as "{:.3}" pass through unchanged.
+fn scan_string(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> Vec<StringPart> {
+    let mut parts: Vec<StringPart> = Vec::new();
+    let mut text: String = String::new();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' => break,
+            '\\' => match chars.next() {
+                Some('n') => text.push('\n'),
+                Some('t') => text.push('\t'),
+                Some('r') => text.push('\r'),
+                Some('0') => text.push('\0'),
+                Some(esc @ ('\\' | '"' | '{' | '}')) => text.push(esc),
+                // unknown escapes are kept as written
+                Some(other) => { text.push('\\'); text.push(other); }
+                None => break,
+            },
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push_str("{{");
+            }
+            '{' if !matches!(chars.peek(), Some('}' | ':') | None) => {
+                // a `{` without its `}` on the same line is text, so the string still ends at its quote
+                let mut ahead = chars.clone();
+                match scan_interpolation(&mut ahead) {
+                    Some(src) => {
+                        *chars = ahead;
+                        if !text.is_empty() { parts.push(StringPart::Text(std::mem::take(&mut text))); }
+                        parts.push(StringPart::Expr(src));
+                    }
+                    None => text.push(ch),
+                }
+            }
+            _ => text.push(ch),
+        }
+    }
+    if !text.is_empty() || parts.is_empty() { parts.push(StringPart::Text(text)); }
+    parts
+}
+
+/// The source of an interpolated expression, up to its closing brace; nested braces and
+/// string literals are skipped over whole. `None` when the line ends before the brace closes.
+fn scan_interpolation(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> Option<String> {
+    let mut src: String = String::new();
+    let mut depth: usize = 0;
+    let mut in_string: bool = false;
+    while let Some(ch) = chars.next() {
+        if ch == '\n' { return None; }
+        if in_string {
+            src.push(ch);
+            match ch {
+                '\\' => { if let Some(esc) = chars.next() { src.push(esc); } }
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match ch {
+            '}' if depth == 0 => return Some(src),
+            '}' => depth -= 1,
+            '{' => depth += 1,
+            '"' => in_string = true,
+            _ => {}
+        }
+        src.push(ch);
+    }
+    None
+}
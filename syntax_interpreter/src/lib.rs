@@ -1,4 +1,27 @@
 pub mod lexer;
 pub mod parser;
 pub mod ast;
-pub mod evaluator;
\ No newline at end of file
+pub mod evaluator;
+pub mod arrays;
+pub mod stats;
+pub mod random;
+pub mod poly;
+pub mod symbolic;
+pub mod series;
+pub mod linalg;
+pub mod calculus;
+pub mod limits;
+pub mod inverse;
+pub mod optimize;
+pub mod signal;
+pub mod sets;
+pub mod strings;
+pub mod display;
+pub mod modules;
+pub mod interpreter;
+pub mod output;
+pub mod logging;
+pub mod sandbox;
+pub mod bytecode;
+pub mod passes;
+pub mod memo;
\ No newline at end of file
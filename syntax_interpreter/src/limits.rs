@@ -0,0 +1,142 @@
+// Limits: lim {x -> a} expr, one-sided, at infinity and divergent
+
+use std::cell::RefCell;
+
+use crate::ast::{AstNode, BinaryOpKind, LimitSide};
+use crate::bytecode::CodeCache;
+use crate::evaluator::{call_value, display_value, eval, Env, Function, Value};
+use crate::symbolic;
+
+// how many rounds of L'Hôpital's rule to try on 0/0 and inf/inf forms
+const MAX_LHOPITAL: usize = 6;
+// numeric estimates of the two sides (or of a side and the symbolic value) closer than this agree
+const AGREEMENT: f64 = 1e-5;
+
+/// What the values of an expression do as `var` approaches the target from one side.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Trend {
+    Converges(f64),
+    Diverges(f64),
+    Unknown,
+}
+
+impl Trend {
+    fn show(self) -> String {
+        match self {
+            Trend::Converges(v) => display_value(&Value::Number(v)),
+            Trend::Diverges(s) => display_value(&Value::Number(s * f64::INFINITY)),
+            Trend::Unknown => "undefined".to_string(),
+        }
+    }
+}
+
+/// Evaluate `lim {var -> to} expr`. The result is a number, `inf`/`-inf`, or an error saying why
+/// the limit does not exist.
+pub fn limit(var: &str, to: &AstNode, side: LimitSide, expr: &AstNode, env: &mut Env) -> Value {
+    let a: f64 = match eval(to, env) {
+        Value::Number(a) if !a.is_nan() => a,
+        other => return Value::Str(format!("ERROR: lim: target must be a number, got {}", display_value(&other))),
+    };
+    if a.is_infinite() && side != LimitSide::Both {
+        return Value::Str("ERROR: lim: one-sided limits need a finite target".to_string());
+    }
+    // the expression as a function of `var` that sees the other variables, compiled once for all the samples
+    let f: Value = Value::Function(Function { params: vec![var.to_string()], body: expr.clone(), captured: env.vars.clone(), module: None, code: CodeCache::default(), memo: None });
+    let local: RefCell<Env> = RefCell::new(env.clone());
+    let at = |x: f64| -> f64 {
+        match call_value(&f, &[Value::Number(x)], &mut local.borrow_mut()) { Value::Number(v) => v, _ => f64::NAN }
+    };
+    // at infinity there is only one way to approach
+    let sides: Vec<f64> = match side {
+        LimitSide::Both if a.is_finite() => vec![-1.0, 1.0],
+        LimitSide::Both => vec![-a.signum()],
+        LimitSide::Below => vec![-1.0],
+        LimitSide::Above => vec![1.0],
+    };
+    let trends: Vec<Trend> = sides.iter().map(|dir| trend(&at, a, *dir)).collect();
+    let exact: Option<f64> = if a.is_finite() { symbolic_limit(expr, var, a, env) } else { None };
+
+    // an exact value is trusted unless the numbers clearly say otherwise
+    if let Some(c) = exact {
+        let consistent = |t: &Trend| match t {
+            Trend::Converges(v) => close(*v, c),
+            Trend::Diverges(_) => false,
+            Trend::Unknown => true,
+        };
+        if trends.iter().all(consistent) { return Value::Number(c); }
+    }
+    match trends.as_slice() {
+        [Trend::Converges(v)] => Value::Number(*v),
+        [Trend::Diverges(s)] => Value::Number(s * f64::INFINITY),
+        [Trend::Converges(l), Trend::Converges(r)] if close(*l, *r) => Value::Number((l + r) / 2.0),
+        [Trend::Diverges(l), Trend::Diverges(r)] if l == r => Value::Number(l * f64::INFINITY),
+        [left, right] if *left != Trend::Unknown && *right != Trend::Unknown => Value::Str(format!(
+            "ERROR: lim: the limit does not exist (limit from below {}, from above {})", left.show(), right.show()
+        )),
+        _ => Value::Str("ERROR: lim: the limit does not exist (the expression does not settle)".to_string()),
+    }
+}
+
+/// Follow the expression along a = x_k with x_k -> a from direction `dir` (or out to infinity).
+fn trend(at: &impl Fn(f64) -> f64, a: f64, dir: f64) -> Trend {
+    let points: Vec<f64> = (1..=9).map(|k| {
+        let h: f64 = 10f64.powi(-k);
+        if a.is_finite() { a + dir * h * a.abs().max(1.0) } else { a.signum() / h }
+    }).collect();
+    let values: Vec<f64> = points.iter().map(|x| at(*x)).collect();
+    if values.iter().any(|v| v.is_nan()) { return Trend::Unknown; }
+    if let Some(s) = diverging(&values) { return Trend::Diverges(s); }
+    // follow the values from the first pair of neighbours that agree for as long as they keep
+    // getting closer: after that they are swamped by rounding error ((1 - cos(x)) / x^2 is exactly
+    // 0 once cos(x) rounds to 1)
+    let gap = |k: usize| (values[k] - values[k + 1]).abs();
+    let Some(mut k) = (0..values.len() - 1).find(|&k| values[k].is_finite() && gap(k) <= AGREEMENT * 0.1 * values[k + 1].abs().max(1.0)) else {
+        return Trend::Unknown;
+    };
+    while k + 2 < values.len() && gap(k + 1) < gap(k) { k += 1; }
+    Trend::Converges(snap(values[k + 1], gap(k)))
+}
+
+/// The sign of the divergence when the values grow without bound.
+fn diverging(values: &[f64]) -> Option<f64> {
+    let tail: &[f64] = &values[values.len() - 5..];
+    let sign: f64 = tail[tail.len() - 1].signum();
+    if tail[tail.len() - 1].is_infinite() { return Some(sign); }
+    let growing: bool = tail.windows(2).all(|w| w[0].signum() == sign && w[1].abs() > w[0].abs());
+    // steps that keep their size (like ln) or grow (like 1/x) never settle
+    let steps: Vec<f64> = tail.windows(2).map(|w| (w[1] - w[0]).abs()).collect();
+    let not_settling: bool = steps.windows(2).all(|s| s[1] >= 0.5 * s[0]);
+    if growing && not_settling && tail[tail.len() - 1].abs() > 10.0 { Some(sign) } else { None }
+}
+
+/// Substitute a directly, or resolve a 0/0 or inf/inf quotient with L'Hôpital's rule.
+fn symbolic_limit(expr: &AstNode, var: &str, a: f64, env: &Env) -> Option<f64> {
+    let value = |node: &AstNode| -> f64 {
+        let mut local: Env = env.clone();
+        local.vars.insert(var.to_string(), Value::Number(a));
+        match eval(node, &mut local) { Value::Number(v) => v, _ => f64::NAN }
+    };
+    let v: f64 = value(expr);
+    if v.is_finite() { return Some(v); }
+    let AstNode::BinaryOp { op: BinaryOpKind::Div, left, right } = expr else { return None };
+    let (mut num, mut den): (AstNode, AstNode) = ((**left).clone(), (**right).clone());
+    for _ in 0..MAX_LHOPITAL {
+        let (n, d) = (value(&num), value(&den));
+        if n.is_finite() && d.is_finite() && d != 0.0 { return Some(n / d); }
+        let indeterminate: bool = (n == 0.0 && d == 0.0) || (n.is_infinite() && d.is_infinite());
+        if !indeterminate { return None; }
+        num = symbolic::differentiate(&num, var, env)?;
+        den = symbolic::differentiate(&den, var, env)?;
+    }
+    None
+}
+
+fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() <= AGREEMENT * a.abs().max(b.abs()).max(1.0)
+}
+
+// a numeric limit within its own uncertainty of an integer is reported as that integer
+fn snap(v: f64, uncertainty: f64) -> f64 {
+    // adding 0.0 turns -0 into 0
+    if (v - v.round()).abs() <= uncertainty.max(1e-12 * v.abs().max(1.0)) { v.round() + 0.0 } else { v }
+}
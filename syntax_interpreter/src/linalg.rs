@@ -0,0 +1,41 @@
+// Dense linear algebra helpers shared by the numeric built-ins
+
+/// Solve `a x = b` by Gaussian elimination with partial pivoting.
+/// Returns `None` when the matrix is singular to working precision.
+pub fn solve(mut a: Vec<Vec<f64>>, mut b: Vec<f64>) -> Option<Vec<f64>> {
+    let n: usize = b.len();
+    let scale: f64 = a.iter().flatten().fold(0.0f64, |m, x| m.max(x.abs()));
+    if scale == 0.0 { return if n == 0 { Some(b) } else { None }; }
+    for col in 0..n {
+        let pivot: usize = (col..n).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+        if a[pivot][col].abs() <= scale * 1e-13 { return None; }
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+        for row in col + 1..n {
+            let factor: f64 = a[row][col] / a[col][col];
+            if factor == 0.0 { continue; }
+            let (upper, lower) = a.split_at_mut(row);
+            for (x, p) in lower[0][col..].iter_mut().zip(&upper[col][col..]) {
+                *x -= factor * p;
+            }
+            b[row] -= factor * b[col];
+        }
+    }
+    let mut x: Vec<f64> = vec![0.0; n];
+    for row in (0..n).rev() {
+        let s: f64 = (row + 1..n).map(|k| a[row][k] * x[k]).sum();
+        x[row] = (b[row] - s) / a[row][row];
+    }
+    Some(x)
+}
+
+/// Inverse of a square matrix, column by column. `None` when it is singular.
+pub fn inverse(a: &[Vec<f64>]) -> Option<Vec<Vec<f64>>> {
+    let n: usize = a.len();
+    let mut columns: Vec<Vec<f64>> = Vec::with_capacity(n);
+    for j in 0..n {
+        let e: Vec<f64> = (0..n).map(|i| if i == j { 1.0 } else { 0.0 }).collect();
+        columns.push(solve(a.to_vec(), e)?);
+    }
+    Some((0..n).map(|i| columns.iter().map(|c| c[i]).collect()).collect())
+}
@@ -0,0 +1,129 @@
+// Leveled logging for log("LEVEL", message...) statements: filtering, timestamps, JSON records and log files
+
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::Write;
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::output::{json_string, Output};
+
+/// How important a log message is, from least to most.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl Level {
+    /// DEBUG, INFO, WARN (or WARNING) and ERROR, in any case.
+    pub fn parse(s: &str) -> Option<Level> {
+        match s.to_ascii_uppercase().as_str() {
+            "DEBUG" => Some(Level::Debug),
+            "INFO" => Some(Level::Info),
+            "WARN" | "WARNING" => Some(Level::Warn),
+            "ERROR" => Some(Level::Error),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Level::Debug => "DEBUG",
+            Level::Info => "INFO",
+            Level::Warn => "WARN",
+            Level::Error => "ERROR",
+        }
+    }
+}
+
+/// How a log record is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    // 2025-01-31T12:00:00.000Z [INFO] message
+    Text,
+    // {"time": "2025-01-31T12:00:00.000Z", "level": "INFO", "message": "message"}
+    Json,
+}
+
+/// Which log messages are kept and where they go.
+#[derive(Clone, Default)]
+pub struct Logger(Rc<RefCell<Config>>);
+
+struct Config {
+    // None turns logging off
+    min: Option<Level>,
+    format: LogFormat,
+    // records go here instead of the output when set
+    file: Option<File>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config { min: Some(Level::Info), format: LogFormat::Text, file: None }
+    }
+}
+
+impl Logger {
+    /// Keep messages at `level` and above; `None` drops them all.
+    pub fn set_level(&self, level: Option<Level>) {
+        self.0.borrow_mut().min = level;
+    }
+
+    pub fn set_format(&self, format: LogFormat) {
+        self.0.borrow_mut().format = format;
+    }
+
+    /// Append records to the file at `path`, creating it if needed.
+    pub fn set_file(&self, path: &str) -> Result<(), String> {
+        let file: File = std::fs::OpenOptions::new().create(true).append(true).open(path)
+            .map_err(|e| format!("cannot open log file '{}': {}", path, e))?;
+        self.0.borrow_mut().file = Some(file);
+        Ok(())
+    }
+
+    /// Write `message` at `level` to the log file, or to `output` when there is none.
+    /// Without access to the clock the record has no time.
+    pub fn log(&self, level: Level, message: &str, output: &Output, clock: bool) {
+        let mut config = self.0.borrow_mut();
+        if config.min.is_none_or(|min| level < min) { return; }
+        let time: Option<String> = if clock { Some(timestamp(SystemTime::now())) } else { None };
+        let record: String = match config.format {
+            LogFormat::Text => match time {
+                Some(t) => format!("{} [{}] {}", t, level.name(), message),
+                None => format!("[{}] {}", level.name(), message),
+            },
+            LogFormat::Json => {
+                let time: String = time.map(|t| format!("\"time\": {}, ", json_string(&t))).unwrap_or_default();
+                format!("{{{}\"level\": {}, \"message\": {}}}", time, json_string(level.name()), json_string(message))
+            }
+        };
+        match &mut config.file {
+            Some(file) => {
+                if let Err(e) = writeln!(file, "{}", record) { output.error(&format!("ERROR: log: {}", e)); }
+            }
+            None => output.log(&record),
+        }
+    }
+}
+
+/// `t` in UTC as 2025-01-31T12:00:00.000Z.
+pub fn timestamp(t: SystemTime) -> String {
+    let since: std::time::Duration = t.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs: u64 = since.as_secs();
+    let (days, rem): (i64, u64) = ((secs / 86_400) as i64, secs % 86_400);
+    // days since 1970-01-01 to a civil date (Howard Hinnant's algorithm)
+    let z: i64 = days + 719_468;
+    let era: i64 = z.div_euclid(146_097);
+    let doe: i64 = z - era * 146_097;
+    let yoe: i64 = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy: i64 = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp: i64 = (5 * doy + 2) / 153;
+    let day: i64 = doy - (153 * mp + 2) / 5 + 1;
+    let month: i64 = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year: i64 = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year, month, day, rem / 3600, rem % 3600 / 60, rem % 60, since.subsec_millis())
+}
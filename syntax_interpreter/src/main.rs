@@ -2,16 +2,181 @@ mod lexer;
 mod parser;
 mod ast;
 mod evaluator;
+mod arrays;
+mod stats;
+mod random;
+mod poly;
+mod symbolic;
+mod series;
+mod linalg;
+mod calculus;
+mod limits;
+mod inverse;
+mod optimize;
+mod signal;
+mod sets;
+mod strings;
+mod display;
+mod modules;
+mod output;
+mod logging;
+mod sandbox;
+mod bytecode;
+mod passes;
+mod memo;
+// the embedding API is only used through the library
+#[allow(dead_code)]
+mod interpreter;
 
 use std::env;
 use std::fs;
 use evaluator::Env;
 
+// scripts run on a thread with this much stack, so that they can recurse deeply
+const STACK_SIZE: usize = 256 << 20;
+
 fn main() {
+    let runner = std::thread::Builder::new().stack_size(STACK_SIZE).spawn(run).expect("cannot start the interpreter thread");
+    if runner.join().is_err() { std::process::exit(101); }
+}
+
+fn run() {
     let mut args: env::Args = env::args();
     let _program: Option<String> = (&mut args).next();
-    let Some(path) = (&mut args).next() else {
-        eprintln!("usage: syntax_interpreter <file.ms>");
+    let mut path: Option<String> = None;
+    let mut seed: Option<u64> = None;
+    let mut json: bool = false;
+    let logger: logging::Logger = logging::Logger::default();
+    let format: display::SharedFormat = display::SharedFormat::default();
+    // leave room for the frames of a call the limit check has not seen yet
+    let mut limits: sandbox::Limits = sandbox::Limits { max_stack: Some(STACK_SIZE - (16 << 20)), ..sandbox::Limits::default() };
+    let mut log_file: Option<String> = None;
+    // --sandbox denies whatever the --allow-* flags do not grant
+    let mut sandboxed: bool = false;
+    let (mut read, mut write, mut clock): (Option<sandbox::Access>, Option<sandbox::Access>, Option<bool>) = (None, None, None);
+    let mut optimized: bool = false;
+    let mut emit: bool = false;
+    let mut no_vm: bool = false;
+    let mut memo: bool = false;
+    while let Some(arg) = args.next() {
+        // --seed N or --seed=N: make rand() and friends reproducible
+        let value: Option<String> = if arg == "--seed" { args.next() } else { arg.strip_prefix("--seed=").map(str::to_string) };
+        if arg == "--seed" || arg.starts_with("--seed=") {
+            match value.as_deref().and_then(|v| v.parse::<i64>().ok()) {
+                Some(n) => seed = Some(n as u64),
+                None => {
+                    eprintln!("--seed expects an integer");
+                    std::process::exit(1);
+                }
+            }
+        } else if arg == "--show-std" {
+            // --show-std std/math: print the source of a bundled module
+            let name: String = args.next().unwrap_or_default();
+            match modules::std_source(&name) {
+                Some(src) => print!("{}", src),
+                None => {
+                    let names: Vec<&str> = modules::STD.iter().map(|(n, _)| *n).collect();
+                    eprintln!("--show-std expects one of {}", names.join(", "));
+                    std::process::exit(1);
+                }
+            }
+            return;
+        } else if arg == "--output" || arg.starts_with("--output=") {
+            // --output json: one JSON object per printed value, log message or error, on stdout
+            let mode: Option<String> = if arg == "--output" { args.next() } else { arg.strip_prefix("--output=").map(str::to_string) };
+            match mode.as_deref() {
+                Some("text") => json = false,
+                Some("json") => json = true,
+                _ => {
+                    eprintln!("--output expects text or json");
+                    std::process::exit(1);
+                }
+            }
+        } else if arg == "--log-level" || arg.starts_with("--log-level=") {
+            // --log-level warn: drop log messages below WARN; off drops them all
+            let level: Option<String> = if arg == "--log-level" { args.next() } else { arg.strip_prefix("--log-level=").map(str::to_string) };
+            match level.as_deref() {
+                Some("off") => logger.set_level(None),
+                Some(l) if logging::Level::parse(l).is_some() => logger.set_level(logging::Level::parse(l)),
+                _ => {
+                    eprintln!("--log-level expects debug, info, warn, error or off");
+                    std::process::exit(1);
+                }
+            }
+        } else if arg == "--log-format" || arg.starts_with("--log-format=") {
+            let format: Option<String> = if arg == "--log-format" { args.next() } else { arg.strip_prefix("--log-format=").map(str::to_string) };
+            match format.as_deref() {
+                Some("text") => logger.set_format(logging::LogFormat::Text),
+                Some("json") => logger.set_format(logging::LogFormat::Json),
+                _ => {
+                    eprintln!("--log-format expects text or json");
+                    std::process::exit(1);
+                }
+            }
+        } else if arg == "--log-file" || arg.starts_with("--log-file=") {
+            // --log-file run.log: append log messages to a file instead of stderr
+            log_file = if arg == "--log-file" { args.next() } else { arg.strip_prefix("--log-file=").map(str::to_string) };
+        } else if arg == "--no-vm" {
+            // --no-vm: walk the syntax tree of function bodies instead of compiling them, to compare
+            no_vm = true;
+        } else if arg == "--memo" {
+            // --memo: remember the results of every pure function, not only those marked @memo
+            memo = true;
+        } else if arg == "-O" {
+            // -O: fold constants, inline small functions and hoist invariant code before running
+            optimized = true;
+        } else if arg == "--emit=optimized-ast" {
+            // --emit=optimized-ast: print the syntax tree -O runs instead of running it
+            optimized = true;
+            emit = true;
+        } else if arg == "--sandbox" {
+            sandboxed = true;
+        } else if arg == "--allow-read" || arg == "--allow-write" {
+            *(if arg == "--allow-read" { &mut read } else { &mut write }) = Some(sandbox::Access::All);
+        } else if let Some((flag, paths)) = arg.strip_prefix("--allow-read=").map(|p| ("read", p))
+            .or_else(|| arg.strip_prefix("--allow-write=").map(|p| ("write", p))) {
+            // --allow-read=./lib,data: only these files and directories
+            let access: &mut Option<sandbox::Access> = if flag == "read" { &mut read } else { &mut write };
+            let access: &mut sandbox::Access = access.get_or_insert_with(sandbox::Access::none);
+            for p in paths.split(',').filter(|p| !p.is_empty()) { access.allow(std::path::Path::new(p)); }
+        } else if arg == "--deny-read" {
+            read = Some(sandbox::Access::none());
+        } else if arg == "--deny-write" {
+            write = Some(sandbox::Access::none());
+        } else if arg == "--allow-clock" || arg == "--deny-clock" {
+            clock = Some(arg == "--allow-clock");
+        } else if let Some(flag) = ["--max-steps", "--max-depth", "--max-array", "--max-memo", "--timeout"].into_iter()
+            .find(|f| arg == *f || arg.strip_prefix(*f).is_some_and(|rest| rest.starts_with('='))) {
+            // --max-steps N, --max-depth N, --max-array N, --max-memo N, --timeout SECONDS: limits for untrusted scripts
+            let value: Option<String> = if arg == flag { args.next() } else { arg.split_once('=').map(|(_, v)| v.to_string()) };
+            let Some(n) = value.as_deref().and_then(|v| v.parse::<f64>().ok()).filter(|n| *n >= 0.0 && n.is_finite()) else {
+                eprintln!("{} expects a non-negative number", flag);
+                std::process::exit(1);
+            };
+            match flag {
+                "--max-steps" => limits.max_steps = Some(n as u64),
+                "--max-depth" => limits.max_depth = Some(n as usize),
+                "--max-array" => limits.max_array_len = Some(n as usize),
+                "--max-memo" => limits.max_memo = Some(n as usize),
+                _ => limits.timeout = Some(std::time::Duration::from_secs_f64(n)),
+            }
+        } else if arg == "--format" || arg.starts_with("--format=") {
+            // --format fixed:3, --format=snap, ...: the same settings as the format directive
+            let spec: Option<String> = if arg == "--format" { args.next() } else { arg.strip_prefix("--format=").map(str::to_string) };
+            if let Err(msg) = apply_format(&format, spec.as_deref().unwrap_or("")) {
+                eprintln!("--format: {}", msg);
+                std::process::exit(1);
+            }
+        } else {
+            path = Some(arg);
+        }
+    }
+    let Some(path) = path else {
+        eprintln!("usage: syntax_interpreter [--seed N] [--format SETTING[:VALUE]] [--output text|json] [--no-vm] [-O] [--memo]
+                          [--log-level LEVEL] [--log-format text|json] [--log-file PATH]
+                          [--max-steps N] [--max-depth N] [--max-array N] [--max-memo N] [--timeout SECONDS]
+                          [--sandbox] [--allow-read[=PATHS]] [--allow-write[=PATHS]] [--allow-clock]
+                          [--deny-read] [--deny-write] [--deny-clock] [--emit=optimized-ast] <file.ms>\n       syntax_interpreter --show-std <module>");
         std::process::exit(1);
     };
 
@@ -24,17 +189,65 @@ fn main() {
     };
 
     let mut env: Env = Env::with_builtins();
+    if let Some(seed) = seed { env.shared.rng.reseed(seed); }
+    let defaults: sandbox::Capabilities = if sandboxed { sandbox::Capabilities::none() } else { sandbox::Capabilities::default() };
+    let capabilities: sandbox::Capabilities = sandbox::Capabilities {
+        read: read.unwrap_or(defaults.read),
+        write: write.unwrap_or(defaults.write),
+        clock: clock.unwrap_or(defaults.clock),
+    };
+    if let Some(file) = log_file {
+        // the log file is written on the script's behalf
+        if let Err(msg) = capabilities.check_write(std::path::Path::new(&file)).and_then(|()| logger.set_file(&file)) {
+            eprintln!("--log-file: {}", msg);
+            std::process::exit(1);
+        }
+    }
+    env.shared.logger = logger;
+    env.shared.format = format;
+    env.shared.vm.set_enabled(!no_vm);
+    env.shared.memo.set_auto(memo);
+    env.shared.sandbox.set_limits(limits);
+    env.shared.sandbox.set_capabilities(capabilities);
+    if json { env.shared.output = output::Output::new(output::JsonLines(std::io::stdout())); }
+    env.shared.modules.enter_script(std::path::Path::new(&path));
     // Process the entire file as a single block (semicolon as separator)
     let src_trimmed = src.trim();
     if !src_trimmed.is_empty() {
         let tokens: Vec<lexer::Token> = lexer::tokenize(src_trimmed);
-    let ast: ast::AstNode = parser::parse(&tokens);
+    let mut ast: ast::AstNode = parser::parse(&tokens);
     #[cfg(feature = "debug-ast")]
     eprintln!("[DEBUG] AST: {:?}", ast);
+    if optimized { ast = passes::optimize(ast); }
+    if emit {
+        // one statement per line
+        if let ast::AstNode::Program(stmts) = &ast { for stmt in stmts { println!("{:?}", stmt); } }
+        return;
+    }
+    env.shared.sandbox.reset();
     let _ = evaluator::eval(&ast, &mut env);
     }
+    // a script stopped by a limit fails
+    if let Some(msg) = env.shared.sandbox.exceeded() {
+        env.shared.output.error(&format!("ERROR: {}", msg));
+        std::process::exit(1);
+    }
 }
 
+/// Apply a `--format` flag such as `sig:6`, `snap:1e-9` or `fraction:off`.
+fn apply_format(format: &display::SharedFormat, spec: &str) -> Result<(), String> {
+    let (name, value) = match spec.split_once(':') {
+        Some((name, value)) => (name, Some(value)),
+        None => (spec, None),
+    };
+    let setting: display::Setting = match value {
+        None => display::Setting::None,
+        Some("on") => display::Setting::Switch(true),
+        Some("off") => display::Setting::Switch(false),
+        Some(v) => display::Setting::Number(v.parse::<f64>().map_err(|_| format!("'{}' is not a number", v))?),
+    };
+    format.apply(name, setting)
+}
 
 fn is_input_complete(src: &str) -> bool {
     let bytes = src.as_bytes();
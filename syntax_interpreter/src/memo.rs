@@ -0,0 +1,217 @@
+// Memoisation of user functions: which functions are pure, and the results remembered for the
+// pure ones, keyed by their argument values
+
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use crate::ast::AstNode;
+use crate::evaluator::{is_builtin, Env, Function, Value};
+use crate::modules::ModuleScope;
+
+/// Whether every pure function is memoised, and the generation of remembered results.
+#[derive(Clone, Default)]
+pub struct MemoSettings(Rc<Settings>);
+
+#[derive(Default)]
+struct Settings {
+    auto: Cell<bool>,
+    // advanced whenever functions are defined or imported, which can change what a remembered call would do
+    generation: Cell<u64>,
+}
+
+impl MemoSettings {
+    /// Memoise every pure function, not only those marked `@memo`. Applies to functions defined afterwards.
+    pub fn set_auto(&self, on: bool) {
+        self.0.auto.set(on);
+    }
+
+    pub fn auto(&self) -> bool {
+        self.0.auto.get()
+    }
+
+    /// Forget every remembered result, because a function may have been defined or replaced.
+    pub fn invalidate(&self) {
+        self.0.generation.set(self.0.generation.get() + 1);
+    }
+
+    fn generation(&self) -> u64 {
+        self.0.generation.get()
+    }
+}
+
+/// What a call of `f` may do besides computing its result from its arguments, such as `print` or
+/// `rand`; `None` if it is pure. Functions it calls or refers to are looked at too.
+pub fn impurity(f: &Function, env: &Env) -> Option<String> {
+    Purity { env, seen: HashSet::new() }.function(f)
+}
+
+/// The results of one function, shared by its copies.
+#[derive(Clone)]
+pub struct Memo(Rc<RefCell<Cache>>);
+
+struct Cache {
+    name: String,
+    // marked @memo, so that being impure is an error rather than a reason not to remember
+    explicit: bool,
+    generation: u64,
+    // found on the first call: whether the function is pure, or what makes it impure
+    pure: Option<Result<(), String>>,
+    results: HashMap<String, Value>,
+}
+
+impl Memo {
+    pub fn new(name: &str, explicit: bool, settings: &MemoSettings) -> Memo {
+        Memo(Rc::new(RefCell::new(Cache {
+            name: name.to_string(),
+            explicit,
+            generation: settings.generation(),
+            pure: None,
+            results: HashMap::new(),
+        })))
+    }
+
+    /// The result of calling `f` with `args`: remembered, or computed by `run` and remembered if
+    /// `f` is pure, the arguments are plain data and the cache has room.
+    pub fn call(&self, f: &Function, args: &[Value], env: &mut Env, run: impl FnOnce(&mut Env) -> Value) -> Value {
+        let generation: u64 = env.shared.memo.generation();
+        let pure: Option<Result<(), String>> = {
+            let mut cache = self.0.borrow_mut();
+            if cache.generation != generation {
+                cache.generation = generation;
+                cache.pure = None;
+                cache.results.clear();
+            }
+            cache.pure.clone()
+        };
+        let pure: Result<(), String> = pure.unwrap_or_else(|| {
+            let pure: Result<(), String> = impurity(f, env).map_or(Ok(()), Err);
+            self.0.borrow_mut().pure = Some(pure.clone());
+            pure
+        });
+        if let Err(what) = pure {
+            let cache = self.0.borrow();
+            if cache.explicit { return Value::Str(format!("ERROR: {}: @memo needs a pure function, but it may call {}", cache.name, what)); }
+            drop(cache);
+            return run(env);
+        }
+        let Some(key) = key(args) else { return run(env) };
+        if let Some(v) = self.0.borrow().results.get(&key) { return v.clone(); }
+        let v: Value = run(env);
+        // an error may come from a limit rather than from the arguments
+        let failed: bool = matches!(&v, Value::Str(s) if s.starts_with("ERROR:")) || env.shared.sandbox.exceeded().is_some();
+        let mut cache = self.0.borrow_mut();
+        if !failed && env.shared.sandbox.max_memo().is_none_or(|max| cache.results.len() < max) {
+            cache.results.insert(key, v.clone());
+        }
+        v
+    }
+}
+
+impl std::fmt::Debug for Memo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Memo({} results)", self.0.borrow().results.len())
+    }
+}
+
+// the arguments as a key, if they are plain data that compare by value
+fn key(args: &[Value]) -> Option<String> {
+    fn data(v: &Value) -> bool {
+        match v {
+            Value::Number(_) | Value::Bool(_) | Value::Complex(_) | Value::Str(_) | Value::Unit => true,
+            Value::Array(items) | Value::Tuple(items) | Value::Set(items) => items.iter().all(data),
+            Value::Record(fields) => fields.iter().all(|(_, v)| data(v)),
+            _ => false,
+        }
+    }
+    args.iter().all(data).then(|| format!("{:?}", args))
+}
+
+struct Purity<'a> {
+    env: &'a Env,
+    // the functions looked at so far, by module and name, so that recursion ends
+    seen: HashSet<(usize, String)>,
+}
+
+impl Purity<'_> {
+    fn function(&mut self, f: &Function) -> Option<String> {
+        match &f.module {
+            Some(scope) => self.node(&f.body, &f.params, &scope.funcs(), scope.id()),
+            None => {
+                let env: &Env = self.env;
+                self.node(&f.body, &f.params, &env.funcs, 0)
+            }
+        }
+    }
+
+    // the function called `name` in code that sees `funcs`, resolved as a call would resolve it
+    fn call(&mut self, name: &str, funcs: &HashMap<String, Function>, scope: usize) -> Option<String> {
+        if let Some(f) = funcs.get(name) {
+            if !self.seen.insert((scope, name.to_string())) { return None; }
+            return self.function(f);
+        }
+        // what a function of the embedding program does is unknown
+        if self.env.shared.natives.contains(name) || name == "print" || crate::random::UNMEMOISABLE.contains(&name) {
+            return Some(name.to_string());
+        }
+        if is_builtin(name) { return None; }
+        let f: Function = crate::modules::prelude(name, self.env)?;
+        if !self.seen.insert((f.module.as_ref().map_or(0, ModuleScope::id), name.to_string())) { return None; }
+        self.function(&f)
+    }
+
+    // what `node` may do, where `bound` are the names of values rather than functions
+    fn node(&mut self, node: &AstNode, bound: &[String], funcs: &HashMap<String, Function>, scope: usize) -> Option<String> {
+        let bind = |var: &String| -> Vec<String> { [bound, std::slice::from_ref(var)].concat() };
+        match node {
+            AstNode::Print(_) => Some("print".to_string()),
+            AstNode::Log { .. } => Some("log".to_string()),
+            // log("INFO", ...) in an expression logs; log(x) is the logarithm
+            AstNode::FunctionCall { name, args } if name == "log" && !funcs.contains_key(name)
+                && matches!(args.first(), Some(AstNode::Str(_) | AstNode::Template(_))) => Some("log".to_string()),
+            AstNode::Import { .. } => Some("import".to_string()),
+            AstNode::Format { .. } => Some("format".to_string()),
+            // a function given as an argument is part of the key, so calling it is not remembered; a
+            // defined function of the same name is called instead, though
+            AstNode::FunctionCall { name, args } | AstNode::DerivativeCall { name, args, .. } => {
+                args.iter().find_map(|a| self.node(a, bound, funcs, scope)).or_else(|| {
+                    if bound.contains(name) && !funcs.contains_key(name) { None } else { self.call(name, funcs, scope) }
+                })
+            }
+            // a function passed by name, e.g. map(f, xs)
+            AstNode::Variable(name) | AstNode::Constant(name) if !bound.contains(name) => self.call(name, funcs, scope),
+            AstNode::Lambda { params, body } => self.node(body, &[bound, &params[..]].concat(), funcs, scope),
+            AstNode::SetBuilder { var, source, condition } => {
+                let inner: Vec<String> = bind(var);
+                self.node(source, bound, funcs, scope).or_else(|| self.node(condition, &inner, funcs, scope))
+            }
+            AstNode::Lim { var, to, expr, .. } => {
+                let inner: Vec<String> = bind(var);
+                self.node(to, bound, funcs, scope).or_else(|| self.node(expr, &inner, funcs, scope))
+            }
+            AstNode::Series { var, at, order, expr } => {
+                let inner: Vec<String> = bind(var);
+                self.node(at, bound, funcs, scope)
+                    .or_else(|| order.as_ref().and_then(|o| self.node(o, bound, funcs, scope)))
+                    .or_else(|| self.node(expr, &inner, funcs, scope))
+            }
+            AstNode::DerivativeExpr { var, expr, .. } => self.node(expr, &bind(var), funcs, scope),
+            _ => children(node).into_iter().find_map(|c| self.node(c, bound, funcs, scope)),
+        }
+    }
+}
+
+// the parts of a node that bind no names
+fn children(node: &AstNode) -> Vec<&AstNode> {
+    match node {
+        AstNode::Program(items) | AstNode::Template(items) | AstNode::Array(items) | AstNode::Tuple(items)
+        | AstNode::Set(items) | AstNode::Chain { operands: items, .. } => items.iter().collect(),
+        AstNode::Record(fields) => fields.iter().map(|(_, e)| e).collect(),
+        AstNode::UnaryOp { expr, .. } | AstNode::Field { expr, .. } | AstNode::Assignment { expr, .. }
+        | AstNode::Destructure { expr, .. } => vec![expr],
+        AstNode::BinaryOp { left, right, .. } => vec![left, right],
+        AstNode::Conditional { condition, body } => vec![condition, body],
+        AstNode::CallExpr { callee, args } => std::iter::once(&**callee).chain(args).collect(),
+        _ => Vec::new(),
+    }
+}
@@ -0,0 +1,289 @@
+// Module loading for import statements: path resolution, caching, namespaces and cycle detection,
+// and the standard library of MS modules bundled with the interpreter
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::ast::AstNode;
+use crate::evaluator::{eval, values_equal, Env, Function, Value};
+
+/// The standard library, written in MS. A file of the same name found on the search path
+/// (next to the script or in MS_PATH) is used instead, so any of these can be overridden.
+pub const STD: &[(&str, &str)] = &[
+    ("std/math", include_str!("../std/math.ms")),
+    ("std/geometry", include_str!("../std/geometry.ms")),
+    ("std/finance", include_str!("../std/finance.ms")),
+    ("std/physics", include_str!("../std/physics.ms")),
+];
+
+/// The source of a bundled module, e.g. `std_source("std/math")`.
+pub fn std_source(name: &str) -> Option<&'static str> {
+    let name: &str = name.strip_suffix(".ms").unwrap_or(name);
+    STD.iter().find(|(n, _)| *n == name).map(|(_, src)| *src)
+}
+
+/// Modules loaded so far and the chain of files being loaded.
+#[derive(Clone, Default)]
+pub struct SharedModules(Rc<RefCell<Modules>>);
+
+#[derive(Default)]
+struct Modules {
+    // canonical path -> exported names, in the order they are listed
+    cache: HashMap<PathBuf, Rc<Vec<(String, Value)>>>,
+    // the script and the modules currently being evaluated, outermost first
+    stack: Vec<PathBuf>,
+    // function name -> the bundled module defining it, built on first use
+    std_index: Option<HashMap<String, &'static str>>,
+    // bundled modules that failed to load, whose error has been reported
+    std_failed: HashSet<&'static str>,
+    // directories searched after the importing file's own; MS_PATH unless set
+    search_path: Option<Vec<PathBuf>>,
+}
+
+// where a module's source comes from; bundled modules are keyed by a path under this prefix
+enum Source {
+    File(PathBuf),
+    Bundled(&'static str, &'static str),
+}
+
+const BUNDLED: &str = "<bundled>";
+
+/// The functions of one module, so that its functions can call each other wherever they are used.
+#[derive(Clone)]
+pub struct ModuleScope(Rc<RefCell<HashMap<String, Function>>>);
+
+impl ModuleScope {
+    pub fn funcs(&self) -> HashMap<String, Function> {
+        self.0.borrow().clone()
+    }
+
+    /// An identifier for the module, the same for every copy of the scope.
+    pub fn id(&self) -> usize {
+        Rc::as_ptr(&self.0) as usize
+    }
+
+    /// Apply `f` to the function called `name`, without copying it.
+    pub fn with<R>(&self, name: &str, f: impl FnOnce(&Function) -> R) -> Option<R> {
+        self.0.borrow().get(name).map(f)
+    }
+}
+
+impl std::fmt::Debug for ModuleScope {
+    // the scope refers back to the functions that hold it
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ModuleScope({} functions)", self.0.borrow().len())
+    }
+}
+
+/// What an import statement brings into the importing environment.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportKind {
+    // import "m.ms": every name
+    All,
+    // import "m.ms" as m: a record holding every name
+    Namespace(String),
+    // from "m.ms" import a, b
+    Names(Vec<String>),
+}
+
+impl SharedModules {
+    /// Record the script being run, so that its imports resolve next to it.
+    pub fn enter_script(&self, path: &Path) {
+        let path: PathBuf = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        self.0.borrow_mut().stack.push(path);
+    }
+
+    /// Search `dirs` for imports instead of the directories listed in MS_PATH.
+    pub fn set_search_path(&self, dirs: impl IntoIterator<Item = PathBuf>) {
+        self.0.borrow_mut().search_path = Some(dirs.into_iter().collect());
+    }
+
+    fn search_path(&self) -> Vec<PathBuf> {
+        if let Some(dirs) = &self.0.borrow().search_path { return dirs.clone(); }
+        std::env::var_os("MS_PATH").map(|p| std::env::split_paths(&p).collect()).unwrap_or_default()
+    }
+
+    fn current_dir(&self) -> Option<PathBuf> {
+        self.0.borrow().stack.last().and_then(|p| p.parent().map(Path::to_path_buf))
+    }
+}
+
+/// Run an import statement in `env`.
+pub fn import(path: &str, kind: &ImportKind, env: &mut Env) -> Result<(), String> {
+    let exports: Rc<Vec<(String, Value)>> = load(resolve(path, env)?, env)?;
+    match kind {
+        ImportKind::All => {
+            for (name, value) in exports.iter() { bind(env, name, value); }
+        }
+        ImportKind::Namespace(alias) => {
+            env.vars.insert(alias.clone(), Value::Record(exports.to_vec()));
+        }
+        ImportKind::Names(names) => {
+            for name in names {
+                let Some((_, value)) = exports.iter().find(|(n, _)| n == name) else {
+                    return Err(format!("import: '{}' does not define '{}'", path, name));
+                };
+                bind(env, name, value);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn bind(env: &mut Env, name: &str, value: &Value) {
+    match value {
+        Value::Function(f) => { env.funcs.insert(name.to_string(), f.clone()); }
+        other => { env.vars.insert(name.to_string(), other.clone()); }
+    }
+}
+
+/// A function of the standard library that the script has not defined itself, loading its
+/// module the first time one of the module's functions is used.
+pub fn prelude(name: &str, env: &Env) -> Option<Function> {
+    let module: &str = {
+        let mut modules = env.shared.modules.0.borrow_mut();
+        let index = modules.std_index.get_or_insert_with(std_index);
+        index.get(name).copied()?
+    };
+    let exports: Rc<Vec<(String, Value)>> = match resolve(module, env).and_then(|source| load(source, env)) {
+        Ok(exports) => exports,
+        Err(msg) => {
+            // said once, so that a broken library module is not taken for a missing function
+            if env.shared.modules.0.borrow_mut().std_failed.insert(module) {
+                env.shared.output.error(&format!("ERROR: {}: {}", module, msg));
+            }
+            return None;
+        }
+    };
+    exports.iter().find_map(|(n, v)| match v {
+        Value::Function(f) if n == name => Some(f.clone()),
+        _ => None,
+    })
+}
+
+// the functions each bundled module defines, read from its source without running it
+fn std_index() -> HashMap<String, &'static str> {
+    let mut index: HashMap<String, &'static str> = HashMap::new();
+    for (module, src) in STD {
+        if let AstNode::Program(stmts) = crate::parser::parse(&crate::lexer::tokenize(src)) {
+            for stmt in stmts {
+                if let AstNode::FunctionDef { name, .. } = stmt { index.insert(name, *module); }
+            }
+        }
+    }
+    index
+}
+
+/// Find `path` next to the importing file (or in the working directory for a script read
+/// from elsewhere), then in each directory of the search path (MS_PATH unless the embedding
+/// program set one), then among the bundled modules.
+/// A missing extension means `.ms`. Files the environment may not read are passed over.
+fn resolve(path: &str, env: &Env) -> Result<Source, String> {
+    let mut names: Vec<PathBuf> = vec![PathBuf::from(path)];
+    if Path::new(path).extension().is_none() { names.push(PathBuf::from(format!("{}.ms", path))); }
+    let dirs: Vec<PathBuf> = if Path::new(path).is_absolute() {
+        vec![PathBuf::new()]
+    } else {
+        let mut dirs: Vec<PathBuf> = vec![env.shared.modules.current_dir().unwrap_or_default()];
+        dirs.extend(env.shared.modules.search_path());
+        dirs
+    };
+    let mut denied: Option<(String, &PathBuf)> = None;
+    for d in &dirs {
+        for name in &names {
+            let candidate: PathBuf = d.join(name);
+            // checked before looking at the file, so that a denied path does not tell whether it exists
+            if let Err(msg) = env.shared.sandbox.check_read(&candidate) {
+                denied.get_or_insert((msg, d));
+                continue;
+            }
+            if candidate.is_file() { return Ok(Source::File(candidate)); }
+        }
+    }
+    let bare: &str = path.strip_suffix(".ms").unwrap_or(path);
+    if let Some((name, src)) = STD.iter().find(|(n, _)| *n == bare) {
+        return Ok(Source::Bundled(name, src));
+    }
+    if let Some((msg, d)) = denied {
+        // name the file as it would be read, with its extension
+        let msg: String = names.last().and_then(|name| env.shared.sandbox.check_read(&d.join(name)).err()).unwrap_or(msg);
+        return Err(format!("import: {}", msg));
+    }
+    if Path::new(path).is_absolute() { return Err(format!("import: cannot find '{}'", path)); }
+    let searched: Vec<String> = dirs.iter()
+        .map(|d| if d.as_os_str().is_empty() { ".".to_string() } else { d.display().to_string() })
+        .collect();
+    Err(format!("import: cannot find '{}' (searched {})", path, searched.join(", ")))
+}
+
+/// Evaluate a module once and return its exports; later imports reuse them.
+fn load(source: Source, env: &Env) -> Result<Rc<Vec<(String, Value)>>, String> {
+    let key: PathBuf = match &source {
+        Source::File(file) => file.canonicalize().map_err(|e| format!("import: cannot read '{}': {}", file.display(), e))?,
+        Source::Bundled(name, _) => Path::new(BUNDLED).join(format!("{}.ms", name)),
+    };
+    let shared: SharedModules = env.shared.modules.clone();
+    if let Some(exports) = shared.0.borrow().cache.get(&key) { return Ok(exports.clone()); }
+    if shared.0.borrow().stack.contains(&key) {
+        let modules = shared.0.borrow();
+        let start: usize = modules.stack.iter().position(|p| *p == key).unwrap_or(0);
+        let chain: Vec<String> = modules.stack[start..].iter().chain(std::iter::once(&key)).map(|p| short_name(p)).collect();
+        return Err(format!("import: cyclic import {}", chain.join(" -> ")));
+    }
+    let src: String = match source {
+        Source::File(_) => std::fs::read_to_string(&key).map_err(|e| format!("import: cannot read '{}': {}", key.display(), e))?,
+        Source::Bundled(_, src) => src.to_string(),
+    };
+
+    // a module runs in a fresh environment of its own
+    let mut module: Env = Env::with_shared(env.shared.clone());
+    shared.0.borrow_mut().stack.push(key.clone());
+    let ast: AstNode = crate::parser::parse(&crate::lexer::tokenize(&src));
+    let result: Result<(), String> = run(&ast, &mut module);
+    shared.0.borrow_mut().stack.pop();
+    result?;
+
+    let exports: Rc<Vec<(String, Value)>> = Rc::new(exports(module));
+    shared.0.borrow_mut().cache.insert(key, exports.clone());
+    Ok(exports)
+}
+
+/// Evaluate a module's statements like a script, except that a failed import fails the module,
+/// so that the error reaches the script with the whole chain.
+fn run(ast: &AstNode, module: &mut Env) -> Result<(), String> {
+    let AstNode::Program(stmts) = ast else { eval(ast, module); return Ok(()) };
+    // functions are defined before anything runs, as in a script
+    for stmt in stmts.iter().filter(|s| matches!(s, AstNode::FunctionDef { .. })) { eval(stmt, module); }
+    for stmt in stmts.iter().filter(|s| !matches!(s, AstNode::FunctionDef { .. })) {
+        match stmt {
+            AstNode::Import { path, kind } => import(path, kind, module)?,
+            other => { eval(other, module); }
+        }
+    }
+    Ok(())
+}
+
+/// The variables a module defined (built-in constants it left alone are not exported)
+/// and its functions, each tied to the module's own functions.
+fn exports(module: Env) -> Vec<(String, Value)> {
+    let defaults: HashMap<String, Value> = crate::evaluator::constants();
+    let mut vars: Vec<(String, Value)> = module.vars.into_iter()
+        .filter(|(name, v)| !defaults.get(name).is_some_and(|d| values_equal(d, v)))
+        .collect();
+    vars.sort_by(|a, b| a.0.cmp(&b.0));
+    let scope: ModuleScope = ModuleScope(Rc::new(RefCell::new(HashMap::new())));
+    let mut funcs: Vec<(String, Function)> = module.funcs.into_iter().collect();
+    funcs.sort_by(|a, b| a.0.cmp(&b.0));
+    for (name, f) in &mut funcs {
+        // functions this module imported keep the scope of the module that defined them
+        if f.module.is_none() { f.module = Some(scope.clone()); }
+        scope.0.borrow_mut().insert(name.clone(), f.clone());
+    }
+    vars.into_iter().chain(funcs.into_iter().map(|(name, f)| (name, Value::Function(f)))).collect()
+}
+
+fn short_name(p: &Path) -> String {
+    p.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_else(|| p.display().to_string())
+}
@@ -0,0 +1,380 @@
+// Optimisation built-ins: minimize, maximize and nonlinear least-squares fit
+
+use crate::calculus::numeric_gradient;
+use crate::evaluator::{call_value, display_value, Env, Value};
+use crate::linalg;
+
+pub const NAMES: &[&str] = &["minimize", "maximize", "fit"];
+
+const METHODS: &[&str] = &["golden", "nelder-mead", "bfgs"];
+const MAX_ITERATIONS: usize = 10_000;
+
+/// Dispatch an optimisation built-in. Returns `None` when `name` is not one of [`NAMES`].
+pub fn call(name: &str, args: &[Value], env: &mut Env) -> Option<Value> {
+    if !NAMES.contains(&name) { return None; }
+    Some(dispatch(name, args, env).unwrap_or_else(|msg| Value::Str(format!("ERROR: {}", msg))))
+}
+
+fn dispatch(name: &str, args: &[Value], env: &mut Env) -> Result<Value, String> {
+    match name {
+        "minimize" | "maximize" => optimize(name, args, env),
+        "fit" => fit(args, env),
+        _ => Err(format!("function '{}' does not exist or is not implemented", name)),
+    }
+}
+
+/// The result of a minimisation: where, how low, and how many iterations it took.
+struct Minimum {
+    x: Vec<f64>,
+    value: f64,
+    iterations: usize,
+}
+
+/// minimize(f, x0 [, bounds] [, method]) and maximize with the same arguments.
+///
+/// `x0` is a number for a function of one variable or an array with one entry per parameter.
+/// `bounds` is `[lo, hi]` in one dimension or one `[lo, hi]` pair per parameter (use `inf` for no bound).
+fn optimize(name: &str, args: &[Value], env: &mut Env) -> Result<Value, String> {
+    let (f, x0) = match args {
+        [f @ (Value::Function(_) | Value::Builtin(_)), x0, ..] if args.len() <= 4 => (f, x0),
+        _ => return Err(format!("{} expects (f, x0 [, bounds] [, method])", name)),
+    };
+    let scalar: bool = matches!(x0, Value::Number(_));
+    let start: Vec<f64> = numbers(name, "x0", x0)?;
+    if start.is_empty() { return Err(format!("{}: x0 must not be empty", name)); }
+    let mut bounds: Option<Vec<(f64, f64)>> = None;
+    let mut method: Option<&str> = None;
+    for extra in &args[2..] {
+        match extra {
+            Value::Str(m) if METHODS.contains(&m.to_lowercase().as_str()) => {
+                method = METHODS.iter().find(|known| **known == m.to_lowercase()).copied();
+            }
+            Value::Str(m) => return Err(format!("{}: unknown method '{}' (expected one of {})", name, m, METHODS.join(", "))),
+            Value::Array(_) => bounds = Some(bounds_arg(name, extra, start.len())?),
+            other => return Err(format!("{}: unexpected argument {}", name, display_value(other))),
+        }
+    }
+    let n: usize = start.len();
+    let method: &str = method.unwrap_or(if n == 1 { "golden" } else { "bfgs" });
+    if method == "golden" && n != 1 {
+        return Err(format!("{}: golden-section search only works in one dimension", name));
+    }
+    // maximising f is minimising -f
+    let sign: f64 = if name == "maximize" { -1.0 } else { 1.0 };
+    let spread: bool = spreads(f, n);
+    let clamp = |x: &[f64]| -> Vec<f64> {
+        match &bounds {
+            Some(b) => x.iter().zip(b).map(|(v, (lo, hi))| v.clamp(*lo, *hi)).collect(),
+            None => x.to_vec(),
+        }
+    };
+    let start: Vec<f64> = clamp(&start);
+    let mut objective = |x: &[f64]| -> f64 {
+        let v: f64 = number(&call_value(f, &arguments(x, spread), env));
+        if v.is_nan() { f64::INFINITY } else { sign * v }
+    };
+    if !objective(&start).is_finite() {
+        return Err(format!("{}: f is not a finite number at x0", name));
+    }
+    let found: Minimum = match method {
+        "golden" => golden(name, &mut objective, start[0], bounds.as_ref().map(|b| b[0]))?,
+        "nelder-mead" => nelder_mead(&mut objective, &start, &clamp),
+        _ => bfgs(&mut objective, &start, &clamp),
+    };
+    let x: Value = if scalar { Value::Number(found.x[0]) } else { Value::Array(found.x.iter().map(|v| Value::Number(*v)).collect()) };
+    Ok(Value::Record(vec![
+        ("x".to_string(), x),
+        ("value".to_string(), Value::Number(sign * found.value)),
+        ("iterations".to_string(), Value::Number(found.iterations as f64)),
+        ("method".to_string(), Value::Str(method.to_string())),
+    ]))
+}
+
+/// Golden-section search, after bracketing the minimum downhill from x0 when no bounds are given.
+fn golden(name: &str, f: &mut impl FnMut(&[f64]) -> f64, x0: f64, bounds: Option<(f64, f64)>) -> Result<Minimum, String> {
+    let r: f64 = (5f64.sqrt() - 1.0) / 2.0;
+    let mut iterations: usize = 0;
+    let (mut a, mut b) = match bounds {
+        Some((lo, hi)) if lo.is_finite() && hi.is_finite() => (lo, hi),
+        _ => {
+            // walk downhill with growing steps until the function turns up again
+            let mut step: f64 = 0.1 * x0.abs().max(1.0);
+            if f(&[x0 + step]) > f(&[x0]) { step = -step; }
+            // start the bracket on the uphill side, so a first step that already overshoots still encloses the minimum
+            let (mut prev, mut x) = (x0 - step, x0);
+            loop {
+                iterations += 1;
+                let next: f64 = x + step;
+                let next: f64 = match bounds { Some((lo, hi)) => next.clamp(lo, hi), None => next };
+                if f(&[next]) >= f(&[x]) || next == x { break (prev.min(next), prev.max(next)); }
+                if iterations > 200 {
+                    return Err(format!("{}: no minimum found; the function keeps decreasing", name));
+                }
+                (prev, x) = (x, next);
+                step *= 1.0 / r;
+            }
+        }
+    };
+    let (mut c, mut d) = (b - r * (b - a), a + r * (b - a));
+    let (mut fc, mut fd) = (f(&[c]), f(&[d]));
+    while (b - a).abs() > 1e-12 * (a.abs() + b.abs()).max(1e-12) && iterations < MAX_ITERATIONS {
+        iterations += 1;
+        if fc < fd {
+            (b, d, fd) = (d, c, fc);
+            c = b - r * (b - a);
+            fc = f(&[c]);
+        } else {
+            (a, c, fc) = (c, d, fd);
+            d = a + r * (b - a);
+            fd = f(&[d]);
+        }
+    }
+    // the ends of a bounded interval are candidates too
+    let mut best: (f64, f64) = if fc < fd { (c, fc) } else { (d, fd) };
+    if let Some((lo, hi)) = bounds {
+        for end in [lo, hi].into_iter().filter(|e| e.is_finite()) {
+            let fe: f64 = f(&[end]);
+            if fe < best.1 { best = (end, fe); }
+        }
+    }
+    Ok(Minimum { x: vec![best.0], value: best.1, iterations })
+}
+
+/// Nelder-Mead simplex search; needs no derivatives.
+fn nelder_mead(f: &mut impl FnMut(&[f64]) -> f64, x0: &[f64], clamp: &impl Fn(&[f64]) -> Vec<f64>) -> Minimum {
+    let n: usize = x0.len();
+    let mut simplex: Vec<(Vec<f64>, f64)> = vec![(x0.to_vec(), f(x0))];
+    for i in 0..n {
+        let mut p: Vec<f64> = x0.to_vec();
+        p[i] += if p[i] == 0.0 { 0.00025 } else { 0.05 * p[i] };
+        let p: Vec<f64> = clamp(&p);
+        let fp: f64 = f(&p);
+        simplex.push((p, fp));
+    }
+    let mut iterations: usize = 0;
+    while iterations < MAX_ITERATIONS.min(400 * n) {
+        iterations += 1;
+        simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+        let (best, worst) = (simplex[0].1, simplex[n].1);
+        let size: f64 = simplex.iter().skip(1).flat_map(|(p, _)| p.iter().zip(&simplex[0].0).map(|(a, b)| (a - b).abs())).fold(0.0, f64::max);
+        if (worst - best).abs() <= 1e-12 * best.abs().max(1e-12) && size <= 1e-10 * simplex[0].0.iter().fold(1.0f64, |m, v| m.max(v.abs())) {
+            break;
+        }
+        let centroid: Vec<f64> = (0..n).map(|j| simplex[..n].iter().map(|(p, _)| p[j]).sum::<f64>() / n as f64).collect();
+        let towards = |t: f64| -> Vec<f64> { clamp(&centroid.iter().zip(&simplex[n].0).map(|(c, w)| c + t * (w - c)).collect::<Vec<f64>>()) };
+        let reflected: Vec<f64> = towards(-1.0);
+        let fr: f64 = f(&reflected);
+        if fr < simplex[0].1 {
+            let expanded: Vec<f64> = towards(-2.0);
+            let fe: f64 = f(&expanded);
+            simplex[n] = if fe < fr { (expanded, fe) } else { (reflected, fr) };
+        } else if fr < simplex[n - 1].1 {
+            simplex[n] = (reflected, fr);
+        } else {
+            let contracted: Vec<f64> = if fr < worst { towards(-0.5) } else { towards(0.5) };
+            let fc: f64 = f(&contracted);
+            if fc < fr.min(worst) {
+                simplex[n] = (contracted, fc);
+            } else {
+                // shrink everything towards the best point
+                let best_point: Vec<f64> = simplex[0].0.clone();
+                for (p, fp) in simplex.iter_mut().skip(1) {
+                    *p = best_point.iter().zip(p.iter()).map(|(b, v)| b + 0.5 * (v - b)).collect();
+                    *fp = f(p);
+                }
+            }
+        }
+    }
+    simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+    let (x, value) = simplex.swap_remove(0);
+    Minimum { x, value, iterations }
+}
+
+/// Quasi-Newton BFGS with numeric gradients and a backtracking line search.
+/// With bounds every trial point is clamped into the box (a projected variant).
+fn bfgs(f: &mut impl FnMut(&[f64]) -> f64, x0: &[f64], clamp: &impl Fn(&[f64]) -> Vec<f64>) -> Minimum {
+    let n: usize = x0.len();
+    let identity = || -> Vec<Vec<f64>> { (0..n).map(|i| (0..n).map(|j| if i == j { 1.0 } else { 0.0 }).collect()).collect() };
+    let mut h: Vec<Vec<f64>> = identity();
+    let mut x: Vec<f64> = x0.to_vec();
+    let mut fx: f64 = f(&x);
+    let mut g: Vec<f64> = numeric_gradient(&mut *f, &x);
+    let mut iterations: usize = 0;
+    while iterations < MAX_ITERATIONS.min(200 * n.max(5)) {
+        iterations += 1;
+        if g.iter().all(|gi| gi.abs() <= 1e-8 * fx.abs().max(1.0)) { break; }
+        let mut p: Vec<f64> = (0..n).map(|i| -(0..n).map(|j| h[i][j] * g[j]).sum::<f64>()).collect();
+        let mut slope: f64 = dot(&p, &g);
+        if slope >= 0.0 {
+            // not a descent direction: restart from steepest descent
+            h = identity();
+            p = g.iter().map(|v| -v).collect();
+            slope = dot(&p, &g);
+        }
+        let mut t: f64 = 1.0;
+        let mut next: Option<(Vec<f64>, f64)> = None;
+        while t > 1e-20 {
+            let trial: Vec<f64> = clamp(&x.iter().zip(&p).map(|(a, b)| a + t * b).collect::<Vec<f64>>());
+            let ft: f64 = f(&trial);
+            if ft <= fx + 1e-4 * t * slope { next = Some((trial, ft)); break; }
+            t *= 0.5;
+        }
+        let Some((x_new, f_new)) = next else { break };
+        let g_new: Vec<f64> = numeric_gradient(&mut *f, &x_new);
+        let s: Vec<f64> = x_new.iter().zip(&x).map(|(a, b)| a - b).collect();
+        let y: Vec<f64> = g_new.iter().zip(&g).map(|(a, b)| a - b).collect();
+        let sy: f64 = dot(&s, &y);
+        let converged: bool = (fx - f_new).abs() <= 1e-15 * fx.abs().max(1e-300) && s.iter().all(|v| v.abs() <= 1e-12 * x.iter().fold(1.0f64, |m, v| m.max(v.abs())));
+        (x, fx, g) = (x_new, f_new, g_new);
+        if converged { break; }
+        if sy > 1e-12 {
+            // H <- (I - rho s y^T) H (I - rho y s^T) + rho s s^T
+            let rho: f64 = 1.0 / sy;
+            let hy: Vec<f64> = (0..n).map(|i| (0..n).map(|j| h[i][j] * y[j]).sum()).collect();
+            let yhy: f64 = dot(&y, &hy);
+            for i in 0..n {
+                for j in 0..n {
+                    h[i][j] += rho * ((1.0 + rho * yhy) * s[i] * s[j] - hy[i] * s[j] - s[i] * hy[j]);
+                }
+            }
+        }
+    }
+    Minimum { x, value: fx, iterations }
+}
+
+/// fit(model, xs, ys, p0): Levenberg-Marquardt least squares for `ys ≈ model(x, p1, p2, ...)`.
+///
+/// The model takes the independent variable first, then either one argument per parameter or
+/// the parameter array. The result holds the fitted `params`, their standard `errors`, the
+/// `residuals` ys - model(xs) and the parameter `covariance` matrix.
+fn fit(args: &[Value], env: &mut Env) -> Result<Value, String> {
+    let [model @ Value::Function(func), Value::Array(xs), ys, p0] = args else {
+        return Err("fit expects (model, xs, ys, p0)".to_string());
+    };
+    let ys: Vec<f64> = numbers("fit", "ys", ys)?;
+    let p0: Vec<f64> = numbers("fit", "p0", p0)?;
+    let (m, n) = (xs.len(), p0.len());
+    if ys.len() != m {
+        return Err("fit: xs and ys must have the same length".to_string());
+    }
+    if n == 0 || m < n {
+        return Err("fit: need at least as many data points as parameters".to_string());
+    }
+    let spread: bool = match func.params.len() {
+        k if k == n + 1 => true,
+        2 => false,
+        _ => return Err(format!("fit: the model must take x and {} parameters", n)),
+    };
+    let mut predict = |x: &Value, p: &[f64]| -> f64 {
+        let mut argv: Vec<Value> = vec![x.clone()];
+        argv.extend(arguments(p, spread));
+        number(&call_value(model, &argv, env))
+    };
+    let residuals = |p: &[f64], predict: &mut dyn FnMut(&Value, &[f64]) -> f64| -> Vec<f64> {
+        xs.iter().zip(&ys).map(|(x, y)| y - predict(x, p)).collect()
+    };
+    // Jacobian of the model with respect to the parameters, one row per data point
+    let jacobian = |p: &[f64], predict: &mut dyn FnMut(&Value, &[f64]) -> f64| -> Vec<Vec<f64>> {
+        xs.iter().map(|x| numeric_gradient(|q| predict(x, q), p)).collect()
+    };
+    let sse = |r: &[f64]| -> f64 { r.iter().map(|v| v * v).sum() };
+
+    let mut p: Vec<f64> = p0;
+    let mut r: Vec<f64> = residuals(&p, &mut predict);
+    if r.iter().any(|v| !v.is_finite()) {
+        return Err("fit: the model is not a finite number at p0".to_string());
+    }
+    let mut lambda: f64 = 1e-3;
+    for _ in 0..MAX_ITERATIONS.min(500) {
+        let j: Vec<Vec<f64>> = jacobian(&p, &mut predict);
+        let jtj: Vec<Vec<f64>> = (0..n).map(|a| (0..n).map(|b| (0..m).map(|i| j[i][a] * j[i][b]).sum()).collect()).collect();
+        let jtr: Vec<f64> = (0..n).map(|a| (0..m).map(|i| j[i][a] * r[i]).sum()).collect();
+        let mut improved: bool = false;
+        while lambda < 1e16 {
+            let damped: Vec<Vec<f64>> = (0..n).map(|a| (0..n).map(|b| {
+                if a == b { jtj[a][b] + lambda * jtj[a][a].max(1e-12) } else { jtj[a][b] }
+            }).collect()).collect();
+            if let Some(step) = linalg::solve(damped, jtr.clone()) {
+                let trial: Vec<f64> = p.iter().zip(&step).map(|(a, b)| a + b).collect();
+                let rt: Vec<f64> = residuals(&trial, &mut predict);
+                if rt.iter().all(|v| v.is_finite()) && sse(&rt) < sse(&r) {
+                    let gain: f64 = sse(&r) - sse(&rt);
+                    let small_step: bool = step.iter().zip(&trial).all(|(s, v)| s.abs() <= 1e-12 * v.abs().max(1e-12));
+                    (p, r) = (trial, rt);
+                    lambda = (lambda / 10.0).max(1e-12);
+                    improved = gain > 1e-15 * sse(&r).max(1e-300) && !small_step;
+                    break;
+                }
+            }
+            lambda *= 10.0;
+        }
+        if !improved { break; }
+    }
+
+    // covariance = s^2 (J^T J)^-1 with s^2 the residual variance
+    let j: Vec<Vec<f64>> = jacobian(&p, &mut predict);
+    let jtj: Vec<Vec<f64>> = (0..n).map(|a| (0..n).map(|b| (0..m).map(|i| j[i][a] * j[i][b]).sum()).collect()).collect();
+    let dof: f64 = (m - n).max(1) as f64;
+    let s2: f64 = sse(&r) / dof;
+    let covariance: Vec<Vec<f64>> = linalg::inverse(&jtj)
+        .map(|inv| inv.into_iter().map(|row| row.into_iter().map(|v| v * s2).collect()).collect())
+        .unwrap_or_else(|| vec![vec![f64::NAN; n]; n]);
+    let vector = |v: &[f64]| Value::Array(v.iter().map(|x| Value::Number(*x)).collect());
+    Ok(Value::Record(vec![
+        ("params".to_string(), vector(&p)),
+        ("errors".to_string(), vector(&(0..n).map(|i| covariance[i][i].sqrt()).collect::<Vec<f64>>())),
+        ("residuals".to_string(), vector(&r)),
+        ("covariance".to_string(), Value::Array(covariance.iter().map(|row| vector(row)).collect())),
+    ]))
+}
+
+/// Whether f takes the coordinates as separate arguments (f(x, y)) rather than one array (f(p)).
+fn spreads(f: &Value, n: usize) -> bool {
+    match f {
+        Value::Function(func) => !(func.params.len() == 1 && n > 1),
+        _ => true,
+    }
+}
+
+fn arguments(x: &[f64], spread: bool) -> Vec<Value> {
+    let values: Vec<Value> = x.iter().map(|v| Value::Number(*v)).collect();
+    if spread { values } else { vec![Value::Array(values)] }
+}
+
+fn numbers(name: &str, what: &str, v: &Value) -> Result<Vec<f64>, String> {
+    match v {
+        Value::Number(x) => Ok(vec![*x]),
+        Value::Array(items) => items.iter().map(|item| match item {
+            Value::Number(x) => Ok(*x),
+            _ => Err(format!("{}: {} must contain only numbers", name, what)),
+        }).collect(),
+        _ => Err(format!("{}: {} must be a number or an array of numbers", name, what)),
+    }
+}
+
+fn bounds_arg(name: &str, v: &Value, n: usize) -> Result<Vec<(f64, f64)>, String> {
+    let pair = |p: &Value| -> Result<(f64, f64), String> {
+        match p {
+            Value::Array(items) => match items.as_slice() {
+                [Value::Number(lo), Value::Number(hi)] if lo <= hi => Ok((*lo, *hi)),
+                _ => Err(format!("{}: each bound must be [lo, hi] with lo <= hi", name)),
+            },
+            _ => Err(format!("{}: each bound must be [lo, hi] with lo <= hi", name)),
+        }
+    };
+    let Value::Array(items) = v else { return Err(format!("{}: bounds must be an array", name)) };
+    // [lo, hi] on its own is the bound of a single variable
+    let bounds: Vec<(f64, f64)> = if items.iter().all(|i| matches!(i, Value::Number(_))) { vec![pair(v)?] } else { items.iter().map(pair).collect::<Result<_, _>>()? };
+    if bounds.len() != n {
+        return Err(format!("{}: expected {} bounds, one per variable", name, n));
+    }
+    Ok(bounds)
+}
+
+fn number(v: &Value) -> f64 {
+    match v { Value::Number(x) => *x, _ => f64::NAN }
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
@@ -0,0 +1,135 @@
+// Where printed values, log messages and error reports go: the terminal, a buffer or JSON lines
+
+use std::cell::RefCell;
+use std::io::Write;
+use std::rc::Rc;
+
+/// The kind of message being written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    // print(...)
+    Print,
+    // log ... statements
+    Log,
+    // errors reported while running, such as syntax errors and failed imports
+    Error,
+}
+
+impl Stream {
+    pub fn name(self) -> &'static str {
+        match self {
+            Stream::Print => "print",
+            Stream::Log => "log",
+            Stream::Error => "error",
+        }
+    }
+}
+
+/// A destination for everything a script writes. Each call is one line without its newline.
+pub trait OutputSink {
+    fn write(&mut self, stream: Stream, text: &str);
+}
+
+/// Printed values to stdout, log messages and errors to stderr.
+pub struct Stdout;
+
+impl OutputSink for Stdout {
+    fn write(&mut self, stream: Stream, text: &str) {
+        match stream {
+            Stream::Print => println!("{}", text),
+            Stream::Log | Stream::Error => eprintln!("{}", text),
+        }
+    }
+}
+
+/// Keeps every line in memory. Clones share the same lines, so one handle can be given to
+/// an environment and another kept to read them back.
+#[derive(Clone, Default)]
+pub struct Buffer(Rc<RefCell<Vec<(Stream, String)>>>);
+
+impl Buffer {
+    /// Every line written so far, in order, leaving the buffer empty.
+    pub fn take(&self) -> Vec<(Stream, String)> {
+        std::mem::take(&mut *self.0.borrow_mut())
+    }
+
+    /// The lines written to `stream`, each followed by a newline, removing them from the buffer.
+    pub fn take_text(&self, stream: Stream) -> String {
+        let mut lines = self.0.borrow_mut();
+        let mut text: String = String::new();
+        lines.retain(|(s, line)| {
+            if *s != stream { return true; }
+            text.push_str(line);
+            text.push('\n');
+            false
+        });
+        text
+    }
+}
+
+impl OutputSink for Buffer {
+    fn write(&mut self, stream: Stream, text: &str) {
+        self.0.borrow_mut().push((stream, text.to_string()));
+    }
+}
+
+/// One JSON object per line, `{"stream": "print", "text": "..."}`, for tools reading the output.
+pub struct JsonLines<W: Write>(pub W);
+
+impl<W: Write> OutputSink for JsonLines<W> {
+    fn write(&mut self, stream: Stream, text: &str) {
+        // a closed pipe is not the script's problem
+        let _ = writeln!(self.0, "{{\"stream\": {}, \"text\": {}}}", json_string(stream.name()), json_string(text));
+    }
+}
+
+/// `s` as a quoted JSON string.
+pub fn json_string(s: &str) -> String {
+    let mut out: String = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// The sink print, log and error reports are written to.
+#[derive(Clone)]
+pub struct Output(Rc<RefCell<dyn OutputSink>>);
+
+impl Default for Output {
+    fn default() -> Self {
+        Output::new(Stdout)
+    }
+}
+
+impl Output {
+    pub fn new(sink: impl OutputSink + 'static) -> Self {
+        Output(Rc::new(RefCell::new(sink)))
+    }
+
+    pub fn write(&self, stream: Stream, text: &str) {
+        self.0.borrow_mut().write(stream, text);
+    }
+
+    pub fn print(&self, text: &str) {
+        self.write(Stream::Print, text);
+    }
+
+    pub fn log(&self, text: &str) {
+        self.write(Stream::Log, text);
+    }
+
+    pub fn error(&self, text: &str) {
+        self.write(Stream::Error, text);
+    }
+}
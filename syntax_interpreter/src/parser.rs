@@ -2,10 +2,18 @@
 
 
 use crate::lexer::{Token, TokenKind};
-use crate::ast::{AstNode, UnaryOpKind, BinaryOpKind};
+use crate::ast::{AstNode, UnaryOpKind, BinaryOpKind, ImportKind, Level, LimitSide};
+use std::collections::HashSet;
 
 pub fn parse(tokens: &[Token]) -> AstNode {
+    parse_with(tokens, std::iter::empty())
+}
+
+/// Parse `tokens` as a program that runs after the `bound` variables have values,
+/// so that dy/dx with a variable dy or dx is a division rather than a derivative.
+pub fn parse_with<'b>(tokens: &[Token], bound: impl IntoIterator<Item = &'b str>) -> AstNode {
     let mut parser: Parser<'_> = Parser::new(tokens);
+    parser.bound.extend(bound.into_iter().map(str::to_string));
     let mut stmts: Vec<AstNode> = Vec::new();
     while parser.pos < parser.tokens.len() {
         // skip EOF
@@ -15,9 +23,15 @@ pub fn parse(tokens: &[Token]) -> AstNode {
             parser.pos += 1;
         }
         if matches!(parser.peek().map(|t| &t.kind), Some(TokenKind::EOF)) { break; }
+        let start = parser.pos;
         if let Some(stmt) = (&mut parser).parse_statement() {
             (&mut stmts).push(stmt);
         }
+        // A statement that fails to parse without consuming anything would loop forever; skip the token
+        if parser.pos == start {
+            stmts.push(AstNode::Error("invalid syntax".to_string()));
+            parser.pos += 1;
+        }
         // After a statement, if the next token is a semicolon or newline, consume it (explicit/implicit statement separator)
         if matches!(parser.peek().map(|t| &t.kind), Some(TokenKind::Semicolon) | Some(TokenKind::Newline)) {
             parser.pos += 1;
@@ -30,6 +44,10 @@ pub fn parse(tokens: &[Token]) -> AstNode {
 struct Parser<'a> {
     tokens: &'a [Token],
     pos: usize,
+    // how many |...| groups are open around the cursor; inside one, || closes it rather than meaning 'or'
+    abs_depth: usize,
+    // variables assigned so far and the parameters in scope; dy/dx divides when these include dy or dx
+    bound: HashSet<String>,
 }
 
 impl<'a> Parser<'a> {
@@ -41,17 +59,59 @@ impl<'a> Parser<'a> {
         // function definition: f(x, y) = expr
         // assignment: x = expr
         // import: import "file"
+        // @memo f(n) = ...: a definition whose results are remembered
+        if self.match_kind(TokenKind::At) {
+            let annotation: String = self.next().filter(|t| t.kind == TokenKind::Identifier)?.lexeme.clone();
+            if annotation != "memo" { return Some(AstNode::Error(format!("unknown annotation @{}", annotation))); }
+            while self.match_kind(TokenKind::Newline) {}
+            return match self.parse_statement() {
+                Some(AstNode::FunctionDef { name, params, body, .. }) => Some(AstNode::FunctionDef { name, params, body, memo: true }),
+                Some(AstNode::Error(msg)) => Some(AstNode::Error(msg)),
+                _ => Some(AstNode::Error("@memo must come before a function definition".to_string())),
+            };
+        }
         if let Some(Token { kind: TokenKind::Identifier, lexeme }) = self.peek() {
             if lexeme == "import" {
+                // import "file" [as name]
                 self.next();
-                match self.peek() {
-                    Some(Token { kind: TokenKind::String, lexeme: path }) => {
-                        let p: String = path.clone();
+                let path: String = self.next().filter(|t| t.kind == TokenKind::String)?.lexeme.clone();
+                let kind: ImportKind = match self.peek() {
+                    Some(Token { kind: TokenKind::Identifier, lexeme }) if lexeme == "as" => {
                         self.next();
-                        return Some(AstNode::Import(p));
+                        ImportKind::Namespace(self.next().filter(|t| t.kind == TokenKind::Identifier)?.lexeme.clone())
                     }
-                    _ => return None,
+                    _ => ImportKind::All,
+                };
+                return Some(AstNode::Import { path, kind });
+            }
+            if lexeme == "from" && matches!(self.lookahead_kind(1), Some(TokenKind::String)) {
+                // from "file" import a, b
+                self.next();
+                let path: String = self.next()?.lexeme.clone();
+                self.next().filter(|t| t.kind == TokenKind::Identifier && t.lexeme == "import")?;
+                let mut names: Vec<String> = Vec::new();
+                loop {
+                    names.push(self.next().filter(|t| t.kind == TokenKind::Identifier)?.lexeme.clone());
+                    if !self.match_kind(TokenKind::Comma) { break; }
                 }
+                self.bound.extend(names.iter().cloned());
+                return Some(AstNode::Import { path, kind: ImportKind::Names(names) });
+            }
+            // format directive: format <setting> [digits | tolerance | on | off];
+            // format(...) is the string function
+            if lexeme == "format" && matches!(self.lookahead_kind(1), Some(TokenKind::Identifier)) {
+                self.next();
+                let setting: String = self.next()?.lexeme.clone();
+                let arg: Option<AstNode> = match self.peek() {
+                    Some(Token { kind: TokenKind::Identifier, lexeme }) if lexeme == "on" || lexeme == "off" => {
+                        let on: bool = lexeme == "on";
+                        self.next();
+                        Some(AstNode::Bool(on))
+                    }
+                    Some(Token { kind: TokenKind::Semicolon | TokenKind::Newline | TokenKind::EOF, .. }) | None => None,
+                    _ => Some(self.parse_expression(0)?),
+                };
+                return Some(AstNode::Format { setting, arg: arg.map(Box::new) });
             }
             if lexeme == "print" {
                 self.next();
@@ -59,48 +119,67 @@ impl<'a> Parser<'a> {
                 let args: Vec<AstNode> = self.parse_arg_list()?;
                 return Some(AstNode::Print(args));
             }
-            if lexeme == "log" {
+            // log("INFO", ...) logs a message; log(100) is the logarithm
+            if lexeme == "log" && matches!(self.lookahead_kind(2), Some(TokenKind::String | TokenKind::Template))
+                && matches!(self.lookahead_kind(1), Some(TokenKind::LParen)) {
                 self.next();
                 self.expect(TokenKind::LParen)?;
-                let args: Vec<AstNode> = self.parse_arg_list()?;
-                return Some(AstNode::Log(args));
+                let mut args: Vec<AstNode> = self.parse_arg_list()?;
+                // a leading level is optional: log("done") logs at INFO
+                let level: Option<Level> = match args.first() {
+                    Some(AstNode::Str(s)) if args.len() > 1 => Level::parse(s),
+                    _ => None,
+                };
+                if level.is_some() { args.remove(0); }
+                return Some(AstNode::Log { level: level.unwrap_or(Level::Info), args });
             }
         }
         if let Some(Token { kind: TokenKind::Identifier, lexeme }) = self.peek() {
             let name: String = lexeme.clone();
             // function def pattern (only allowed as a statement, not as an expression)
+            // f(x, y) = ... is a definition; anything else starting with f( is a call
             if matches!(self.lookahead_kind(1), Some(TokenKind::LParen)) {
+                let start = self.pos;
                 self.next(); // name
                 self.next(); // (
-                let params = self.parse_params();
-                if params.is_none() {
-                    return Some(AstNode::Error("invalid syntax".to_string()));
-                }
-                let params = params.unwrap();
-                if !self.match_kind(TokenKind::Assign) {
-                    return Some(AstNode::Error("invalid syntax".to_string()));
-                }
+                let params = match self.parse_params() {
+                    Some(params) if self.match_kind(TokenKind::Assign) => params,
+                    _ => {
+                        self.pos = start;
+                        return self.parse_expression(0);
+                    }
+                };
                 // Parse only a single expression for the function body
-                let expr = self.parse_expression(0).unwrap_or(AstNode::Empty);
-                let mut expr = expr;
-                // Check for trailing {condition} after function body
-                if self.match_kind(TokenKind::LBrace) {
-                    let cond = self.parse_condition_expression(0);
-                    if cond.is_none() || !self.match_kind(TokenKind::RBrace) {
-                        return Some(AstNode::Error("invalid syntax".to_string()));
+                let body: Option<AstNode> = self.with_bound(&params, |p| {
+                    let mut expr = p.parse_expression(0).unwrap_or(AstNode::Empty);
+                    // Check for trailing {condition} after function body
+                    if p.match_kind(TokenKind::LBrace) {
+                        let cond = p.parse_condition_expression(0);
+                        if cond.is_none() || !p.match_kind(TokenKind::RBrace) {
+                            return None;
+                        }
+                        expr = AstNode::Conditional { condition: Box::new(cond.unwrap()), body: Box::new(expr) };
                     }
-                    expr = AstNode::Conditional { condition: Box::new(cond.unwrap()), body: Box::new(expr) };
-                }
-                return Some(AstNode::FunctionDef { name, params, body: Box::new(expr) });
+                    Some(expr)
+                });
+                let Some(expr) = body else { return Some(AstNode::Error("invalid syntax".to_string())) };
+                return Some(AstNode::FunctionDef { name, params, body: Box::new(expr), memo: false });
             }
             // assignment pattern
             if matches!(self.lookahead_kind(1), Some(TokenKind::Assign)) {
                 self.next(); // name
                 self.next(); // =
                 let expr: AstNode = self.parse_expression(0)?;
+                self.bound.insert(name.clone());
                 return Some(AstNode::Assignment { name, expr: Box::new(expr) });
             }
         }
+        // destructuring assignment: (a, b) = expr
+        if let Some(names) = self.match_destructure() {
+            let expr: AstNode = self.parse_expression(0)?;
+            self.bound.extend(names.iter().cloned());
+            return Some(AstNode::Destructure { names, expr: Box::new(expr) });
+        }
         // fallback to expression
         self.parse_expression(0)
     }
@@ -126,7 +205,7 @@ impl<'a> Parser<'a> {
         Some(params)
     }
     fn new(tokens: &'a [Token]) -> Self {
-        Parser { tokens, pos: 0 }
+        Parser { tokens, pos: 0, abs_depth: 0, bound: HashSet::new() }
     }
 
     fn peek(&self) -> Option<&Token> {
@@ -144,6 +223,9 @@ impl<'a> Parser<'a> {
     fn parse_expression(&mut self, min_bp: u8) -> Option<AstNode> {
         let mut lhs: AstNode = self.parse_prefix()?;
         lhs = self.parse_postfix(lhs)?;
+        // whether lhs is a comparison made at this level, which a further comparison extends
+        // (a parenthesised comparison is a value of its own)
+        let mut chaining: bool = false;
 
         loop {
             // Stop implicit multiplication and expression parsing at statement boundaries
@@ -153,6 +235,16 @@ impl<'a> Parser<'a> {
                 _ => {}
             }
 
+            // binary operators, including the keyword operators (and, in, union, ...)
+            if let Some((lbp, rbp, bop)) = self.infix_operator(false) {
+                if lbp < min_bp { break; }
+                self.consume_operator();
+                let rhs: AstNode = self.parse_expression(rbp)?;
+                lhs = combine(lhs, bop, rhs, chaining);
+                chaining = is_comparison(&lhs);
+                continue;
+            }
+
             // Check for implicit multiplication
             let implicit_mul = match self.peek() {
                 Some(Token { kind: TokenKind::Identifier, .. })
@@ -160,46 +252,19 @@ impl<'a> Parser<'a> {
                 | Some(Token { kind: TokenKind::LParen, .. })
                 | Some(Token { kind: TokenKind::LBracket, .. }) => {
                     match &lhs {
-                        AstNode::Number(_) | AstNode::Variable(_) | AstNode::Str(_) | AstNode::FunctionCall { .. } | AstNode::Array(_) | AstNode::UnaryOp { .. } | AstNode::BinaryOp { .. } => true,
+                        AstNode::Number(_) | AstNode::Variable(_) | AstNode::Str(_) | AstNode::FunctionCall { .. } | AstNode::Array(_) | AstNode::Field { .. } | AstNode::UnaryOp { .. } | AstNode::BinaryOp { .. } => true,
                         _ => false,
                     }
                 }
                 _ => false,
             };
-            if implicit_mul {
-                // Precedence for implicit multiplication is the same as explicit '*'
-                let rbp = 6;
-                if 5 < min_bp { break; }
-                let rhs = self.parse_expression(rbp)?;
-                lhs = AstNode::BinaryOp { op: BinaryOpKind::Mul, left: Box::new(lhs), right: Box::new(rhs) };
-                continue;
-            }
-
-            let op: TokenKind = match self.peek() {
-                Some(t) => (&(*t).kind).clone(),
-                None => break,
-            };
-
-            let (lbp, rbp, bop) = match op {
-                TokenKind::Caret => (7, 6, Some(BinaryOpKind::Pow)),
-                TokenKind::Star => (5, 6, Some(BinaryOpKind::Mul)),
-                TokenKind::Slash => (5, 6, Some(BinaryOpKind::Div)),
-                TokenKind::Mod => (5, 6, Some(BinaryOpKind::Mod)),
-                TokenKind::Plus => (3, 4, Some(BinaryOpKind::Add)),
-                TokenKind::Minus => (3, 4, Some(BinaryOpKind::Sub)),
-                TokenKind::GreaterEq => (2, 3, Some(BinaryOpKind::Gte)),
-                TokenKind::LessEq => (2, 3, Some(BinaryOpKind::Lte)),
-                TokenKind::Greater => (2, 3, Some(BinaryOpKind::Gt)),
-                TokenKind::Less => (2, 3, Some(BinaryOpKind::Lt)),
-                TokenKind::Equal => (2, 3, Some(BinaryOpKind::Eq)),
-                TokenKind::NotEqual => (2, 3, Some(BinaryOpKind::NotEq)),
-                _ => break,
-            };
-
-            if lbp < min_bp { break; }
-            self.next();
-            let rhs: AstNode = self.parse_expression(rbp)?;
-            lhs = AstNode::BinaryOp { op: bop.unwrap(), left: Box::new(lhs), right: Box::new(rhs) };
+            if !implicit_mul { break; }
+            // Precedence for implicit multiplication is the same as explicit '*'
+            let rbp = 12;
+            if 11 < min_bp { break; }
+            let rhs = self.parse_expression(rbp)?;
+            lhs = AstNode::BinaryOp { op: BinaryOpKind::Mul, left: Box::new(lhs), right: Box::new(rhs) };
+            chaining = false;
         }
 
         Some(lhs)
@@ -226,49 +291,64 @@ impl<'a> Parser<'a> {
                 if !self.match_kind(TokenKind::Minus) || !self.match_kind(TokenKind::Greater) {
                     return Some(AstNode::Error("expected '->' in lim".to_string()));
                 }
-                let to = self.parse_expression(0)?;
-                if !self.match_kind(TokenKind::RBrace) {
-                    return Some(AstNode::Error("expected '}}' after lim target".to_string()));
+                // a trailing + or - just before the closing brace picks a one-sided limit
+                let end = match self.closing_brace() {
+                    Some(end) => end,
+                    None => return Some(AstNode::Error("expected '}}' after lim target".to_string())),
+                };
+                let side = match self.tokens[end - 1].kind {
+                    TokenKind::Plus if end - 1 > self.pos => LimitSide::Above,
+                    TokenKind::Minus if end - 1 > self.pos => LimitSide::Below,
+                    _ => LimitSide::Both,
+                };
+                let target_end = if side == LimitSide::Both { end } else { end - 1 };
+                let mut target = Parser::new(&self.tokens[..target_end]);
+                target.pos = self.pos;
+                let to = target.parse_expression(0)?;
+                if target.pos != target_end {
+                    return Some(AstNode::Error("invalid lim target".to_string()));
                 }
+                self.pos = end + 1;
                 let expr = self.parse_expression(0)?;
-                return Some(AstNode::Lim { var, to: Box::new(to), expr: Box::new(expr) });
+                return Some(AstNode::Lim { var, to: Box::new(to), side, expr: Box::new(expr) });
             }
-            if lexeme == "d" {
-                // consume 'd'
-                self.next();
-                let mut order = 1usize;
-                if self.match_kind(TokenKind::Caret) {
-                    if let Some(Token { kind: TokenKind::Number, lexeme }) = self.peek() {
-                        if let Ok(n) = (&**lexeme).parse::<usize>() { order = n; }
+            if lexeme == "series" && matches!(self.lookahead_kind(1), Some(TokenKind::LBrace)) {
+                // series {var -> val[, order]} expr
+                self.next(); // consume 'series'
+                self.next(); // consume '{'
+                let var = match self.peek() {
+                    Some(Token { kind: TokenKind::Identifier, lexeme }) => {
+                        let v = lexeme.clone();
                         self.next();
-                    } else { return None; }
+                        v
+                    },
+                    _ => return Some(AstNode::Error("expected variable in series".to_string())),
+                };
+                if !self.match_kind(TokenKind::Minus) || !self.match_kind(TokenKind::Greater) {
+                    return Some(AstNode::Error("expected '->' in series".to_string()));
                 }
-                // expect '/' 'd' var [^n]
-                self.expect(TokenKind::Slash)?;
-                if let Some(Token { kind: TokenKind::Identifier, lexeme }) = self.peek() {
-                    if lexeme == "d" {
-                        self.next(); // 'd'
-                        let var: String = if let Some(Token { kind: TokenKind::Identifier, lexeme }) = self.peek() {
-                            let v: String = lexeme.clone();
-                            self.next();
-                            v
-                        } else { return None };
-                        // optional ^n on denominator (ignored if differs)
-                        if self.match_kind(TokenKind::Caret) {
-                            if let Some(Token { kind: TokenKind::Number, .. }) = self.peek() { self.next(); }
-                        }
-                        // target expression, parentheses optional
-                        let expr: AstNode = if self.match_kind(TokenKind::LParen) {
-                            let e: AstNode = self.parse_expression(0)?;
-                            self.expect(TokenKind::RParen)?;
-                            e
-                        } else {
-                            self.parse_expression(6)?
-                        };
-                        return Some(AstNode::DerivativeExpr { var, order, expr: Box::new(expr) });
-                    }
+                let at = self.parse_expression(0)?;
+                let order = if self.match_kind(TokenKind::Comma) { Some(Box::new(self.parse_expression(0)?)) } else { None };
+                if !self.match_kind(TokenKind::RBrace) {
+                    return Some(AstNode::Error("expected '}}' after series point".to_string()));
                 }
-                return None;
+                let expr = self.parse_expression(0)?;
+                return Some(AstNode::Series { var, at: Box::new(at), order, expr: Box::new(expr) });
+            }
+            if lexeme == "true" || lexeme == "false" {
+                let value: bool = lexeme == "true";
+                self.next();
+                return Some(AstNode::Bool(value));
+            }
+            if lexeme == "not" {
+                // looser than comparisons, so that not x > 3 negates x > 3
+                self.next();
+                let expr = self.parse_expression(5)?;
+                return Some(AstNode::UnaryOp { op: UnaryOpKind::Not, expr: Box::new(expr) });
+            }
+            // derivative operator: d/dx expr, d^n/dx^n expr or dy/dx expr
+            if lexeme.starts_with('d') && matches!(self.lookahead_kind(1), Some(TokenKind::Slash) | Some(TokenKind::Caret)) {
+                if let Some(node) = self.match_derivative() { return Some(node); }
             }
         }
         match (*self.peek()?).kind {
@@ -277,10 +357,26 @@ impl<'a> Parser<'a> {
                 let v: f64 = (&*(*tok).lexeme).parse::<f64>().ok()?;
                 Some(AstNode::Number(v))
             }
-            TokenKind::String => {
+            // a docstring is a string value, so a file or block may open with one
+            TokenKind::String | TokenKind::DocString => {
                 let tok: &Token = self.next()?;
                 Some(AstNode::Str((&(*tok).lexeme).clone()))
             }
+            TokenKind::Template => {
+                // the lexer splits "a {x} b" into its text and {expr} parts
+                self.next();
+                let mut parts: Vec<AstNode> = Vec::new();
+                while !self.match_kind(TokenKind::TemplateEnd) {
+                    if self.match_kind(TokenKind::LBrace) {
+                        parts.push(self.with_abs_depth(0, |p| p.parse_expression(0))?);
+                        self.expect(TokenKind::RBrace)?;
+                    } else {
+                        let text: String = self.next().filter(|t| t.kind == TokenKind::String)?.lexeme.clone();
+                        parts.push(AstNode::Str(text));
+                    }
+                }
+                Some(AstNode::Template(parts))
+            }
             TokenKind::Identifier => {
                 // function call or variable
                 let mut name: String = (&(*self.next()?).lexeme).clone();
@@ -319,19 +415,35 @@ impl<'a> Parser<'a> {
             TokenKind::Bang => {
                 // logical negation
                 self.next();
-                let expr = self.parse_expression(6)?;
+                let expr = self.parse_expression(12)?;
                 Some(AstNode::UnaryOp { op: UnaryOpKind::Not, expr: Box::new(expr) })
             }
             TokenKind::LParen => {
+                // anonymous function: (x, y) => expr
+                if let Some(params) = self.match_lambda_params() {
+                    let body: AstNode = self.with_bound(&params, |p| p.parse_expression(0))?;
+                    return Some(AstNode::Lambda { params, body: Box::new(body) });
+                }
+                // (expr) or a tuple (a, b, ...)
                 self.next();
-                let expr: AstNode = self.parse_expression(0)?;
-                self.expect(TokenKind::RParen)?;
-                Some(expr)
+                let expr: AstNode = self.with_abs_depth(0, |p| p.parse_expression(0))?;
+                if !self.match_kind(TokenKind::Comma) {
+                    self.expect(TokenKind::RParen)?;
+                    return Some(expr);
+                }
+                let mut items: Vec<AstNode> = vec![expr];
+                loop {
+                    items.push(self.with_abs_depth(0, |p| p.parse_expression(0))?);
+                    if self.match_kind(TokenKind::Comma) { continue; }
+                    self.expect(TokenKind::RParen)?;
+                    break;
+                }
+                Some(AstNode::Tuple(items))
             }
             TokenKind::Pipe => {
                 // |expr|
                 self.next();
-                let inner: AstNode = self.parse_expression(0)?;
+                let inner: AstNode = self.with_abs_depth(self.abs_depth + 1, |p| p.parse_expression(0))?;
                 self.expect(TokenKind::Pipe)?;
                 Some(AstNode::UnaryOp { op: UnaryOpKind::Abs, expr: Box::new(inner) })
             }
@@ -343,7 +455,7 @@ impl<'a> Parser<'a> {
                     return Some(AstNode::Array(items));
                 }
                 loop {
-                    let expr: AstNode = self.parse_expression(0)?;
+                    let expr: AstNode = self.with_abs_depth(0, |p| p.parse_expression(0))?;
                     (&mut items).push(expr);
                     if self.match_kind(TokenKind::Comma) { continue; }
                     self.expect(TokenKind::RBracket)?;
@@ -351,9 +463,26 @@ impl<'a> Parser<'a> {
                 }
                 Some(AstNode::Array(items))
             }
+            TokenKind::LBrace => {
+                // {a, b, c}, set-builder {x in S | condition} or record {x: 1, y: 2}
+                self.next();
+                if let Some(node) = self.match_set_builder() { return Some(node); }
+                if matches!(self.lookahead_kind(1), Some(TokenKind::Colon)) { return self.parse_record(); }
+                let mut items: Vec<AstNode> = Vec::new();
+                if self.match_kind(TokenKind::RBrace) {
+                    return Some(AstNode::Set(items));
+                }
+                loop {
+                    items.push(self.with_abs_depth(0, |p| p.parse_expression(0))?);
+                    if self.match_kind(TokenKind::Comma) { continue; }
+                    self.expect(TokenKind::RBrace)?;
+                    break;
+                }
+                Some(AstNode::Set(items))
+            }
             TokenKind::Minus => {
                 self.next();
-                let expr: AstNode = self.parse_expression(6)?;
+                let expr: AstNode = self.parse_expression(12)?;
                 Some(AstNode::UnaryOp { op: UnaryOpKind::Negate, expr: Box::new(expr) })
             }
             _ => None,
@@ -376,6 +505,17 @@ impl<'a> Parser<'a> {
                         other => other,
                     };
                 }
+                Some(TokenKind::Dot) => {
+                    // field access p.x
+                    self.next();
+                    let name: String = self.next().filter(|t| t.kind == TokenKind::Identifier)?.lexeme.clone();
+                    lhs = AstNode::Field { expr: Box::new(lhs), name };
+                    // a function stored in a field is called directly: g.area(2)
+                    if self.match_kind(TokenKind::LParen) {
+                        let args: Vec<AstNode> = self.parse_arg_list()?;
+                        lhs = AstNode::CallExpr { callee: Box::new(lhs), args };
+                    }
+                }
                 Some(TokenKind::LBrace) => {
                     self.next();
                     let cond: AstNode = self.parse_condition_expression(0)?;
@@ -389,40 +529,86 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_condition_expression(&mut self, min_bp: u8) -> Option<AstNode> {
-        // same as parse_expression, but treat '=' as equality
+        // same as parse_expression, but treat '=' as equality and without implicit multiplication
         let mut lhs: AstNode = self.parse_prefix()?;
+        let mut chaining: bool = false;
         loop {
-            let op: TokenKind = match self.peek() { Some(t) => (&(*t).kind).clone(), None => break };
-            let (lbp, rbp, bop) = match op {
-                TokenKind::Caret => (7, 6, Some(BinaryOpKind::Pow)),
-                TokenKind::Star => (5, 6, Some(BinaryOpKind::Mul)),
-                TokenKind::Slash => (5, 6, Some(BinaryOpKind::Div)),
-                TokenKind::Plus => (3, 4, Some(BinaryOpKind::Add)),
-                TokenKind::Minus => (3, 4, Some(BinaryOpKind::Sub)),
-                TokenKind::GreaterEq => (2, 3, Some(BinaryOpKind::Gte)),
-                TokenKind::LessEq => (2, 3, Some(BinaryOpKind::Lte)),
-                TokenKind::Greater => (2, 3, Some(BinaryOpKind::Gt)),
-                TokenKind::Less => (2, 3, Some(BinaryOpKind::Lt)),
-                TokenKind::Equal => (2, 3, Some(BinaryOpKind::Eq)),
-                TokenKind::NotEqual => (2, 3, Some(BinaryOpKind::NotEq)),
-                TokenKind::Assign => (2, 3, Some(BinaryOpKind::Eq)), // reinterpret '=' as equality in conditions
-                _ => break,
-            };
+            let Some((lbp, rbp, bop)) = self.infix_operator(true) else { break };
             if lbp < min_bp { break; }
-            self.next();
+            self.consume_operator();
             let rhs: AstNode = self.parse_condition_expression(rbp)?;
-            lhs = AstNode::BinaryOp { op: bop.unwrap(), left: Box::new(lhs), right: Box::new(rhs) };
+            lhs = combine(lhs, bop, rhs, chaining);
+            chaining = is_comparison(&lhs);
         }
         Some(lhs)
     }
 
+    fn infix_operator(&self, condition: bool) -> Option<(u8, u8, BinaryOpKind)> {
+        // (left binding power, right binding power, operator) of the binary operator at the cursor,
+        // loosest first: or/xor, and, comparisons and in, .., + - union \, * / mod intersect, ^
+        let tok: &Token = self.peek()?;
+        Some(match &tok.kind {
+            TokenKind::Caret => (14, 13, BinaryOpKind::Pow),
+            TokenKind::Star => (11, 12, BinaryOpKind::Mul),
+            TokenKind::Slash => (11, 12, BinaryOpKind::Div),
+            TokenKind::Mod => (11, 12, BinaryOpKind::Mod),
+            TokenKind::Plus => (9, 10, BinaryOpKind::Add),
+            TokenKind::Minus => (9, 10, BinaryOpKind::Sub),
+            TokenKind::Backslash => (9, 10, BinaryOpKind::Difference),
+            TokenKind::DotDot => (7, 8, BinaryOpKind::Range),
+            TokenKind::GreaterEq => (5, 6, BinaryOpKind::Gte),
+            TokenKind::LessEq => (5, 6, BinaryOpKind::Lte),
+            TokenKind::Greater => (5, 6, BinaryOpKind::Gt),
+            TokenKind::Less => (5, 6, BinaryOpKind::Lt),
+            TokenKind::Equal => (5, 6, BinaryOpKind::Eq),
+            TokenKind::NotEqual => (5, 6, BinaryOpKind::NotEq),
+            TokenKind::Assign if condition => (5, 6, BinaryOpKind::Eq), // reinterpret '=' as equality in conditions
+            TokenKind::AndAnd => (3, 4, BinaryOpKind::And),
+            // || is two pipes, which cannot otherwise follow an operand
+            TokenKind::Pipe if self.abs_depth == 0 && matches!(self.lookahead_kind(1), Some(TokenKind::Pipe)) => (1, 2, BinaryOpKind::Or),
+            TokenKind::Identifier => match tok.lexeme.as_str() {
+                "intersect" => (11, 12, BinaryOpKind::Intersect),
+                "union" => (9, 10, BinaryOpKind::Union),
+                "in" => (5, 6, BinaryOpKind::In),
+                "and" => (3, 4, BinaryOpKind::And),
+                "or" => (1, 2, BinaryOpKind::Or),
+                "xor" => (1, 2, BinaryOpKind::Xor),
+                _ => return None,
+            },
+            _ => return None,
+        })
+    }
+
+    fn with_abs_depth<T>(&mut self, depth: usize, parse: impl FnOnce(&mut Self) -> Option<T>) -> Option<T> {
+        // parse a nested group with its own |...| depth, restoring ours however the group ends
+        let outer: usize = std::mem::replace(&mut self.abs_depth, depth);
+        let result: Option<T> = parse(self);
+        self.abs_depth = outer;
+        result
+    }
+
+    fn with_bound<T>(&mut self, params: &[String], parse: impl FnOnce(&mut Self) -> Option<T>) -> Option<T> {
+        // parse a function body with its parameters bound, unbinding afterwards those that were not already
+        let added: Vec<String> = params.iter().filter(|p| self.bound.insert((*p).clone())).cloned().collect();
+        let result: Option<T> = parse(self);
+        for p in added { self.bound.remove(&p); }
+        result
+    }
+
+    fn consume_operator(&mut self) {
+        // every operator is one token except ||
+        let pipes: bool = matches!(self.lookahead_kind(0), Some(TokenKind::Pipe));
+        self.next();
+        if pipes { self.next(); }
+    }
+
     fn parse_arg_list(&mut self) -> Option<Vec<AstNode>> {
         let mut args: Vec<AstNode> = Vec::new();
         if self.match_kind(TokenKind::RParen) {
             return Some(args);
         }
         loop {
-            let expr: AstNode = self.parse_expression(0)?;
+            let expr: AstNode = self.with_abs_depth(0, |p| p.parse_expression(0))?;
             (&mut args).push(expr);
             if self.match_kind(TokenKind::Comma) { continue; }
             self.expect(TokenKind::RParen)?;
@@ -442,26 +628,178 @@ impl<'a> Parser<'a> {
         if self.match_kind(kind) { Some(()) } else { None }
     }
 
+    fn match_lambda_params(&mut self) -> Option<Vec<String>> {
+        // matches ( [ident {, ident}] ) => without consuming anything on failure
+        let start = self.pos;
+        self.next(); // (
+        let params = self.parse_params();
+        if params.is_some() && self.match_kind(TokenKind::FatArrow) {
+            return params;
+        }
+        self.pos = start;
+        None
+    }
+
+    fn match_destructure(&mut self) -> Option<Vec<String>> {
+        // matches ( ident, ident {, ident} ) = without consuming anything on failure
+        if !matches!(self.lookahead_kind(0), Some(TokenKind::LParen)) { return None; }
+        let start = self.pos;
+        self.next(); // (
+        match self.parse_params() {
+            Some(names) if names.len() >= 2 && self.match_kind(TokenKind::Assign) => Some(names),
+            _ => {
+                self.pos = start;
+                None
+            }
+        }
+    }
+
+    fn parse_record(&mut self) -> Option<AstNode> {
+        // name: expr {, name: expr} }, after the opening brace
+        let mut fields: Vec<(String, AstNode)> = Vec::new();
+        loop {
+            let name: String = self.next().filter(|t| t.kind == TokenKind::Identifier)?.lexeme.clone();
+            self.expect(TokenKind::Colon)?;
+            fields.push((name, self.with_abs_depth(0, |p| p.parse_expression(0))?));
+            if self.match_kind(TokenKind::Comma) { continue; }
+            self.expect(TokenKind::RBrace)?;
+            break;
+        }
+        Some(AstNode::Record(fields))
+    }
+
+    fn match_set_builder(&mut self) -> Option<AstNode> {
+        // matches var in source | condition } without consuming anything on failure,
+        // so that {x in S} stays a one-element set literal
+        let start = self.pos;
+        let node = self.parse_set_builder();
+        if node.is_none() { self.pos = start; }
+        node
+    }
+
+    fn parse_set_builder(&mut self) -> Option<AstNode> {
+        let var: String = self.next().filter(|t| t.kind == TokenKind::Identifier)?.lexeme.clone();
+        self.next().filter(|t| t.kind == TokenKind::Identifier && t.lexeme == "in")?;
+        // parsed as if inside |...|, so that the `|` ending it and an absolute value after it are not read as `||`
+        let source: AstNode = self.with_abs_depth(self.abs_depth + 1, |p| p.parse_expression(0))?;
+        self.expect(TokenKind::Pipe)?;
+        let condition: AstNode = self.parse_condition_expression(0)?;
+        self.expect(TokenKind::RBrace)?;
+        Some(AstNode::SetBuilder { var, source: Box::new(source), condition: Box::new(condition) })
+    }
+
+    fn closing_brace(&self) -> Option<usize> {
+        // index of the '}' that closes the brace group we are inside
+        let mut depth = 0usize;
+        for (i, tok) in self.tokens.iter().enumerate().skip(self.pos) {
+            match tok.kind {
+                TokenKind::LBrace => depth += 1,
+                TokenKind::RBrace if depth == 0 => return Some(i),
+                TokenKind::RBrace => depth -= 1,
+                TokenKind::Semicolon | TokenKind::Newline | TokenKind::EOF => return None,
+                _ => {}
+            }
+        }
+        None
+    }
+
+    fn match_derivative(&mut self) -> Option<AstNode> {
+        // matches d[^n]/dx[^n] expr (or dy/dx expr) without consuming anything on failure,
+        // so that a plain division such as dy/dx is still parsed as one
+        let start = self.pos;
+        let node = self.parse_derivative();
+        if node.is_none() { self.pos = start; }
+        node
+    }
+
+    fn parse_derivative(&mut self) -> Option<AstNode> {
+        // a d... that is a variable, as in dy = 6; dy/dx, makes this a division
+        let numerator: String = self.next()?.lexeme.clone();
+        if self.bound.contains(&numerator) { return None; }
+        let mut order = 1usize;
+        if numerator == "d" && self.match_kind(TokenKind::Caret) {
+            order = self.next().filter(|t| t.kind == TokenKind::Number)?.lexeme.parse::<usize>().ok()?;
+        }
+        self.expect(TokenKind::Slash)?;
+        // denominator: dx, or d x
+        let denominator: &'a Token = self.tokens.get(self.pos)?;
+        self.pos += 1;
+        if self.bound.contains(&denominator.lexeme) { return None; }
+        let var: String = match denominator {
+            Token { kind: TokenKind::Identifier, lexeme } if lexeme.len() > 1 && lexeme.starts_with('d') => lexeme[1..].to_string(),
+            Token { kind: TokenKind::Identifier, lexeme } if lexeme == "d" => {
+                self.next().filter(|t| t.kind == TokenKind::Identifier)?.lexeme.clone()
+            }
+            _ => return None,
+        };
+        // optional ^n on the denominator, which repeats the order
+        if self.match_kind(TokenKind::Caret) {
+            self.next().filter(|t| t.kind == TokenKind::Number)?;
+        }
+        // something to differentiate must follow
+        if !matches!(self.peek().map(|t| &t.kind), Some(TokenKind::Identifier | TokenKind::LParen | TokenKind::Pipe)) {
+            return None;
+        }
+        // target expression, parentheses optional
+        let expr: AstNode = if self.match_kind(TokenKind::LParen) {
+            let e: AstNode = self.parse_expression(0)?;
+            self.expect(TokenKind::RParen)?;
+            e
+        } else {
+            self.parse_expression(12)?
+        };
+        // d/dx f(a, b) is the partial derivative of f with respect to its parameter x
+        Some(match expr {
+            AstNode::FunctionCall { name, args } => AstNode::DerivativeCall { name, args, var: Some(var), order },
+            expr => AstNode::DerivativeExpr { var, order, expr: Box::new(expr) },
+        })
+    }
+
     fn match_inverse_marker(&mut self) -> bool {
-        // matches ^ - 1 sequence
+        // matches ^ - 1 ( sequence, leaving the '(' for the argument list;
+        // anything else (x^-1, x^-2) is an ordinary power and nothing is consumed
         if matches!(self.lookahead_kind(0), Some(TokenKind::Caret))
             && matches!(self.lookahead_kind(1), Some(TokenKind::Minus))
-            && matches!(self.lookahead_kind(2), Some(TokenKind::Number))
+            && self.tokens.get(self.pos + 2).is_some_and(|t| t.kind == TokenKind::Number && t.lexeme == "1")
+            && matches!(self.lookahead_kind(3), Some(TokenKind::LParen))
         {
-            // ensure the number is 1
-            // consume tokens and verify lexeme
             self.next(); // ^
             self.next(); // -
-            // number token must be "1"
-            if let Some(tok) = self.next() {
-                if (*tok).lexeme == "1" { return true; }
-            }
-            return false;
+            self.next(); // 1
+            return true;
         }
         false
     }
 }
 
+fn comparison_op(op: &BinaryOpKind) -> bool {
+    matches!(op, BinaryOpKind::Eq | BinaryOpKind::NotEq | BinaryOpKind::Gt | BinaryOpKind::Lt | BinaryOpKind::Gte | BinaryOpKind::Lte)
+}
+
+fn is_comparison(node: &AstNode) -> bool {
+    match node {
+        AstNode::Chain { .. } => true,
+        AstNode::BinaryOp { op, .. } => comparison_op(op),
+        _ => false,
+    }
+}
+
+/// lhs op rhs, extending lhs into a chain such as 0 < x <= 1 when both are comparisons.
+fn combine(lhs: AstNode, op: BinaryOpKind, rhs: AstNode, chaining: bool) -> AstNode {
+    if !chaining || !comparison_op(&op) {
+        return AstNode::BinaryOp { op, left: Box::new(lhs), right: Box::new(rhs) };
+    }
+    match lhs {
+        AstNode::Chain { mut operands, mut ops } => {
+            operands.push(rhs);
+            ops.push(op);
+            AstNode::Chain { operands, ops }
+        }
+        AstNode::BinaryOp { op: first, left, right } => AstNode::Chain { operands: vec![*left, *right, rhs], ops: vec![first, op] },
+        other => AstNode::BinaryOp { op, left: Box::new(other), right: Box::new(rhs) },
+    }
+}
+
 fn inverse_name(name: &str) -> String {
     match name {
         "sin" => "asin".to_string(),
@@ -470,6 +808,12 @@ fn inverse_name(name: &str) -> String {
         "sinh" => "asinh".to_string(),
         "cosh" => "acosh".to_string(),
         "tanh" => "atanh".to_string(),
+        "sec" => "asec".to_string(),
+        "csc" => "acsc".to_string(),
+        "cot" => "acot".to_string(),
+        "sech" => "asech".to_string(),
+        "csch" => "acsch".to_string(),
+        "coth" => "acoth".to_string(),
         _ => format!("{}^-1", name),
     }
 }
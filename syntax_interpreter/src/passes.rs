@@ -0,0 +1,371 @@
+// Optimisation passes over a parsed program, run before evaluation with -O: constant folding,
+// inlining of small pure functions, hoisting of loop-invariant code out of the functions given to
+// int, map and friends, and removal of conditionals whose guard is constant
+
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::{AstNode, BinaryOpKind, ImportKind};
+use crate::evaluator::{binary, call_builtin, compare, is_plain_builtin, unary, Value};
+use crate::display::NumberFormat;
+use crate::sandbox::Sandbox;
+
+// names every environment starts with; only the real-valued ones can be folded
+const CONSTANTS: &[(&str, f64)] = &[("e", std::f64::consts::E), ("pi", std::f64::consts::PI), ("inf", f64::INFINITY)];
+// built-ins that call the function they are given many times
+const LOOPS: &[&str] = &["int", "map", "filter", "reduce"];
+// the largest function body, in nodes, that is inlined
+const MAX_INLINE: usize = 16;
+
+/// Optimise a parsed program. The result prints and evaluates to the same values in fewer steps;
+/// only the limits on steps and call depth can tell them apart.
+pub fn optimize(program: AstNode) -> AstNode {
+    let mut stmts: Vec<AstNode> = match program {
+        AstNode::Program(stmts) => stmts,
+        other => vec![other],
+    };
+    let mut o: Optimizer = Optimizer::new(&stmts);
+    // functions are defined before anything runs, so they are optimised first and can be inlined everywhere
+    let defs_first: Vec<usize> = (0..stmts.len()).filter(|k| matches!(stmts[*k], AstNode::FunctionDef { .. }))
+        .chain((0..stmts.len()).filter(|k| !matches!(stmts[*k], AstNode::FunctionDef { .. }))).collect();
+    for k in defs_first {
+        let stmt: AstNode = std::mem::replace(&mut stmts[k], AstNode::Empty);
+        stmts[k] = o.node(stmt, &[]);
+    }
+    AstNode::Program(stmts.into_iter().filter(|s| *s != AstNode::Empty).collect())
+}
+
+struct Optimizer {
+    // constants that keep their value throughout the program
+    constants: HashMap<&'static str, f64>,
+    // names the program defines or imports as functions, which may hide built-ins
+    defined: HashSet<String>,
+    // an import of every name of a module can hide anything
+    open: bool,
+    // functions defined once, which are the ones that may be inlined
+    once: HashSet<String>,
+    // functions small and simple enough to inline: parameters and body
+    inlinable: HashMap<String, (Vec<String>, AstNode)>,
+    // hoisted values so far, for naming the next one
+    hoisted: usize,
+}
+
+impl Optimizer {
+    fn new(stmts: &[AstNode]) -> Self {
+        let mut assigned: HashSet<String> = HashSet::new();
+        let mut defined: HashSet<String> = HashSet::new();
+        let mut defs: HashMap<String, usize> = HashMap::new();
+        let mut open: bool = false;
+        for stmt in stmts {
+            match stmt {
+                AstNode::Assignment { name, .. } => { assigned.insert(name.clone()); }
+                AstNode::Destructure { names, .. } => assigned.extend(names.iter().cloned()),
+                AstNode::FunctionDef { name, .. } => {
+                    defined.insert(name.clone());
+                    *defs.entry(name.clone()).or_default() += 1;
+                }
+                AstNode::Import { kind: ImportKind::All, .. } => open = true,
+                AstNode::Import { kind: ImportKind::Names(names), .. } => {
+                    assigned.extend(names.iter().cloned());
+                    defined.extend(names.iter().cloned());
+                }
+                AstNode::Import { kind: ImportKind::Namespace(alias), .. } => { assigned.insert(alias.clone()); }
+                _ => {}
+            }
+        }
+        let constants: HashMap<&'static str, f64> = if open { HashMap::new() } else {
+            CONSTANTS.iter().filter(|(name, _)| !assigned.contains(*name)).copied().collect()
+        };
+        // a function defined twice is whichever definition comes last; leave it alone
+        let once: HashSet<String> = defs.into_iter().filter(|(_, n)| *n == 1).map(|(name, _)| name).collect();
+        let mut o: Optimizer = Optimizer { constants, defined, open, once, inlinable: HashMap::new(), hoisted: 0 };
+        for stmt in stmts {
+            if let AstNode::FunctionDef { name, params, body, .. } = stmt { o.define(name, params, body); }
+        }
+        o
+    }
+
+    // remember a function definition for inlining if it qualifies
+    fn define(&mut self, name: &str, params: &[String], body: &AstNode) {
+        if !self.open && self.once.contains(name) && size(body) <= MAX_INLINE && self.closed(body, params) {
+            self.inlinable.insert(name.to_string(), (params.to_vec(), body.clone()));
+        }
+    }
+
+    /// `node` optimised, where the names in `bound` are parameters or bound variables.
+    fn node(&mut self, node: AstNode, bound: &[String]) -> AstNode {
+        match node {
+            AstNode::Program(stmts) => AstNode::Program(stmts.into_iter().map(|s| self.node(s, bound)).collect()),
+            AstNode::Template(parts) => AstNode::Template(self.nodes(parts, bound)),
+            AstNode::UnaryOp { op, expr } => {
+                let expr: AstNode = self.node(*expr, bound);
+                match self.literal(&expr, bound) {
+                    Some(v) => fold(unary(&op, v)),
+                    None => None,
+                }.unwrap_or(AstNode::UnaryOp { op, expr: Box::new(expr) })
+            }
+            AstNode::BinaryOp { op, left, right } => {
+                let (left, right): (AstNode, AstNode) = (self.node(*left, bound), self.node(*right, bound));
+                let folded: Option<AstNode> = match (op.clone(), self.literal(&left, bound), self.literal(&right, bound)) {
+                    // false and ..., true or ...: the right side is never evaluated
+                    (BinaryOpKind::And, Some(Value::Bool(false)), _) => Some(AstNode::Bool(false)),
+                    (BinaryOpKind::Or, Some(Value::Bool(true)), _) => Some(AstNode::Bool(true)),
+                    (BinaryOpKind::And | BinaryOpKind::Or, Some(Value::Bool(_)), Some(Value::Bool(r))) => Some(AstNode::Bool(r)),
+                    (BinaryOpKind::And | BinaryOpKind::Or, _, _) => None,
+                    // a range is an array, which is never folded, and building it here would escape the array limit
+                    (BinaryOpKind::Range, _, _) => None,
+                    // text made from a number depends on the format in effect when it runs
+                    (BinaryOpKind::Add, Some(l), Some(r)) if matches!(l, Value::Str(_)) != matches!(r, Value::Str(_)) => None,
+                    (op, Some(l), Some(r)) => fold(binary(&op, l, r, &Sandbox::default(), NumberFormat::default())),
+                    _ => None,
+                };
+                folded.unwrap_or(AstNode::BinaryOp { op, left: Box::new(left), right: Box::new(right) })
+            }
+            AstNode::Chain { operands, ops } => {
+                let operands: Vec<AstNode> = self.nodes(operands, bound);
+                let values: Option<Vec<Value>> = operands.iter().map(|o| self.literal(o, bound)).collect();
+                let folded: Option<AstNode> = values.and_then(|values| {
+                    let mut result: Value = Value::Bool(true);
+                    for (op, pair) in ops.iter().zip(values.windows(2)) {
+                        result = compare(op.clone(), pair[0].clone(), pair[1].clone());
+                        if !matches!(result, Value::Bool(true)) { break; }
+                    }
+                    fold(result)
+                });
+                folded.unwrap_or(AstNode::Chain { operands, ops })
+            }
+            AstNode::Conditional { condition, body } => {
+                let condition: AstNode = self.node(*condition, bound);
+                match self.literal(&condition, bound) {
+                    Some(Value::Bool(true)) => self.node(*body, bound),
+                    // the value of a conditional that does not hold is nothing
+                    Some(Value::Bool(false)) => AstNode::Empty,
+                    _ => AstNode::Conditional { condition: Box::new(condition), body: Box::new(self.node(*body, bound)) },
+                }
+            }
+            AstNode::FunctionCall { name, args } => {
+                let args: Vec<AstNode> = self.nodes(args, bound);
+                if self.builtin(&name) {
+                    let values: Option<Vec<Value>> = args.iter().map(|a| self.literal(a, bound)).collect();
+                    if let Some(folded) = values.and_then(|values| call_builtin(&name, &values)).and_then(fold) { return folded; }
+                }
+                if let Some(inlined) = self.inline(&name, &args, bound) { return inlined; }
+                let args: Vec<AstNode> = if LOOPS.contains(&name.as_str()) && self.builtin_name(&name) {
+                    args.into_iter().map(|a| self.hoist(a)).collect()
+                } else { args };
+                AstNode::FunctionCall { name, args }
+            }
+            AstNode::FunctionDef { name, params, body, memo } => {
+                // a function body sees its parameters, not the variables around its definition
+                let body: AstNode = self.node(*body, &params);
+                // the optimised body may have become small enough, e.g. by inlining the functions it calls
+                self.define(&name, &params, &body);
+                AstNode::FunctionDef { name, params, body: Box::new(body), memo }
+            }
+            AstNode::Lambda { params, body } => {
+                let inner: Vec<String> = [bound, &params[..]].concat();
+                AstNode::Lambda { body: Box::new(self.node(*body, &inner)), params }
+            }
+            AstNode::CallExpr { callee, args } => AstNode::CallExpr { callee: Box::new(self.node(*callee, bound)), args: self.nodes(args, bound) },
+            AstNode::Assignment { name, expr } => AstNode::Assignment { name, expr: Box::new(self.node(*expr, bound)) },
+            AstNode::Destructure { names, expr } => AstNode::Destructure { names, expr: Box::new(self.node(*expr, bound)) },
+            AstNode::Array(items) => AstNode::Array(self.nodes(items, bound)),
+            AstNode::Tuple(items) => AstNode::Tuple(self.nodes(items, bound)),
+            AstNode::Set(items) => AstNode::Set(self.nodes(items, bound)),
+            AstNode::Record(fields) => AstNode::Record(fields.into_iter().map(|(k, e)| (k, self.node(e, bound))).collect()),
+            AstNode::Field { expr, name } => AstNode::Field { expr: Box::new(self.node(*expr, bound)), name },
+            AstNode::Print(args) => AstNode::Print(self.nodes(args, bound)),
+            AstNode::Log { level, args } => AstNode::Log { level, args: self.nodes(args, bound) },
+            AstNode::SetBuilder { var, source, condition } => {
+                let inner: Vec<String> = [bound, std::slice::from_ref(&var)].concat();
+                AstNode::SetBuilder { source: Box::new(self.node(*source, bound)), condition: Box::new(self.node(*condition, &inner)), var }
+            }
+            AstNode::Lim { var, to, side, expr } => {
+                let inner: Vec<String> = [bound, std::slice::from_ref(&var)].concat();
+                AstNode::Lim { to: Box::new(self.node(*to, bound)), expr: Box::new(self.node(*expr, &inner)), var, side }
+            }
+            AstNode::Series { var, at, order, expr } => {
+                let inner: Vec<String> = [bound, std::slice::from_ref(&var)].concat();
+                AstNode::Series {
+                    at: Box::new(self.node(*at, bound)),
+                    order: order.map(|o| Box::new(self.node(*o, bound))),
+                    expr: Box::new(self.node(*expr, &inner)),
+                    var,
+                }
+            }
+            AstNode::DerivativeExpr { var, order, expr } => {
+                let inner: Vec<String> = [bound, std::slice::from_ref(&var)].concat();
+                AstNode::DerivativeExpr { expr: Box::new(self.node(*expr, &inner)), var, order }
+            }
+            AstNode::DerivativeCall { name, args, var, order } => AstNode::DerivativeCall { args: self.nodes(args, bound), name, var, order },
+            // format settings tell a switch from a number by the syntax of the argument, so they are left as written
+            other => other,
+        }
+    }
+
+    fn nodes(&mut self, nodes: Vec<AstNode>, bound: &[String]) -> Vec<AstNode> {
+        nodes.into_iter().map(|n| self.node(n, bound)).collect()
+    }
+
+    // the value of a literal, or of a constant that is not rebound where it appears
+    fn literal(&self, node: &AstNode, bound: &[String]) -> Option<Value> {
+        match node {
+            AstNode::Number(n) => Some(Value::Number(*n)),
+            AstNode::Bool(b) => Some(Value::Bool(*b)),
+            AstNode::Str(s) => Some(Value::Str(s.clone())),
+            AstNode::Variable(name) | AstNode::Constant(name) if !bound.contains(name) => self.constants.get(name.as_str()).map(|x| Value::Number(*x)),
+            _ => None,
+        }
+    }
+
+    // whether a call to `name` certainly reaches the built-in, and the built-in depends only on its arguments
+    fn builtin(&self, name: &str) -> bool {
+        is_plain_builtin(name) && self.builtin_name(name)
+    }
+
+    fn builtin_name(&self, name: &str) -> bool {
+        !self.open && !self.defined.contains(name)
+    }
+
+    // Whether `node` can be evaluated anywhere with the same result and no effects: it uses only
+    // `params`, the constants and built-ins that depend only on their arguments.
+    fn closed(&self, node: &AstNode, params: &[String]) -> bool {
+        match node {
+            AstNode::Variable(name) | AstNode::Constant(name) => params.contains(name) || self.constants.contains_key(name.as_str()),
+            _ => self.simple(node, &|n| self.closed(n, params)),
+        }
+    }
+
+    // Whether `node` has the same value wherever it is evaluated in a function with parameters
+    // `params`, without using them and without effects.
+    fn invariant(&self, node: &AstNode, params: &[String]) -> bool {
+        match node {
+            AstNode::Variable(name) | AstNode::Constant(name) => !params.contains(name),
+            _ => self.simple(node, &|n| self.invariant(n, params)),
+        }
+    }
+
+    // literals, operators, conditions, arrays and calls of plain built-ins whose parts all satisfy `parts`
+    fn simple(&self, node: &AstNode, parts: &dyn Fn(&AstNode) -> bool) -> bool {
+        match node {
+            AstNode::Number(_) | AstNode::Bool(_) | AstNode::Str(_) | AstNode::Empty => true,
+            AstNode::UnaryOp { expr, .. } => parts(expr),
+            AstNode::BinaryOp { left, right, .. } => parts(left) && parts(right),
+            AstNode::Conditional { condition, body } => parts(condition) && parts(body),
+            AstNode::Chain { operands: items, .. } | AstNode::Array(items) | AstNode::Tuple(items) => items.iter().all(parts),
+            AstNode::FunctionCall { name, args } => self.builtin(name) && args.iter().all(parts),
+            _ => false,
+        }
+    }
+
+    // the body of a small function with its parameters replaced by the arguments
+    fn inline(&mut self, name: &str, args: &[AstNode], bound: &[String]) -> Option<AstNode> {
+        let (params, body) = self.inlinable.get(name)?;
+        if args.len() != params.len() { return None; }
+        // the constants in the body must not be rebound where it is inlined
+        if bound.iter().any(|b| !params.contains(b) && mentions(body, b)) { return None; }
+        for (p, a) in params.iter().zip(args) {
+            // an argument is evaluated once; it may only be repeated or dropped when that makes no difference
+            if !self.invariant(a, &[]) { return None; }
+            let trivial: bool = matches!(a, AstNode::Number(_) | AstNode::Bool(_) | AstNode::Str(_) | AstNode::Variable(_) | AstNode::Constant(_));
+            if !trivial && uses(body, p) > 1 { return None; }
+        }
+        let with: HashMap<&String, &AstNode> = params.iter().zip(args).collect();
+        let inlined: AstNode = substitute(body, &with);
+        Some(self.node(inlined, bound))
+    }
+
+    // `(x) => body` with the parts of the body that do not depend on x computed once, as
+    // `((h0, h1) => (x) => body)(part0, part1)`
+    fn hoist(&mut self, f: AstNode) -> AstNode {
+        let AstNode::Lambda { params, body } = f else { return f };
+        let mut parts: Vec<(String, AstNode)> = Vec::new();
+        let body: AstNode = self.extract(*body, &params, &mut parts);
+        if parts.is_empty() { return AstNode::Lambda { params, body: Box::new(body) }; }
+        let (names, values): (Vec<String>, Vec<AstNode>) = parts.into_iter().unzip();
+        AstNode::CallExpr {
+            callee: Box::new(AstNode::Lambda { params: names, body: Box::new(AstNode::Lambda { params, body: Box::new(body) }) }),
+            args: values,
+        }
+    }
+
+    fn extract(&mut self, node: AstNode, params: &[String], parts: &mut Vec<(String, AstNode)>) -> AstNode {
+        let trivial: bool = matches!(node, AstNode::Number(_) | AstNode::Bool(_) | AstNode::Str(_) | AstNode::Variable(_) | AstNode::Constant(_));
+        if !trivial && self.invariant(&node, params) {
+            // names no script can write, so they cannot clash
+            let name: String = format!("${}", self.hoisted);
+            self.hoisted += 1;
+            parts.push((name.clone(), node));
+            return AstNode::Variable(name);
+        }
+        let mut each = |n: Box<AstNode>, parts: &mut Vec<(String, AstNode)>| Box::new(self.extract(*n, params, parts));
+        match node {
+            AstNode::UnaryOp { op, expr } => AstNode::UnaryOp { op, expr: each(expr, parts) },
+            AstNode::BinaryOp { op, left, right } => {
+                let left: Box<AstNode> = each(left, parts);
+                AstNode::BinaryOp { op, left, right: each(right, parts) }
+            }
+            AstNode::Conditional { condition, body } => {
+                let condition: Box<AstNode> = each(condition, parts);
+                AstNode::Conditional { condition, body: each(body, parts) }
+            }
+            AstNode::FunctionCall { name, args } => AstNode::FunctionCall { name, args: args.into_iter().map(|a| *each(Box::new(a), parts)).collect() },
+            AstNode::Array(items) => AstNode::Array(items.into_iter().map(|a| *each(Box::new(a), parts)).collect()),
+            AstNode::Tuple(items) => AstNode::Tuple(items.into_iter().map(|a| *each(Box::new(a), parts)).collect()),
+            AstNode::Chain { operands, ops } => AstNode::Chain { operands: operands.into_iter().map(|a| *each(Box::new(a), parts)).collect(), ops },
+            other => other,
+        }
+    }
+}
+
+// a literal node for a value that has one
+fn fold(v: Value) -> Option<AstNode> {
+    match v {
+        Value::Number(n) => Some(AstNode::Number(n)),
+        Value::Bool(b) => Some(AstNode::Bool(b)),
+        Value::Str(s) if !s.starts_with("ERROR:") => Some(AstNode::Str(s)),
+        _ => None,
+    }
+}
+
+// the number of nodes of a simple expression
+fn size(node: &AstNode) -> usize {
+    1 + children(node).into_iter().map(size).sum::<usize>()
+}
+
+// how often a simple expression refers to `name`
+fn uses(node: &AstNode, name: &str) -> usize {
+    match node {
+        AstNode::Variable(n) | AstNode::Constant(n) => usize::from(n == name),
+        _ => children(node).into_iter().map(|c| uses(c, name)).sum(),
+    }
+}
+
+fn mentions(node: &AstNode, name: &str) -> bool {
+    uses(node, name) > 0
+}
+
+fn children(node: &AstNode) -> Vec<&AstNode> {
+    match node {
+        AstNode::UnaryOp { expr, .. } => vec![expr],
+        AstNode::BinaryOp { left, right, .. } => vec![left, right],
+        AstNode::Conditional { condition, body } => vec![condition, body],
+        AstNode::Chain { operands: items, .. } | AstNode::Array(items) | AstNode::Tuple(items) | AstNode::FunctionCall { args: items, .. } => items.iter().collect(),
+        _ => Vec::new(),
+    }
+}
+
+// a simple expression with variables replaced
+fn substitute(node: &AstNode, with: &HashMap<&String, &AstNode>) -> AstNode {
+    let sub = |n: &AstNode| Box::new(substitute(n, with));
+    match node {
+        AstNode::Variable(name) | AstNode::Constant(name) => with.get(name).map_or_else(|| node.clone(), |a| (*a).clone()),
+        AstNode::UnaryOp { op, expr } => AstNode::UnaryOp { op: op.clone(), expr: sub(expr) },
+        AstNode::BinaryOp { op, left, right } => AstNode::BinaryOp { op: op.clone(), left: sub(left), right: sub(right) },
+        AstNode::Conditional { condition, body } => AstNode::Conditional { condition: sub(condition), body: sub(body) },
+        AstNode::Chain { operands, ops } => AstNode::Chain { operands: operands.iter().map(|o| substitute(o, with)).collect(), ops: ops.clone() },
+        AstNode::Array(items) => AstNode::Array(items.iter().map(|o| substitute(o, with)).collect()),
+        AstNode::Tuple(items) => AstNode::Tuple(items.iter().map(|o| substitute(o, with)).collect()),
+        AstNode::FunctionCall { name, args } => AstNode::FunctionCall { name: name.clone(), args: args.iter().map(|o| substitute(o, with)).collect() },
+        other => other.clone(),
+    }
+}
@@ -0,0 +1,398 @@
+// Polynomial values: construction, arithmetic, calculus and root finding
+
+use num_complex::Complex64;
+
+use crate::ast::{AstNode, BinaryOpKind, UnaryOpKind};
+use crate::display::NumberFormat;
+use crate::evaluator::{display_value, eval, Env, Function, Value};
+use crate::sandbox::Sandbox;
+
+pub const NAMES: &[&str] = &[
+    "poly", "coeffs", "degree", "polyval", "polyder", "polyint", "polydiv", "polygcd", "compose", "roots",
+];
+
+// coefficients smaller than this (relative to the largest) are treated as zero by division and gcd
+const EPS: f64 = 1e-10;
+// the highest power of a polynomial that is expanded
+const MAX_POWER: f64 = 64.0;
+
+/// A polynomial in one variable, stored with the constant term first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polynomial {
+    pub coeffs: Vec<f64>,
+    pub var: String,
+}
+
+impl Polynomial {
+    pub fn new(mut coeffs: Vec<f64>, var: &str) -> Self {
+        while coeffs.len() > 1 && coeffs[coeffs.len() - 1] == 0.0 { coeffs.pop(); }
+        if coeffs.is_empty() { coeffs.push(0.0); }
+        Polynomial { coeffs, var: var.to_string() }
+    }
+
+    fn constant(c: f64, var: &str) -> Self {
+        Polynomial::new(vec![c], var)
+    }
+
+    pub fn degree(&self) -> usize {
+        self.coeffs.len() - 1
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.coeffs.len() == 1 && self.coeffs[0] == 0.0
+    }
+
+    /// Evaluate with Horner's scheme.
+    pub fn eval(&self, x: f64) -> f64 {
+        self.coeffs.iter().rev().fold(0.0, |acc, c| acc * x + c)
+    }
+
+    pub fn eval_complex(&self, z: Complex64) -> Complex64 {
+        self.coeffs.iter().rev().fold(Complex64::new(0.0, 0.0), |acc, c| acc * z + c)
+    }
+
+    pub fn add(&self, other: &Polynomial) -> Polynomial {
+        let n: usize = self.coeffs.len().max(other.coeffs.len());
+        let c: Vec<f64> = (0..n).map(|i| self.coeffs.get(i).unwrap_or(&0.0) + other.coeffs.get(i).unwrap_or(&0.0)).collect();
+        Polynomial::new(c, &self.var)
+    }
+
+    pub fn scale(&self, k: f64) -> Polynomial {
+        Polynomial::new(self.coeffs.iter().map(|c| c * k).collect(), &self.var)
+    }
+
+    pub fn sub(&self, other: &Polynomial) -> Polynomial {
+        self.add(&other.scale(-1.0))
+    }
+
+    pub fn mul(&self, other: &Polynomial) -> Polynomial {
+        let mut c: Vec<f64> = vec![0.0; self.coeffs.len() + other.coeffs.len() - 1];
+        for (i, a) in self.coeffs.iter().enumerate() {
+            for (j, b) in other.coeffs.iter().enumerate() {
+                c[i + j] += a * b;
+            }
+        }
+        Polynomial::new(c, &self.var)
+    }
+
+    pub fn pow(&self, n: u32) -> Polynomial {
+        let mut acc: Polynomial = Polynomial::constant(1.0, &self.var);
+        for _ in 0..n { acc = acc.mul(self); }
+        acc
+    }
+
+    /// Long division: returns (quotient, remainder) with deg(remainder) < deg(divisor).
+    pub fn div_rem(&self, divisor: &Polynomial) -> Option<(Polynomial, Polynomial)> {
+        if divisor.is_zero() { return None; }
+        let mut rem: Vec<f64> = self.coeffs.clone();
+        let d: usize = divisor.degree();
+        let lead: f64 = divisor.coeffs[d];
+        if rem.len() <= d {
+            return Some((Polynomial::constant(0.0, &self.var), self.clone()));
+        }
+        let mut quot: Vec<f64> = vec![0.0; rem.len() - d];
+        for k in (0..quot.len()).rev() {
+            let q: f64 = rem[k + d] / lead;
+            quot[k] = q;
+            for (j, c) in divisor.coeffs.iter().enumerate() {
+                rem[k + j] -= q * c;
+            }
+            rem[k + d] = 0.0;
+        }
+        rem.truncate(d.max(1));
+        Some((Polynomial::new(quot, &self.var), Polynomial::new(rem, &self.var).chop(self.magnitude())))
+    }
+
+    /// Greatest common divisor, normalised to be monic.
+    pub fn gcd(&self, other: &Polynomial) -> Polynomial {
+        let scale: f64 = self.magnitude().max(other.magnitude());
+        let (mut a, mut b) = (self.clone(), other.clone());
+        while !b.chop(scale).is_zero() {
+            let (_, r) = a.div_rem(&b).expect("divisor is non-zero");
+            a = b;
+            b = r;
+        }
+        let lead: f64 = a.coeffs[a.degree()];
+        if lead == 0.0 { a } else { a.scale(1.0 / lead) }
+    }
+
+    /// self(inner(x))
+    pub fn compose(&self, inner: &Polynomial) -> Polynomial {
+        let mut acc: Polynomial = Polynomial::constant(0.0, &inner.var);
+        for c in self.coeffs.iter().rev() {
+            acc = acc.mul(inner).add(&Polynomial::constant(*c, &inner.var));
+        }
+        acc
+    }
+
+    pub fn derivative(&self) -> Polynomial {
+        let c: Vec<f64> = self.coeffs.iter().enumerate().skip(1).map(|(i, c)| c * i as f64).collect();
+        Polynomial::new(c, &self.var)
+    }
+
+    /// Antiderivative with constant term `c0`.
+    pub fn integral(&self, c0: f64) -> Polynomial {
+        let mut c: Vec<f64> = vec![c0];
+        c.extend(self.coeffs.iter().enumerate().map(|(i, c)| c / (i as f64 + 1.0)));
+        Polynomial::new(c, &self.var)
+    }
+
+    /// All complex roots (with multiplicity), by the Durand-Kerner iteration.
+    pub fn roots(&self) -> Vec<Complex64> {
+        // x = 0 roots are split off exactly; they would slow the iteration down
+        let zeros: usize = self.coeffs.iter().take_while(|c| **c == 0.0).count().min(self.degree());
+        let mut roots: Vec<Complex64> = vec![Complex64::new(0.0, 0.0); zeros];
+        let p: Polynomial = Polynomial::new(self.coeffs[zeros..].to_vec(), &self.var);
+        // repeated roots are badly conditioned; p / gcd(p, p') has the same roots, each only once
+        let g: Polynomial = p.gcd(&p.derivative());
+        if g.degree() > 0 {
+            if let Some((square_free, _)) = p.div_rem(&g) {
+                roots.extend(square_free.simple_roots());
+                roots.extend(g.roots());
+            }
+        } else {
+            roots.extend(p.simple_roots());
+        }
+        // snap imaginary parts that are only rounding noise, and real roots that are exact integers,
+        // then order by real, then imaginary part
+        for r in roots.iter_mut() {
+            if r.im.abs() <= 1e-9 * r.norm().max(1.0) { r.im = 0.0; }
+            if r.im == 0.0 && (r.re - r.re.round()).abs() < 1e-9 && self.eval(r.re.round()) == 0.0 { r.re = r.re.round(); }
+        }
+        roots.sort_by(|a, b| a.re.total_cmp(&b.re).then(a.im.total_cmp(&b.im)));
+        roots
+    }
+
+    fn simple_roots(&self) -> Vec<Complex64> {
+        let n: usize = self.degree();
+        let monic: Polynomial = self.scale(1.0 / self.coeffs[n]);
+        match n {
+            0 => return Vec::new(),
+            1 => return vec![Complex64::new(-monic.coeffs[0], 0.0)],
+            _ => {}
+        }
+        // start on a circle bounding the roots, at angles that avoid symmetry
+        let radius: f64 = 1.0 + monic.coeffs[..n].iter().map(|c| c.abs()).fold(0.0, f64::max);
+        let mut z: Vec<Complex64> = (0..n)
+            .map(|k| Complex64::from_polar(radius, 0.4 + 2.0 * std::f64::consts::PI * k as f64 / n as f64))
+            .collect();
+        for _ in 0..1000 {
+            let mut change: f64 = 0.0;
+            for i in 0..n {
+                let mut denom: Complex64 = Complex64::new(1.0, 0.0);
+                for (j, zj) in z.iter().enumerate() {
+                    if i != j { denom *= z[i] - zj; }
+                }
+                if denom.norm() == 0.0 { denom = Complex64::new(EPS, EPS); }
+                let step: Complex64 = monic.eval_complex(z[i]) / denom;
+                z[i] -= step;
+                change = change.max(step.norm() / z[i].norm().max(1.0));
+            }
+            if change < 1e-15 { break; }
+        }
+        // a couple of Newton steps remove the last bits of error
+        let d: Polynomial = monic.derivative();
+        for r in z.iter_mut() {
+            for _ in 0..2 {
+                let slope: Complex64 = d.eval_complex(*r);
+                if slope.norm() == 0.0 { break; }
+                *r -= monic.eval_complex(*r) / slope;
+            }
+        }
+        z
+    }
+
+    fn magnitude(&self) -> f64 {
+        self.coeffs.iter().map(|c| c.abs()).fold(0.0, f64::max)
+    }
+
+    fn chop(&self, scale: f64) -> Polynomial {
+        let tol: f64 = EPS * scale.max(1.0);
+        Polynomial::new(self.coeffs.iter().map(|c| if c.abs() < tol { 0.0 } else { *c }).collect(), &self.var)
+    }
+
+    /// Standard form, highest power first, e.g. `x^2 - 3x + 2`.
+    pub fn display(&self, f: NumberFormat) -> String {
+        let mut out: String = String::new();
+        for (i, c) in self.coeffs.iter().enumerate().rev() {
+            if *c == 0.0 && !(i == 0 && out.is_empty()) { continue; }
+            let mag: f64 = c.abs();
+            if out.is_empty() {
+                if *c < 0.0 { out.push('-'); }
+            } else {
+                out.push_str(if *c < 0.0 { " - " } else { " + " });
+            }
+            if mag != 1.0 || i == 0 { out.push_str(&crate::display::number(mag, f)); }
+            match i {
+                0 => {}
+                1 => out.push_str(&self.var),
+                _ => out.push_str(&format!("{}^{}", self.var, i)),
+            }
+        }
+        out
+    }
+}
+
+/// Apply an arithmetic operator when at least one operand is a polynomial.
+pub fn binary(op: &BinaryOpKind, l: Value, r: Value, sandbox: &Sandbox) -> Value {
+    let var: String = match (&l, &r) {
+        (Value::Polynomial(p), _) | (_, Value::Polynomial(p)) => p.var.clone(),
+        _ => "x".to_string(),
+    };
+    let as_poly = |v: &Value| match v {
+        Value::Polynomial(p) => Some(p.clone()),
+        Value::Number(n) => Some(Polynomial::constant(*n, &var)),
+        _ => None,
+    };
+    let (Some(a), Some(b)) = (as_poly(&l), as_poly(&r)) else {
+        return Value::Str(format!("ERROR: cannot combine {} and {}", display_value(&l), display_value(&r)));
+    };
+    let res: Result<Polynomial, String> = match op {
+        BinaryOpKind::Add => Ok(a.add(&b)),
+        BinaryOpKind::Sub => Ok(a.sub(&b)),
+        BinaryOpKind::Mul => Ok(a.mul(&b)),
+        BinaryOpKind::Div if b.degree() == 0 && !b.is_zero() => Ok(a.scale(1.0 / b.coeffs[0])),
+        BinaryOpKind::Div => Err("polynomials can only be divided by a non-zero number; use polydiv(p, q)".to_string()),
+        BinaryOpKind::Mod => a.div_rem(&b).map(|(_, r)| r).ok_or_else(|| "division by the zero polynomial".to_string()),
+        BinaryOpKind::Pow => match r {
+            // the power has degree * n + 1 coefficients
+            Value::Number(n) if n >= 0.0 && n.fract() == 0.0 && n <= MAX_POWER => {
+                sandbox.check_len(a.degree() * n as usize + 1).map(|()| a.pow(n as u32))
+            }
+            Value::Number(n) if n >= 0.0 && n.fract() == 0.0 => Err(format!("polynomial powers above {} are not expanded", MAX_POWER)),
+            _ => Err("polynomial powers must be non-negative integers".to_string()),
+        },
+        BinaryOpKind::Eq => return Value::Bool(a == b),
+        BinaryOpKind::NotEq => return Value::Bool(a != b),
+        _ => Err("polynomials cannot be ordered".to_string()),
+    };
+    match res {
+        Ok(p) => Value::Polynomial(p),
+        Err(msg) => Value::Str(format!("ERROR: {}", msg)),
+    }
+}
+
+/// Evaluate a polynomial at a number, complex number or (element-wise) array.
+pub fn apply(p: &Polynomial, x: &Value) -> Value {
+    match x {
+        Value::Number(x) => Value::Number(p.eval(*x)),
+        Value::Complex(z) => Value::Complex(p.eval_complex(*z)),
+        Value::Array(items) => Value::Array(items.iter().map(|v| apply(p, v)).collect()),
+        Value::Polynomial(q) => Value::Polynomial(p.compose(q)),
+        other => Value::Str(format!("ERROR: cannot evaluate a polynomial at {}", display_value(other))),
+    }
+}
+
+/// Dispatch a polynomial built-in. Returns `None` when `name` is not one of [`NAMES`].
+pub fn call(name: &str, args: &[Value], env: &mut Env) -> Option<Value> {
+    if !NAMES.contains(&name) { return None; }
+    Some(dispatch(name, args, env).unwrap_or_else(|msg| Value::Str(format!("ERROR: {}", msg))))
+}
+
+fn dispatch(name: &str, args: &[Value], env: &mut Env) -> Result<Value, String> {
+    if name == "poly" {
+        // poly([a_n, ..., a_1, a_0]) with the highest power first, or poly(f) for a polynomial function f
+        return match args.first() {
+            Some(Value::Array(items)) => {
+                let mut c: Vec<f64> = items.iter().map(|v| match v {
+                    Value::Number(n) => Ok(*n),
+                    _ => Err("poly: coefficients must be numbers".to_string()),
+                }).collect::<Result<_, _>>()?;
+                c.reverse();
+                Ok(Value::Polynomial(Polynomial::new(c, "x")))
+            }
+            Some(Value::Function(f)) => from_function(f, env).map(Value::Polynomial),
+            Some(Value::Polynomial(p)) => Ok(Value::Polynomial(p.clone())),
+            _ => Err("poly expects an array of coefficients or a function of one variable".to_string()),
+        };
+    }
+    let p: &Polynomial = match args.first() {
+        Some(Value::Polynomial(p)) => p,
+        _ => return Err(format!("{} expects a polynomial as its first argument", name)),
+    };
+    let other = || match args.get(1) {
+        Some(Value::Polynomial(q)) => Ok(q.clone()),
+        Some(Value::Number(n)) => Ok(Polynomial::constant(*n, &p.var)),
+        _ => Err(format!("{} expects 2 polynomials", name)),
+    };
+    match name {
+        "coeffs" => Ok(Value::Array(p.coeffs.iter().rev().map(|c| Value::Number(*c)).collect())),
+        "degree" => Ok(Value::Number(p.degree() as f64)),
+        "polyval" => args.get(1).map(|x| apply(p, x)).ok_or_else(|| "polyval expects 2 arguments (p, x)".to_string()),
+        "polyder" => {
+            let n: usize = match args.get(1) { Some(Value::Number(n)) if *n >= 0.0 => *n as usize, _ => 1 };
+            Ok(Value::Polynomial((0..n).fold(p.clone(), |acc, _| acc.derivative())))
+        }
+        "polyint" => {
+            let c0: f64 = match args.get(1) { Some(Value::Number(c)) => *c, _ => 0.0 };
+            Ok(Value::Polynomial(p.integral(c0)))
+        }
+        "polydiv" => {
+            let (q, r) = p.div_rem(&other()?).ok_or("polydiv: division by the zero polynomial")?;
+            Ok(Value::Array(vec![Value::Polynomial(q), Value::Polynomial(r)]))
+        }
+        "polygcd" => Ok(Value::Polynomial(p.gcd(&other()?))),
+        "compose" => Ok(Value::Polynomial(p.compose(&other()?))),
+        "roots" => {
+            if p.is_zero() { return Err("roots: the zero polynomial has infinitely many roots".to_string()); }
+            Ok(Value::Array(p.roots().into_iter().map(|z| if z.im == 0.0 { Value::Number(z.re) } else { Value::Complex(z) }).collect()))
+        }
+        _ => Err(format!("function '{}' does not exist or is not implemented", name)),
+    }
+}
+
+fn from_function(f: &Function, env: &Env) -> Result<Polynomial, String> {
+    let [var] = f.params.as_slice() else {
+        return Err("poly: function must take exactly one parameter".to_string());
+    };
+    let mut local: Env = env.clone();
+    for (k, v) in &f.captured { local.vars.insert(k.clone(), v.clone()); }
+    local.vars.remove(var);
+    from_ast(&f.body, var, &mut local).ok_or_else(|| "poly: function body is not a polynomial".to_string())
+}
+
+/// Recognise `node` as a polynomial in `var`; subexpressions free of `var` are evaluated to constants.
+pub fn from_ast(node: &AstNode, var: &str, env: &mut Env) -> Option<Polynomial> {
+    if !mentions(node, var) {
+        return match eval(node, env) {
+            Value::Number(n) => Some(Polynomial::constant(n, var)),
+            _ => None,
+        };
+    }
+    match node {
+        AstNode::Variable(name) if name == var => Some(Polynomial::new(vec![0.0, 1.0], var)),
+        AstNode::UnaryOp { op: UnaryOpKind::Negate, expr } => Some(from_ast(expr, var, env)?.scale(-1.0)),
+        AstNode::BinaryOp { op, left, right } => {
+            let a: Polynomial = from_ast(left, var, env)?;
+            match op {
+                BinaryOpKind::Pow if !mentions(right, var) => match eval(right, env) {
+                    Value::Number(n) if n >= 0.0 && n.fract() == 0.0 && n <= MAX_POWER => Some(a.pow(n as u32)),
+                    _ => None,
+                },
+                BinaryOpKind::Add => Some(a.add(&from_ast(right, var, env)?)),
+                BinaryOpKind::Sub => Some(a.sub(&from_ast(right, var, env)?)),
+                BinaryOpKind::Mul => Some(a.mul(&from_ast(right, var, env)?)),
+                BinaryOpKind::Div => {
+                    let b: Polynomial = from_ast(right, var, env)?;
+                    if b.degree() == 0 && !b.is_zero() { Some(a.scale(1.0 / b.coeffs[0])) } else { None }
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn mentions(node: &AstNode, var: &str) -> bool {
+    match node {
+        AstNode::Variable(name) => name == var,
+        AstNode::UnaryOp { expr, .. } => mentions(expr, var),
+        AstNode::BinaryOp { left, right, .. } => mentions(left, var) || mentions(right, var),
+        AstNode::FunctionCall { args, .. } | AstNode::DerivativeCall { args, .. } | AstNode::Array(args) => args.iter().any(|a| mentions(a, var)),
+        AstNode::Conditional { condition, body } => mentions(condition, var) || mentions(body, var),
+        AstNode::Lambda { body, .. } => mentions(body, var),
+        AstNode::DerivativeExpr { expr, .. } => mentions(expr, var),
+        AstNode::Lim { to, expr, .. } => mentions(to, var) || mentions(expr, var),
+        _ => false,
+    }
+}
@@ -0,0 +1,158 @@
+// Random number built-ins backed by a seedable generator
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::evaluator::{Env, Value};
+
+pub const NAMES: &[&str] = &["rand", "seed", "randint", "randn", "choice", "shuffle"];
+
+/// Built-ins that use the generator, so that a function calling one cannot be memoised: those that
+/// draw (including `sample` from stats) differ from call to call, and `seed` restarts the stream,
+/// which a remembered call would skip.
+pub const UNMEMOISABLE: &[&str] = &["rand", "seed", "randint", "randn", "choice", "shuffle", "sample"];
+
+/// Handle to the interpreter's random number generator.
+///
+/// Clones share the same stream, so a function call draws from the same sequence as its caller.
+/// ChaCha8 is used because its output for a given seed is fixed across platforms and releases.
+#[derive(Clone)]
+pub struct SharedRng(Rc<RefCell<ChaCha8Rng>>);
+
+impl Default for SharedRng {
+    fn default() -> Self {
+        SharedRng(Rc::new(RefCell::new(ChaCha8Rng::from_rng(&mut rand::rng()))))
+    }
+}
+
+impl SharedRng {
+    /// Restart the stream from `seed`; every handle sharing this generator sees the new sequence.
+    pub fn reseed(&self, seed: u64) {
+        *self.0.borrow_mut() = ChaCha8Rng::seed_from_u64(seed);
+    }
+
+    pub fn next_u64(&self) -> u64 {
+        self.0.borrow_mut().next_u64()
+    }
+
+    /// Uniform number in [0, 1) with 53 random bits.
+    pub fn uniform(&self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform number in the open interval (0, 1), for inverse transform sampling.
+    pub fn open_uniform(&self) -> f64 {
+        ((self.next_u64() >> 11) as f64 + 0.5) / (1u64 << 53) as f64
+    }
+
+    /// Uniform integer in [0, n) without modulo bias.
+    pub fn below(&self, n: u64) -> u64 {
+        let zone: u64 = u64::MAX - u64::MAX % n;
+        loop {
+            let x: u64 = self.next_u64();
+            if x < zone { return x % n; }
+        }
+    }
+
+    /// Standard normal deviate (Marsaglia polar method).
+    pub fn normal(&self) -> f64 {
+        loop {
+            let u: f64 = 2.0 * self.uniform() - 1.0;
+            let v: f64 = 2.0 * self.uniform() - 1.0;
+            let s: f64 = u * u + v * v;
+            if s > 0.0 && s < 1.0 {
+                return u * (-2.0 * s.ln() / s).sqrt();
+            }
+        }
+    }
+}
+
+/// Dispatch a random built-in. Returns `None` when `name` is not one of [`NAMES`].
+pub fn call(name: &str, args: &[Value], env: &mut Env) -> Option<Value> {
+    if !NAMES.contains(&name) { return None; }
+    Some(dispatch(name, args, env).unwrap_or_else(|msg| Value::Str(format!("ERROR: {}", msg))))
+}
+
+fn dispatch(name: &str, args: &[Value], env: &Env) -> Result<Value, String> {
+    let rng: &SharedRng = &env.shared.rng;
+    match name {
+        "rand" => match args {
+            // rand() -> [0,1)
+            [] => Ok(Value::Number(rng.uniform())),
+            // rand(min, max) -> [min, max)
+            [Value::Number(min), Value::Number(max)] => Ok(Value::Number(min + (max - min) * rng.uniform())),
+            _ => Err("rand expects 0 or 2 arguments (min, max)".to_string()),
+        },
+        "seed" => match args {
+            [Value::Number(n)] if n.fract() == 0.0 && n.abs() < 2f64.powi(63) => {
+                // negative seeds are allowed and map onto distinct streams
+                rng.reseed(*n as i64 as u64);
+                Ok(Value::Unit)
+            }
+            _ => Err("seed expects an integer".to_string()),
+        },
+        "randint" => {
+            // randint(lo, hi): integer in [lo, hi], both ends included
+            let (lo, hi) = match args {
+                [Value::Number(lo), Value::Number(hi), ..] if args.len() <= 3 && lo.fract() == 0.0 && hi.fract() == 0.0 && lo <= hi => (*lo, *hi),
+                _ => return Err("randint expects 2 integers (lo, hi) with lo <= hi and an optional count".to_string()),
+            };
+            let span: f64 = hi - lo + 1.0;
+            if span > 2f64.powi(53) {
+                return Err("randint: range is too large".to_string());
+            }
+            repeat(count_arg("randint", args.get(2), env)?, || Value::Number(lo + rng.below(span as u64) as f64))
+        }
+        "randn" => {
+            // randn([n]) standard normal; randn(mean, std [, n])
+            let (mean, std, n): (f64, f64, Option<&Value>) = match args {
+                [] | [_] => (0.0, 1.0, args.first()),
+                [Value::Number(m), Value::Number(s), ..] if *s >= 0.0 => (*m, *s, args.get(2)),
+                _ => return Err("randn expects ([n]) or (mean, std [, n])".to_string()),
+            };
+            repeat(count_arg("randn", n, env)?, || Value::Number(mean + std * rng.normal()))
+        }
+        "choice" => {
+            // choice(a [, n]): one element, or n elements drawn with replacement
+            let items: &Vec<Value> = match args.first() {
+                Some(Value::Array(a)) if !a.is_empty() => a,
+                _ => return Err("choice expects a non-empty array".to_string()),
+            };
+            repeat(count_arg("choice", args.get(1), env)?, || items[rng.below(items.len() as u64) as usize].clone())
+        }
+        "shuffle" => {
+            let Some(Value::Array(items)) = args.first() else {
+                return Err("shuffle expects an array".to_string());
+            };
+            // Fisher-Yates; returns a new array and leaves the argument untouched
+            let mut out: Vec<Value> = items.clone();
+            for i in (1..out.len()).rev() {
+                let j: usize = rng.below(i as u64 + 1) as usize;
+                out.swap(i, j);
+            }
+            Ok(Value::Array(out))
+        }
+        _ => Err(format!("function '{}' does not exist or is not implemented", name)),
+    }
+}
+
+fn count_arg(name: &str, n: Option<&Value>, env: &Env) -> Result<Option<usize>, String> {
+    match n {
+        None => Ok(None),
+        Some(Value::Number(n)) if *n >= 0.0 && n.fract() == 0.0 => {
+            env.shared.sandbox.check_len(*n as usize)?;
+            Ok(Some(*n as usize))
+        }
+        _ => Err(format!("{}: count must be a non-negative integer", name)),
+    }
+}
+
+fn repeat(n: Option<usize>, mut draw: impl FnMut() -> Value) -> Result<Value, String> {
+    match n {
+        None => Ok(draw()),
+        Some(n) => Ok(Value::Array((0..n).map(|_| draw()).collect())),
+    }
+}
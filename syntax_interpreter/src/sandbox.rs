@@ -0,0 +1,236 @@
+// Limits on what a script may use: evaluation steps, call depth, running time and array sizes,
+// and which files and clocks it may access
+
+use std::cell::RefCell;
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+/// Nested function calls allowed by default.
+pub const DEFAULT_MAX_DEPTH: usize = 1000;
+
+/// Native stack the evaluator may use by default, in bytes: safe on any thread with the usual
+/// 2 MiB stack. A call can take tens of kilobytes in a debug build, so programs that run
+/// scripts on a bigger stack can raise this to allow deeper recursion.
+pub const DEFAULT_MAX_STACK: usize = 1 << 20;
+
+/// Elements allowed in one array by default: enough for any reasonable script, and well short of
+/// the memory that would make an allocation abort the process.
+pub const DEFAULT_MAX_ARRAY: usize = 10_000_000;
+
+/// Results remembered for each memoised function by default.
+pub const DEFAULT_MAX_MEMO: usize = 100_000;
+
+// the clock is read once per this many steps
+const TIME_CHECK_INTERVAL: u64 = 1024;
+
+/// How much a run may use; `None` means unlimited.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limits {
+    // expressions evaluated
+    pub max_steps: Option<u64>,
+    // nested function calls
+    pub max_depth: Option<usize>,
+    // native stack used by nested calls, in bytes; exceeding it is also a recursion error
+    pub max_stack: Option<usize>,
+    // wall-clock time from the last reset (or the first step)
+    pub timeout: Option<Duration>,
+    // elements in one array
+    pub max_array_len: Option<usize>,
+    // results remembered for each memoised function; later results are computed again
+    pub max_memo: Option<usize>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits { max_steps: None, max_depth: Some(DEFAULT_MAX_DEPTH), max_stack: Some(DEFAULT_MAX_STACK), timeout: None, max_array_len: Some(DEFAULT_MAX_ARRAY),
+            max_memo: Some(DEFAULT_MAX_MEMO) }
+    }
+}
+
+/// Which files a run may read or write.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Access {
+    All,
+    // these files and everything under these directories; empty allows nothing
+    Only(Vec<PathBuf>),
+}
+
+impl Access {
+    pub fn none() -> Access {
+        Access::Only(Vec::new())
+    }
+
+    /// Also allow `path` (a file or a directory).
+    pub fn allow(&mut self, path: &Path) {
+        match self {
+            Access::All => {}
+            Access::Only(paths) => paths.push(absolute(path)),
+        }
+    }
+
+    pub fn allows(&self, path: &Path) -> bool {
+        match self {
+            Access::All => true,
+            Access::Only(paths) => {
+                let path: PathBuf = absolute(path);
+                paths.iter().any(|p| path.starts_with(p))
+            }
+        }
+    }
+}
+
+// The path with symbolic links and .. resolved, so that it cannot escape an allowed directory.
+// Components are resolved one at a time, so a file that does not exist yet, such as a new log
+// file, is placed under the real directory that does.
+fn absolute(path: &Path) -> PathBuf {
+    let mut resolved: PathBuf = if path.is_absolute() { PathBuf::new() } else { std::env::current_dir().unwrap_or_default() };
+    for part in path.components() {
+        match part {
+            Component::CurDir => {}
+            Component::ParentDir => { resolved.pop(); }
+            part => {
+                resolved.push(part);
+                if let Ok(real) = resolved.canonicalize() { resolved = real; }
+            }
+        }
+    }
+    resolved
+}
+
+/// What a run may touch outside the interpreter. Everything is allowed by default.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Capabilities {
+    // files read by import
+    pub read: Access,
+    // files written on the script's behalf, such as the log file
+    pub write: Access,
+    // the current time, shown in log messages
+    pub clock: bool,
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Capabilities { read: Access::All, write: Access::All, clock: true }
+    }
+}
+
+impl Capabilities {
+    /// Nothing allowed: no file access and no clock.
+    pub fn none() -> Self {
+        Capabilities { read: Access::none(), write: Access::none(), clock: false }
+    }
+
+    pub fn check_read(&self, path: &Path) -> Result<(), String> {
+        if self.read.allows(path) { Ok(()) } else { Err(format!("reading '{}' is not allowed", path.display())) }
+    }
+
+    pub fn check_write(&self, path: &Path) -> Result<(), String> {
+        if self.write.allows(path) { Ok(()) } else { Err(format!("writing '{}' is not allowed", path.display())) }
+    }
+}
+
+/// The limits of a run and what it has used so far. Exceeding any limit stops the run: every later step fails with
+/// the same error until [`Sandbox::reset`], so that the error cannot be lost along the way.
+#[derive(Clone, Default)]
+pub struct Sandbox(Rc<RefCell<State>>);
+
+#[derive(Default)]
+struct State {
+    limits: Limits,
+    capabilities: Capabilities,
+    steps: u64,
+    depth: usize,
+    // the stack address of the outermost call
+    stack_base: usize,
+    started: Option<Instant>,
+    exceeded: Option<String>,
+}
+
+impl Sandbox {
+    pub fn set_limits(&self, limits: Limits) {
+        self.0.borrow_mut().limits = limits;
+    }
+
+    pub fn set_capabilities(&self, capabilities: Capabilities) {
+        self.0.borrow_mut().capabilities = capabilities;
+    }
+
+    pub fn check_read(&self, path: &Path) -> Result<(), String> {
+        self.0.borrow().capabilities.check_read(path)
+    }
+
+    pub fn allows_clock(&self) -> bool {
+        self.0.borrow().capabilities.clock
+    }
+
+    /// Start counting steps and time afresh, clearing a limit that was exceeded.
+    pub fn reset(&self) {
+        let mut state = self.0.borrow_mut();
+        state.steps = 0;
+        state.started = Some(Instant::now());
+        state.exceeded = None;
+    }
+
+    /// The error that stopped the run, if a limit was exceeded.
+    pub fn exceeded(&self) -> Option<String> {
+        self.0.borrow().exceeded.clone()
+    }
+
+    /// Count one evaluation step.
+    pub fn step(&self) -> Result<(), String> {
+        let mut state = self.0.borrow_mut();
+        if let Some(msg) = &state.exceeded { return Err(msg.clone()); }
+        state.steps += 1;
+        if let Some(max) = state.limits.max_steps {
+            if state.steps > max { return Err(stop(&mut state, format!("step limit exceeded ({} steps)", max))); }
+        }
+        if let Some(timeout) = state.limits.timeout {
+            let started: Instant = *state.started.get_or_insert_with(Instant::now);
+            if state.steps % TIME_CHECK_INTERVAL == 1 && started.elapsed() > timeout {
+                return Err(stop(&mut state, format!("time limit exceeded ({}s)", timeout.as_secs_f64())));
+            }
+        }
+        Ok(())
+    }
+
+    /// Enter a function call; every successful call must be matched by [`Sandbox::leave`].
+    pub fn enter(&self) -> Result<(), String> {
+        let marker: u8 = 0;
+        let here: usize = std::ptr::addr_of!(marker) as usize;
+        let mut state = self.0.borrow_mut();
+        if let Some(msg) = &state.exceeded { return Err(msg.clone()); }
+        if state.depth == 0 { state.stack_base = here; }
+        let too_deep: bool = state.limits.max_depth.is_some_and(|max| state.depth >= max);
+        let too_much_stack: bool = state.limits.max_stack.is_some_and(|max| state.stack_base.abs_diff(here) > max);
+        if too_deep || too_much_stack {
+            let depth: usize = state.depth;
+            return Err(stop(&mut state, format!("recursion limit exceeded (depth {})", depth)));
+        }
+        state.depth += 1;
+        Ok(())
+    }
+
+    pub fn leave(&self) {
+        let mut state = self.0.borrow_mut();
+        state.depth = state.depth.saturating_sub(1);
+    }
+
+    pub fn max_memo(&self) -> Option<usize> {
+        self.0.borrow().limits.max_memo
+    }
+
+    /// Check an array of `len` elements before (or after) building it.
+    pub fn check_len(&self, len: usize) -> Result<(), String> {
+        let mut state = self.0.borrow_mut();
+        match state.limits.max_array_len {
+            Some(max) if len > max => Err(stop(&mut state, format!("array of {} elements exceeds the limit of {}", len, max))),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn stop(state: &mut State, msg: String) -> String {
+    state.exceeded = Some(msg.clone());
+    msg
+}
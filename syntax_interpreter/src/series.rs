@@ -0,0 +1,201 @@
+// Taylor series and Padé approximants of functions and expressions
+
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+use crate::ast::AstNode;
+use crate::evaluator::{call_scope, eval, Env, Function, Value};
+use crate::poly::Polynomial;
+use crate::symbolic;
+use crate::bytecode::CodeCache;
+
+pub const NAMES: &[&str] = &["taylor", "pade"];
+
+/// Order used by `series {x -> a} expr` when none is given.
+pub const DEFAULT_ORDER: usize = 5;
+
+// symbolic derivatives that grow past this many nodes are abandoned for the numeric method
+const MAX_TREE_SIZE: usize = 20000;
+const MAX_ORDER: usize = 40;
+
+/// Dispatch a series built-in. Returns `None` when `name` is not one of [`NAMES`].
+pub fn call(name: &str, args: &[Value], env: &mut Env) -> Option<Value> {
+    if !NAMES.contains(&name) { return None; }
+    Some(dispatch(name, args, env).unwrap_or_else(|msg| Value::Str(format!("ERROR: {}", msg))))
+}
+
+fn dispatch(name: &str, args: &[Value], env: &mut Env) -> Result<Value, String> {
+    match name {
+        "taylor" => {
+            // taylor(f, x0, n): the series of f about x0 up to (x - x0)^n
+            let [f, Value::Number(x0), n] = args else {
+                return Err("taylor expects (f, x0, n)".to_string());
+            };
+            let n: usize = order_arg("taylor", n)?;
+            let (var, c) = function_coefficients("taylor", f, *x0, n, env)?;
+            Ok(Value::Polynomial(shifted(c, &var, *x0)))
+        }
+        "pade" => {
+            // pade(f, x0, m, n): rational approximant with numerator degree m and denominator degree n
+            let [f, Value::Number(x0), m, n] = args else {
+                return Err("pade expects (f, x0, m, n)".to_string());
+            };
+            let (m, n) = (order_arg("pade", m)?, order_arg("pade", n)?);
+            let (var, c) = function_coefficients("pade", f, *x0, m + n, env)?;
+            let (p, q) = pade(&c, m, n).ok_or("pade: no approximant of these degrees exists")?;
+            // the result is an ordinary function whose body divides the two captured polynomials
+            let part = |name: &str| AstNode::FunctionCall { name: name.to_string(), args: vec![AstNode::Variable(var.clone())] };
+            let captured: HashMap<String, Value> = HashMap::from([
+                ("pade_num".to_string(), Value::Polynomial(shifted(p, &var, *x0))),
+                ("pade_den".to_string(), Value::Polynomial(shifted(q, &var, *x0))),
+            ]);
+            Ok(Value::Function(Function { params: vec![var.clone()], body: symbolic::div(part("pade_num"), part("pade_den")), captured, module: None, code: CodeCache::default(), memo: None }))
+        }
+        _ => Err(format!("function '{}' does not exist or is not implemented", name)),
+    }
+}
+
+/// Evaluate `series {var -> at[, order]} expr`.
+pub fn expand(var: &str, at: &AstNode, order: Option<&AstNode>, expr: &AstNode, env: &mut Env) -> Value {
+    expand_expr(var, at, order, expr, env).unwrap_or_else(|msg| Value::Str(format!("ERROR: {}", msg)))
+}
+
+fn expand_expr(var: &str, at: &AstNode, order: Option<&AstNode>, expr: &AstNode, env: &mut Env) -> Result<Value, String> {
+    let x0: f64 = match eval(at, env) {
+        Value::Number(x) => x,
+        _ => return Err("series: the expansion point must be a number".to_string()),
+    };
+    let n: usize = match order {
+        Some(o) => order_arg("series", &eval(o, env))?,
+        None => DEFAULT_ORDER,
+    };
+    let c: Vec<f64> = coefficients("series", var, expr, env, x0, n)?;
+    Ok(Value::Polynomial(shifted(c, var, x0)))
+}
+
+fn order_arg(name: &str, v: &Value) -> Result<usize, String> {
+    match v {
+        Value::Number(n) if *n >= 0.0 && n.fract() == 0.0 && *n as usize <= MAX_ORDER => Ok(*n as usize),
+        _ => Err(format!("{}: order must be an integer between 0 and {}", name, MAX_ORDER)),
+    }
+}
+
+/// Taylor coefficients of a function value about x0, with the name of its variable.
+fn function_coefficients(name: &str, f: &Value, x0: f64, n: usize, env: &Env) -> Result<(String, Vec<f64>), String> {
+    match f {
+        Value::Function(func) => {
+            let [param] = func.params.as_slice() else {
+                return Err(format!("{}: the function must take exactly one argument", name));
+            };
+            // same scope a call would see: globals are not visible, captured variables are
+            let local: Env = call_scope(func, env);
+            Ok((param.clone(), coefficients(name, param, &func.body, &local, x0, n)?))
+        }
+        Value::Builtin(b) => {
+            let body: AstNode = AstNode::FunctionCall { name: b.clone(), args: vec![AstNode::Variable("x".to_string())] };
+            Ok(("x".to_string(), coefficients(name, "x", &body, env, x0, n)?))
+        }
+        Value::Polynomial(p) => {
+            // exact: re-centre the polynomial on x0 and truncate
+            let mut c: Vec<f64> = p.compose(&Polynomial::new(vec![x0, 1.0], &p.var)).coeffs;
+            c.resize(n + 1, 0.0);
+            Ok((p.var.clone(), c))
+        }
+        _ => Err(format!("{}: first argument must be a function", name)),
+    }
+}
+
+/// c[k] = f^(k)(x0) / k! for k = 0..=n, where f is `body` as a function of `var`.
+///
+/// Derivatives are taken symbolically when every part of the expression has a known rule;
+/// otherwise (or when a derivative is not finite at x0, e.g. sin(x)/x at 0) the coefficients
+/// are read off a Chebyshev interpolant around x0.
+fn coefficients(name: &str, var: &str, body: &AstNode, env: &Env, x0: f64, n: usize) -> Result<Vec<f64>, String> {
+    if let Some(c) = symbolic_coefficients(var, body, env, x0, n) {
+        return Ok(c);
+    }
+    numeric_coefficients(name, var, body, env, x0, n)
+}
+
+fn value_at(body: &AstNode, var: &str, x: f64, env: &Env) -> f64 {
+    let mut local: Env = env.clone();
+    local.vars.insert(var.to_string(), Value::Number(x));
+    match eval(body, &mut local) {
+        Value::Number(v) => v,
+        _ => f64::NAN,
+    }
+}
+
+fn symbolic_coefficients(var: &str, body: &AstNode, env: &Env, x0: f64, n: usize) -> Option<Vec<f64>> {
+    let mut d: AstNode = body.clone();
+    let mut factorial: f64 = 1.0;
+    let mut out: Vec<f64> = Vec::with_capacity(n + 1);
+    for k in 0..=n {
+        if k > 0 {
+            d = symbolic::differentiate(&d, var, env)?;
+            if symbolic::size(&d) > MAX_TREE_SIZE { return None; }
+            factorial *= k as f64;
+        }
+        let v: f64 = value_at(&d, var, x0, env);
+        if !v.is_finite() { return None; }
+        out.push(v / factorial);
+    }
+    Some(out)
+}
+
+fn numeric_coefficients(name: &str, var: &str, body: &AstNode, env: &Env, x0: f64, n: usize) -> Result<Vec<f64>, String> {
+    // sample on [x0 - h, x0 + h] at an even number of Chebyshev nodes, so x0 itself is never evaluated
+    let h: f64 = if x0 == 0.0 { 0.5 } else { 0.5 * x0.abs().min(1.0) };
+    let m: usize = (n + 12).next_multiple_of(2);
+    let node = |j: usize| (PI * (j as f64 + 0.5) / m as f64).cos();
+    let ys: Vec<f64> = (0..m).map(|j| value_at(body, var, x0 + h * node(j), env)).collect();
+    if ys.iter().any(|y| !y.is_finite()) {
+        return Err(format!("{}: the expression is not a finite number near {} = {}", name, var, x0));
+    }
+    // Chebyshev coefficients by the discrete cosine transform
+    let mut a: Vec<f64> = (0..m).map(|k| {
+        2.0 / m as f64 * ys.iter().enumerate().map(|(j, y)| y * (PI * k as f64 * (j as f64 + 0.5) / m as f64).cos()).sum::<f64>()
+    }).collect();
+    a[0] /= 2.0;
+    // an interpolant that has not converged means a pole or kink nearby, not a series worth reporting
+    let scale: f64 = ys.iter().fold(0.0f64, |s, y| s.max(y.abs())).max(f64::MIN_POSITIVE);
+    if a[m - 2..].iter().any(|c| c.abs() > 1e-8 * scale) {
+        return Err(format!("{}: the expression has no Taylor series near {} = {}", name, var, x0));
+    }
+    // expand sum a_k T_k(t) into powers of t = (x - x0) / h using T_{k+1} = 2t T_k - T_{k-1}
+    let mut b: Vec<f64> = vec![0.0; m];
+    let (mut t_prev, mut t_cur): (Vec<f64>, Vec<f64>) = (vec![1.0], vec![0.0, 1.0]);
+    b[0] += a[0];
+    b[1] += a[1];
+    for ak in a.iter().skip(2) {
+        let mut t_next: Vec<f64> = vec![0.0; t_cur.len() + 1];
+        for (i, c) in t_cur.iter().enumerate() { t_next[i + 1] += 2.0 * c; }
+        for (i, c) in t_prev.iter().enumerate() { t_next[i] -= c; }
+        for (i, c) in t_next.iter().enumerate() { b[i] += ak * c; }
+        t_prev = std::mem::replace(&mut t_cur, t_next);
+    }
+    // terms at the level of rounding noise are dropped rather than reported as tiny coefficients
+    Ok((0..=n).map(|k| {
+        let bk: f64 = b.get(k).copied().unwrap_or(0.0);
+        if bk.abs() < 1e-11 * scale { 0.0 } else { bk / h.powi(k as i32) }
+    }).collect())
+}
+
+/// sum c[k] (x - x0)^k as a polynomial in x.
+fn shifted(c: Vec<f64>, var: &str, x0: f64) -> Polynomial {
+    let p: Polynomial = Polynomial::new(c, var);
+    if x0 == 0.0 { p } else { p.compose(&Polynomial::new(vec![-x0, 1.0], var)) }
+}
+
+/// Numerator and denominator coefficients (in powers of x - x0) of the [m/n] Padé approximant
+/// from the Taylor coefficients c[0..=m+n], normalised so the denominator starts with 1.
+fn pade(c: &[f64], m: usize, n: usize) -> Option<(Vec<f64>, Vec<f64>)> {
+    let coef = |i: isize| if i < 0 { 0.0 } else { c[i as usize] };
+    // sum_{j=1..n} q_j c[m+k-j] = -c[m+k] for k = 1..n
+    let a: Vec<Vec<f64>> = (1..=n).map(|k| (1..=n).map(|j| coef((m + k) as isize - j as isize)).collect()).collect();
+    let rhs: Vec<f64> = (1..=n).map(|k| -c[m + k]).collect();
+    let mut q: Vec<f64> = vec![1.0];
+    q.extend(crate::linalg::solve(a, rhs)?);
+    let p: Vec<f64> = (0..=m).map(|i| (0..=i.min(n)).map(|j| q[j] * c[i - j]).sum()).collect();
+    Some((p, q))
+}
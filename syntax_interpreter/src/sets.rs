@@ -0,0 +1,83 @@
+// Finite sets: literals, set-builder notation, set algebra and membership
+
+use crate::ast::BinaryOpKind;
+use crate::evaluator::{display_value, values_equal, Env, Value};
+use crate::sandbox::Sandbox;
+
+pub const NAMES: &[&str] = &["set", "powerset"];
+
+// power sets grow as 2^n; refuse anything larger than this many elements
+const MAX_POWERSET: usize = 20;
+
+/// Dispatch a set built-in. Returns `None` when `name` is not one of [`NAMES`].
+pub fn call(name: &str, args: &[Value], env: &mut Env) -> Option<Value> {
+    if !NAMES.contains(&name) { return None; }
+    Some(dispatch(name, args, &env.shared.sandbox).unwrap_or_else(|msg| Value::Str(format!("ERROR: {}", msg))))
+}
+
+fn dispatch(name: &str, args: &[Value], sandbox: &Sandbox) -> Result<Value, String> {
+    match name {
+        "set" => match args {
+            [] => Ok(Value::Set(Vec::new())),
+            [v] => Ok(from_values(elements(name, v)?)),
+            _ => Err("set expects 1 argument (array)".to_string()),
+        },
+        "powerset" => {
+            let [v] = args else { return Err("powerset expects 1 argument (set)".to_string()) };
+            let items: Vec<Value> = match from_values(elements(name, v)?) { Value::Set(items) => items, _ => Vec::new() };
+            if items.len() > MAX_POWERSET {
+                return Err(format!("powerset: a set of {} elements has too many subsets", items.len()));
+            }
+            sandbox.check_len(1 << items.len())?;
+            // subsets in order of size, each listed in the order of the original set
+            let mut subsets: Vec<Vec<Value>> = (0..1usize << items.len())
+                .map(|mask| items.iter().enumerate().filter(|(i, _)| mask & (1 << i) != 0).map(|(_, v)| v.clone()).collect())
+                .collect();
+            subsets.sort_by_key(|s| s.len());
+            Ok(Value::Set(subsets.into_iter().map(Value::Set).collect()))
+        }
+        _ => Err(format!("function '{}' does not exist or is not implemented", name)),
+    }
+}
+
+/// A set of the given values: duplicates dropped, and numbers in ascending order.
+pub fn from_values(values: Vec<Value>) -> Value {
+    let mut out: Vec<Value> = Vec::with_capacity(values.len());
+    for v in values {
+        if !out.iter().any(|u| values_equal(u, &v)) { out.push(v); }
+    }
+    if out.iter().all(|v| matches!(v, Value::Number(_))) {
+        out.sort_by(|a, b| match (a, b) { (Value::Number(x), Value::Number(y)) => x.total_cmp(y), _ => std::cmp::Ordering::Equal });
+    }
+    Value::Set(out)
+}
+
+/// Whether `item` is an element of a set or an array; `None` when `collection` is neither.
+pub fn contains(collection: &Value, item: &Value) -> Option<bool> {
+    match collection {
+        Value::Set(items) | Value::Array(items) => Some(items.iter().any(|v| values_equal(v, item))),
+        _ => None,
+    }
+}
+
+/// Union, intersection and difference of two sets (arrays are read as sets).
+pub fn binary(op: &BinaryOpKind, l: Value, r: Value) -> Value {
+    let (Ok(a), Ok(b)) = (elements("", &l), elements("", &r)) else {
+        return Value::Str(format!("ERROR: cannot combine {} and {}", display_value(&l), display_value(&r)));
+    };
+    match op {
+        BinaryOpKind::Union => from_values(a.into_iter().chain(b).collect()),
+        BinaryOpKind::Intersect => from_values(a.into_iter().filter(|v| b.iter().any(|u| values_equal(u, v))).collect()),
+        BinaryOpKind::Difference | BinaryOpKind::Sub => from_values(a.into_iter().filter(|v| !b.iter().any(|u| values_equal(u, v))).collect()),
+        BinaryOpKind::Eq => Value::Bool(values_equal(&l, &r)),
+        BinaryOpKind::NotEq => Value::Bool(!values_equal(&l, &r)),
+        _ => Value::Str(format!("ERROR: this operator does not apply to sets ({} and {})", display_value(&l), display_value(&r))),
+    }
+}
+
+fn elements(name: &str, v: &Value) -> Result<Vec<Value>, String> {
+    match v {
+        Value::Set(items) | Value::Array(items) => Ok(items.clone()),
+        other => Err(format!("{}: expected a set or an array, got {}", name, display_value(other))),
+    }
+}
@@ -0,0 +1,237 @@
+// Signal-processing built-ins: FFTs, convolution, windows and linear filters
+
+use std::f64::consts::PI;
+
+use num_complex::Complex64;
+
+use crate::evaluator::{display_value, has_complex, Env, Value};
+
+pub const NAMES: &[&str] = &[
+    "fft", "ifft", "rfft", "convolve", "correlate", "hann", "hamming", "blackman", "freqs",
+];
+
+// below this many multiplications a direct convolution beats going through the FFT
+const DIRECT_CONVOLUTION: usize = 4096;
+
+/// Dispatch a signal-processing built-in. Returns `None` when `name` is not one of [`NAMES`].
+/// `filter(b, a, x)` shares its name with the array `filter` and is reached through [`filter`].
+pub fn call(name: &str, args: &[Value], env: &mut Env) -> Option<Value> {
+    if !NAMES.contains(&name) { return None; }
+    Some(dispatch(name, args, env).unwrap_or_else(|msg| Value::Str(format!("ERROR: {}", msg))))
+}
+
+fn dispatch(name: &str, args: &[Value], env: &Env) -> Result<Value, String> {
+    match name {
+        "fft" | "ifft" => {
+            let [x] = args else { return Err(format!("{} expects 1 argument (array)", name)) };
+            let x: Vec<Complex64> = samples(name, x)?;
+            Ok(spectrum(transform(x, name == "ifft")))
+        }
+        "rfft" => {
+            let [x] = args else { return Err("rfft expects 1 argument (array)".to_string()) };
+            let x: Vec<f64> = reals(name, x)?;
+            let half: usize = x.len() / 2 + 1;
+            let mut out: Vec<Complex64> = transform(x.into_iter().map(|v| Complex64::new(v, 0.0)).collect(), false);
+            out.truncate(half);
+            Ok(spectrum(out))
+        }
+        "convolve" | "correlate" => {
+            let [a, b] = args else { return Err(format!("{} expects 2 arguments (a, b)", name)) };
+            let real: bool = !has_complex(a) && !has_complex(b);
+            let a: Vec<Complex64> = samples(name, a)?;
+            let mut b: Vec<Complex64> = samples(name, b)?;
+            if a.is_empty() || b.is_empty() { return Err(format!("{}: arrays must not be empty", name)); }
+            if name == "correlate" {
+                // correlation at every lag is convolution with the reversed conjugate
+                b = b.iter().rev().map(|v| v.conj()).collect();
+            }
+            let out: Vec<Complex64> = convolve(&a, &b);
+            Ok(if real { Value::Array(out.iter().map(|v| Value::Number(v.re)).collect()) } else { spectrum(out) })
+        }
+        "hann" | "hamming" | "blackman" => {
+            let n: usize = count(name, args.first(), args.len() == 1, env)?;
+            let w = |k: usize| -> f64 {
+                if n == 1 { return 1.0; }
+                let t: f64 = 2.0 * PI * k as f64 / (n - 1) as f64;
+                match name {
+                    "hann" => 0.5 - 0.5 * t.cos(),
+                    "hamming" => 0.54 - 0.46 * t.cos(),
+                    _ => 0.42 - 0.5 * t.cos() + 0.08 * (2.0 * t).cos(),
+                }
+            };
+            // the formulas leave rounding dust where the window should be exactly 0
+            Ok(Value::Array((0..n).map(|k| Value::Number(if w(k).abs() < 1e-15 { 0.0 } else { w(k) })).collect()))
+        }
+        "freqs" => {
+            let n: usize = count(name, args.first(), (1..=2).contains(&args.len()), env)?;
+            let dt: f64 = match args.get(1) {
+                None => 1.0,
+                Some(Value::Number(dt)) if *dt > 0.0 => *dt,
+                Some(other) => return Err(format!("freqs: sample spacing must be a positive number, got {}", display_value(other))),
+            };
+            // bin k of an n-point FFT: 0, 1, ..., then the negative frequencies
+            let scale: f64 = 1.0 / (n as f64 * dt);
+            Ok(Value::Array((0..n).map(|k| {
+                let k: f64 = if k < n.div_ceil(2) { k as f64 } else { k as f64 - n as f64 };
+                Value::Number(k * scale)
+            }).collect()))
+        }
+        _ => Err(format!("function '{}' does not exist or is not implemented", name)),
+    }
+}
+
+/// filter(b, a, x): run x through the filter with numerator b and denominator a
+/// (a = [1] for an FIR filter), as a direct form II transposed difference equation.
+pub fn filter(args: &[Value]) -> Result<Value, String> {
+    let [b, a, x] = args else { return Err("filter expects 3 arguments (b, a, x)".to_string()) };
+    let (b, a, x) = (reals("filter", b)?, reals("filter", a)?, reals("filter", x)?);
+    let Some(&a0) = a.first().filter(|a0| **a0 != 0.0) else {
+        return Err("filter: the first denominator coefficient must be nonzero".to_string());
+    };
+    if b.is_empty() { return Err("filter: numerator must not be empty".to_string()); }
+    let order: usize = a.len().max(b.len());
+    let coeff = |c: &[f64], i: usize| c.get(i).copied().unwrap_or(0.0) / a0;
+    let mut state: Vec<f64> = vec![0.0; order];
+    let mut out: Vec<Value> = Vec::with_capacity(x.len());
+    for v in x {
+        let y: f64 = coeff(&b, 0) * v + state[0];
+        for i in 1..order {
+            state[i - 1] = coeff(&b, i) * v - coeff(&a, i) * y + state[i];
+        }
+        out.push(Value::Number(y));
+    }
+    Ok(Value::Array(out))
+}
+
+/// Discrete Fourier transform of any length; the inverse includes the 1/n factor.
+fn transform(mut x: Vec<Complex64>, inverse: bool) -> Vec<Complex64> {
+    let n: usize = x.len();
+    if n <= 1 { return x; }
+    if n.is_power_of_two() {
+        radix2(&mut x, inverse);
+    } else {
+        x = bluestein(&x, inverse);
+    }
+    if inverse {
+        for v in &mut x { *v /= n as f64; }
+    }
+    x
+}
+
+/// In-place iterative Cooley-Tukey FFT for power-of-two lengths (unscaled).
+fn radix2(x: &mut [Complex64], inverse: bool) {
+    let n: usize = x.len();
+    let bits: u32 = n.trailing_zeros();
+    for i in 0..n {
+        let j: usize = i.reverse_bits() >> (usize::BITS - bits);
+        if i < j { x.swap(i, j); }
+    }
+    let sign: f64 = if inverse { 1.0 } else { -1.0 };
+    let mut len: usize = 2;
+    while len <= n {
+        // each twiddle is computed directly: repeated multiplication accumulates rounding error
+        let twiddles: Vec<Complex64> = (0..len / 2).map(|k| twiddle(sign, k, len)).collect();
+        for chunk in x.chunks_mut(len) {
+            let (lower, upper) = chunk.split_at_mut(len / 2);
+            for ((u, v), w) in lower.iter_mut().zip(upper.iter_mut()).zip(&twiddles) {
+                let t: Complex64 = *v * w;
+                *v = *u - t;
+                *u += t;
+            }
+        }
+        len *= 2;
+    }
+}
+
+/// exp(sign 2 pi i k / n), exact at the quarter turns
+fn twiddle(sign: f64, k: usize, n: usize) -> Complex64 {
+    if !(4 * k).is_multiple_of(n) { return Complex64::from_polar(1.0, sign * 2.0 * PI * k as f64 / n as f64); }
+    match 4 * k / n {
+        0 => Complex64::new(1.0, 0.0),
+        1 => Complex64::new(0.0, sign),
+        2 => Complex64::new(-1.0, 0.0),
+        _ => Complex64::new(0.0, -sign),
+    }
+}
+
+/// Bluestein's algorithm: a DFT of any length as a convolution of power-of-two length (unscaled).
+fn bluestein(x: &[Complex64], inverse: bool) -> Vec<Complex64> {
+    let n: usize = x.len();
+    let m: usize = (2 * n - 1).next_power_of_two();
+    let sign: f64 = if inverse { 1.0 } else { -1.0 };
+    // chirp w_k = exp(sign i pi k^2 / n), with k^2 reduced mod 2n to keep the angle accurate
+    let chirp: Vec<Complex64> = (0..n).map(|k| {
+        let k2: usize = (k * k) % (2 * n);
+        Complex64::from_polar(1.0, sign * PI * k2 as f64 / n as f64)
+    }).collect();
+    let mut a: Vec<Complex64> = vec![Complex64::new(0.0, 0.0); m];
+    let mut b: Vec<Complex64> = vec![Complex64::new(0.0, 0.0); m];
+    for k in 0..n {
+        a[k] = x[k] * chirp[k];
+        b[k] = chirp[k].conj();
+        if k > 0 { b[m - k] = chirp[k].conj(); }
+    }
+    radix2(&mut a, false);
+    radix2(&mut b, false);
+    let mut c: Vec<Complex64> = a.iter().zip(&b).map(|(p, q)| p * q).collect();
+    radix2(&mut c, true);
+    (0..n).map(|k| c[k] * chirp[k] / m as f64).collect()
+}
+
+/// Full linear convolution, directly for short inputs and through the FFT otherwise.
+fn convolve(a: &[Complex64], b: &[Complex64]) -> Vec<Complex64> {
+    let len: usize = a.len() + b.len() - 1;
+    if a.len() * b.len() <= DIRECT_CONVOLUTION {
+        let mut out: Vec<Complex64> = vec![Complex64::new(0.0, 0.0); len];
+        for (i, p) in a.iter().enumerate() {
+            for (j, q) in b.iter().enumerate() { out[i + j] += p * q; }
+        }
+        return out;
+    }
+    let m: usize = len.next_power_of_two();
+    let pad = |v: &[Complex64]| -> Vec<Complex64> {
+        let mut p: Vec<Complex64> = v.to_vec();
+        p.resize(m, Complex64::new(0.0, 0.0));
+        p
+    };
+    let (mut fa, mut fb) = (pad(a), pad(b));
+    radix2(&mut fa, false);
+    radix2(&mut fb, false);
+    let mut c: Vec<Complex64> = fa.iter().zip(&fb).map(|(p, q)| p * q).collect();
+    radix2(&mut c, true);
+    c.truncate(len);
+    c.iter().map(|v| v / m as f64).collect()
+}
+
+fn spectrum(x: Vec<Complex64>) -> Value {
+    Value::Array(x.into_iter().map(Value::Complex).collect())
+}
+
+fn samples(name: &str, v: &Value) -> Result<Vec<Complex64>, String> {
+    let Value::Array(items) = v else { return Err(format!("{}: expected an array, got {}", name, display_value(v))) };
+    items.iter().map(|item| match item {
+        Value::Number(x) => Ok(Complex64::new(*x, 0.0)),
+        Value::Complex(c) => Ok(*c),
+        other => Err(format!("{}: array must contain only numbers, got {}", name, display_value(other))),
+    }).collect()
+}
+
+fn reals(name: &str, v: &Value) -> Result<Vec<f64>, String> {
+    let Value::Array(items) = v else { return Err(format!("{}: expected an array, got {}", name, display_value(v))) };
+    items.iter().map(|item| match item {
+        Value::Number(x) => Ok(*x),
+        other => Err(format!("{}: array must contain only real numbers, got {}", name, display_value(other))),
+    }).collect()
+}
+
+// the length of a window or spectrum to build, within the array limit
+fn count(name: &str, v: Option<&Value>, arity_ok: bool, env: &Env) -> Result<usize, String> {
+    match v {
+        Some(Value::Number(n)) if arity_ok && *n >= 1.0 && n.fract() == 0.0 => {
+            env.shared.sandbox.check_len(*n as usize)?;
+            Ok(*n as usize)
+        }
+        _ if !arity_ok => Err(format!("{} expects a length n", name)),
+        _ => Err(format!("{}: length must be a positive whole number", name)),
+    }
+}
@@ -0,0 +1,399 @@
+// Statistics built-ins: descriptive statistics, probability distributions and hypothesis tests (backed by statrs)
+
+use statrs::distribution::{
+    Beta, Binomial, ChiSquared, Continuous, ContinuousCDF, Discrete, DiscreteCDF, FisherSnedecor, Gamma, Normal,
+    Poisson, StudentsT,
+};
+
+use crate::evaluator::{Env, Value};
+
+pub const NAMES: &[&str] = &[
+    "mean", "median", "mode", "var", "std", "quantile", "cov", "corr",
+    "Normal", "Binomial", "Poisson", "StudentT", "ChiSquared", "FisherF", "Gamma", "Beta",
+    "pdf", "pmf", "cdf", "sample",
+    "t_test", "chi2_test",
+];
+
+/// Dispatch a statistics built-in. Returns `None` when `name` is not one of [`NAMES`].
+pub fn call(name: &str, args: &[Value], env: &mut Env) -> Option<Value> {
+    if !NAMES.contains(&name) { return None; }
+    Some(dispatch(name, args, env).unwrap_or_else(|msg| Value::Str(format!("ERROR: {}", msg))))
+}
+
+fn dispatch(name: &str, args: &[Value], env: &mut Env) -> Result<Value, String> {
+    match name {
+        "mean" => {
+            let xs: Vec<f64> = sample_arg(name, args, 0)?;
+            Ok(Value::Number(mean(&xs)))
+        }
+        "median" => {
+            let mut xs: Vec<f64> = sample_arg(name, args, 0)?;
+            sort_numbers(&mut xs);
+            Ok(Value::Number(quantile_sorted(&xs, 0.5)))
+        }
+        "mode" => {
+            // most frequent value; ties go to the smallest
+            let mut xs: Vec<f64> = sample_arg(name, args, 0)?;
+            sort_numbers(&mut xs);
+            let (mut best, mut best_count) = (xs[0], 0usize);
+            let mut i: usize = 0;
+            while i < xs.len() {
+                let j: usize = i + xs[i..].iter().take_while(|&&x| x == xs[i]).count();
+                if j - i > best_count { best = xs[i]; best_count = j - i; }
+                i = j;
+            }
+            Ok(Value::Number(best))
+        }
+        "var" | "std" => {
+            // sample variance by default; var(a, 0) for the population variance
+            let xs: Vec<f64> = sample_arg(name, args, 0)?;
+            let ddof: f64 = match args.get(1) { Some(Value::Number(d)) => *d, None => 1.0, _ => return Err(format!("{}: ddof must be a number", name)) };
+            let v: f64 = variance(&xs, ddof).ok_or_else(|| format!("{}: need more than {} values", name, ddof))?;
+            Ok(Value::Number(if name == "std" { v.sqrt() } else { v }))
+        }
+        "quantile" => {
+            if let Some(Value::Record(_)) = args.first() {
+                let d: Dist = Dist::from_value(&args[0])?;
+                return map_probabilities(args.get(1), |p| d.quantile(p));
+            }
+            let mut xs: Vec<f64> = sample_arg(name, args, 0)?;
+            sort_numbers(&mut xs);
+            map_probabilities(args.get(1), |p| Ok(quantile_sorted(&xs, p)))
+        }
+        "cov" | "corr" => {
+            let xs: Vec<f64> = sample_arg(name, args, 0)?;
+            let ys: Vec<f64> = sample_arg(name, args, 1)?;
+            if xs.len() != ys.len() || xs.len() < 2 {
+                return Err(format!("{}: samples must have the same length (at least 2)", name));
+            }
+            let c: f64 = covariance(&xs, &ys);
+            if name == "cov" { return Ok(Value::Number(c)); }
+            let sx: f64 = variance(&xs, 1.0).unwrap_or(f64::NAN).sqrt();
+            let sy: f64 = variance(&ys, 1.0).unwrap_or(f64::NAN).sqrt();
+            Ok(Value::Number(c / (sx * sy)))
+        }
+        "Normal" | "Binomial" | "Poisson" | "StudentT" | "ChiSquared" | "FisherF" | "Gamma" | "Beta" => {
+            let params: Vec<f64> = args.iter().enumerate().map(|(i, v)| match v {
+                Value::Number(x) => Ok(*x),
+                _ => Err(format!("{}: parameter {} must be a number", name, i + 1)),
+            }).collect::<Result<_, _>>()?;
+            let d: Dist = Dist::new(name, &params)?;
+            Ok(d.to_value(name, &params))
+        }
+        "pdf" | "pmf" | "cdf" => {
+            let d: Dist = match args.first() {
+                Some(v @ Value::Record(_)) => Dist::from_value(v)?,
+                _ => return Err(format!("{} expects a distribution as its first argument", name)),
+            };
+            let f = |x: f64| if name == "cdf" { d.cdf(x) } else { d.pdf(x) };
+            match args.get(1) {
+                Some(Value::Number(x)) => Ok(Value::Number(f(*x))),
+                Some(Value::Array(xs)) => xs.iter().map(|v| match v {
+                    Value::Number(x) => Ok(Value::Number(f(*x))),
+                    _ => Err(format!("{}: x must be a number", name)),
+                }).collect::<Result<Vec<_>, _>>().map(Value::Array),
+                _ => Err(format!("{} expects 2 arguments (distribution, x)", name)),
+            }
+        }
+        "sample" => {
+            let d: Dist = match args.first() {
+                Some(v @ Value::Record(_)) => Dist::from_value(v)?,
+                _ => return Err("sample expects a distribution as its first argument".to_string()),
+            };
+            // inverse transform sampling keeps seeded draws reproducible; drawing puts sample in random::UNMEMOISABLE
+            let draw = || -> Result<f64, String> { d.quantile(env.shared.rng.open_uniform()) };
+            match args.get(1) {
+                None => draw().map(Value::Number),
+                Some(Value::Number(n)) if *n >= 0.0 && n.fract() == 0.0 => {
+                    env.shared.sandbox.check_len(*n as usize)?;
+                    (0..*n as usize).map(|_| draw().map(Value::Number)).collect::<Result<Vec<_>, _>>().map(Value::Array)
+                }
+                _ => Err("sample: count must be a non-negative integer".to_string()),
+            }
+        }
+        "t_test" => t_test(args),
+        "chi2_test" => chi2_test(args),
+        _ => Err(format!("function '{}' does not exist or is not implemented", name)),
+    }
+}
+
+fn sample_arg(name: &str, args: &[Value], i: usize) -> Result<Vec<f64>, String> {
+    let mut out: Vec<f64> = Vec::new();
+    match args.get(i) {
+        Some(Value::Array(items)) => collect_numbers(name, items, &mut out)?,
+        _ => return Err(format!("{}: argument {} must be an array of numbers", name, i + 1)),
+    }
+    if out.is_empty() {
+        return Err(format!("{}: sample is empty", name));
+    }
+    Ok(out)
+}
+
+fn collect_numbers(name: &str, items: &[Value], out: &mut Vec<f64>) -> Result<(), String> {
+    for v in items {
+        match v {
+            Value::Number(x) => out.push(*x),
+            Value::Array(inner) => collect_numbers(name, inner, out)?,
+            _ => return Err(format!("{}: sample must contain only numbers", name)),
+        }
+    }
+    Ok(())
+}
+
+fn map_probabilities(p: Option<&Value>, f: impl Fn(f64) -> Result<f64, String>) -> Result<Value, String> {
+    let check = |p: f64| if (0.0..=1.0).contains(&p) { f(p) } else { Err("quantile: p must be between 0 and 1".to_string()) };
+    match p {
+        Some(Value::Number(p)) => check(*p).map(Value::Number),
+        Some(Value::Array(ps)) => ps.iter().map(|v| match v {
+            Value::Number(p) => check(*p).map(Value::Number),
+            _ => Err("quantile: p must be a number".to_string()),
+        }).collect::<Result<Vec<_>, _>>().map(Value::Array),
+        _ => Err("quantile expects 2 arguments (sample or distribution, p)".to_string()),
+    }
+}
+
+fn sort_numbers(xs: &mut [f64]) {
+    xs.sort_by(|a, b| a.total_cmp(b));
+}
+
+fn mean(xs: &[f64]) -> f64 {
+    xs.iter().sum::<f64>() / xs.len() as f64
+}
+
+fn variance(xs: &[f64], ddof: f64) -> Option<f64> {
+    let n: f64 = xs.len() as f64;
+    if n <= ddof { return None; }
+    let m: f64 = mean(xs);
+    Some(xs.iter().map(|x| (x - m) * (x - m)).sum::<f64>() / (n - ddof))
+}
+
+fn covariance(xs: &[f64], ys: &[f64]) -> f64 {
+    let (mx, my) = (mean(xs), mean(ys));
+    xs.iter().zip(ys.iter()).map(|(x, y)| (x - mx) * (y - my)).sum::<f64>() / (xs.len() as f64 - 1.0)
+}
+
+fn quantile_sorted(xs: &[f64], p: f64) -> f64 {
+    // linear interpolation between closest ranks
+    let h: f64 = (xs.len() as f64 - 1.0) * p;
+    let lo: usize = h.floor() as usize;
+    let hi: usize = h.ceil() as usize;
+    xs[lo] + (h - lo as f64) * (xs[hi] - xs[lo])
+}
+
+fn t_test(args: &[Value]) -> Result<Value, String> {
+    // t_test(a, mu): one-sample test; t_test(a, b): Welch's two-sample test. p-values are two-sided.
+    let xs: Vec<f64> = sample_arg("t_test", args, 0)?;
+    let (t, df): (f64, f64) = match args.get(1) {
+        Some(Value::Number(mu)) => {
+            let n: f64 = xs.len() as f64;
+            let s2: f64 = variance(&xs, 1.0).ok_or("t_test: need at least 2 values")?;
+            ((mean(&xs) - mu) / (s2 / n).sqrt(), n - 1.0)
+        }
+        Some(Value::Array(_)) => {
+            let ys: Vec<f64> = sample_arg("t_test", args, 1)?;
+            let (va, vb) = match (variance(&xs, 1.0), variance(&ys, 1.0)) {
+                (Some(va), Some(vb)) => (va / xs.len() as f64, vb / ys.len() as f64),
+                _ => return Err("t_test: each sample needs at least 2 values".to_string()),
+            };
+            let df: f64 = (va + vb).powi(2) / (va * va / (xs.len() as f64 - 1.0) + vb * vb / (ys.len() as f64 - 1.0));
+            ((mean(&xs) - mean(&ys)) / (va + vb).sqrt(), df)
+        }
+        _ => return Err("t_test expects 2 arguments (sample, mu) or (sample, sample)".to_string()),
+    };
+    let dist: StudentsT = StudentsT::new(0.0, 1.0, df).map_err(|_| "t_test: degenerate sample".to_string())?;
+    let p: f64 = 2.0 * dist.sf(t.abs());
+    Ok(record(&[("t", t), ("df", df), ("p_value", p)]))
+}
+
+fn chi2_test(args: &[Value]) -> Result<Value, String> {
+    // chi2_test(observed [, expected]): goodness of fit (uniform when expected is omitted);
+    // chi2_test(table) with a 2D table: test of independence
+    let (stat, df): (f64, f64) = match args.first() {
+        Some(Value::Array(rows)) if !rows.is_empty() && rows.iter().all(|r| matches!(r, Value::Array(_))) => {
+            let table: Vec<Vec<f64>> = rows.iter().map(|r| match r {
+                Value::Array(cells) => {
+                    let mut row: Vec<f64> = Vec::new();
+                    collect_numbers("chi2_test", cells, &mut row).map(|_| row)
+                }
+                _ => unreachable!(),
+            }).collect::<Result<_, _>>()?;
+            let width: usize = table[0].len();
+            if width < 2 || table.len() < 2 || table.iter().any(|r| r.len() != width) {
+                return Err("chi2_test: contingency table must be rectangular and at least 2x2".to_string());
+            }
+            let row_sums: Vec<f64> = table.iter().map(|r| r.iter().sum()).collect();
+            let col_sums: Vec<f64> = (0..width).map(|j| table.iter().map(|r| r[j]).sum()).collect();
+            let total: f64 = row_sums.iter().sum();
+            let mut stat: f64 = 0.0;
+            for (r, rs) in table.iter().zip(row_sums.iter()) {
+                for (o, cs) in r.iter().zip(col_sums.iter()) {
+                    let e: f64 = rs * cs / total;
+                    stat += (o - e) * (o - e) / e;
+                }
+            }
+            (stat, ((table.len() - 1) * (width - 1)) as f64)
+        }
+        Some(Value::Array(_)) => {
+            let observed: Vec<f64> = sample_arg("chi2_test", args, 0)?;
+            let total: f64 = observed.iter().sum();
+            let expected: Vec<f64> = match args.get(1) {
+                None => vec![total / observed.len() as f64; observed.len()],
+                Some(_) => sample_arg("chi2_test", args, 1)?,
+            };
+            if expected.len() != observed.len() || observed.len() < 2 {
+                return Err("chi2_test: observed and expected must have the same length (at least 2)".to_string());
+            }
+            let stat: f64 = observed.iter().zip(expected.iter()).map(|(o, e)| (o - e) * (o - e) / e).sum();
+            (stat, observed.len() as f64 - 1.0)
+        }
+        _ => return Err("chi2_test expects an array of observed counts".to_string()),
+    };
+    let dist: ChiSquared = ChiSquared::new(df).map_err(|_| "chi2_test: invalid degrees of freedom".to_string())?;
+    Ok(record(&[("chi2", stat), ("df", df), ("p_value", dist.sf(stat))]))
+}
+
+fn record(fields: &[(&str, f64)]) -> Value {
+    Value::Record(fields.iter().map(|(k, v)| (k.to_string(), Value::Number(*v))).collect())
+}
+
+/// A distribution rebuilt from its record form, e.g. `{dist: "Normal", mean: 0, std: 1}`.
+enum Dist {
+    Normal(Normal),
+    Binomial(Binomial),
+    Poisson(Poisson),
+    StudentT(StudentsT),
+    ChiSquared(ChiSquared),
+    FisherF(FisherSnedecor),
+    Gamma(Gamma),
+    Beta(Beta),
+}
+
+const PARAM_NAMES: &[(&str, &[&str])] = &[
+    ("Normal", &["mean", "std"]),
+    ("Binomial", &["n", "p"]),
+    ("Poisson", &["lambda"]),
+    ("StudentT", &["df"]),
+    ("ChiSquared", &["df"]),
+    ("FisherF", &["df1", "df2"]),
+    ("Gamma", &["shape", "rate"]),
+    ("Beta", &["alpha", "beta"]),
+];
+
+impl Dist {
+    fn new(name: &str, p: &[f64]) -> Result<Dist, String> {
+        let names: &[&str] = PARAM_NAMES.iter().find(|(n, _)| *n == name).map(|(_, ps)| *ps).unwrap_or(&[]);
+        // Normal() is the standard normal distribution
+        let p: &[f64] = if name == "Normal" && p.is_empty() { &[0.0, 1.0] } else { p };
+        if p.len() != names.len() {
+            return Err(format!("{} expects {} parameter(s) ({})", name, names.len(), names.join(", ")));
+        }
+        let bad = |_| format!("{}: invalid parameters", name);
+        Ok(match name {
+            "Normal" => Dist::Normal(Normal::new(p[0], p[1]).map_err(bad)?),
+            "Binomial" => {
+                if p[0] < 0.0 || p[0].fract() != 0.0 { return Err("Binomial: n must be a non-negative integer".to_string()); }
+                Dist::Binomial(Binomial::new(p[1], p[0] as u64).map_err(bad)?)
+            }
+            "Poisson" => Dist::Poisson(Poisson::new(p[0]).map_err(bad)?),
+            "StudentT" => Dist::StudentT(StudentsT::new(0.0, 1.0, p[0]).map_err(bad)?),
+            "ChiSquared" => Dist::ChiSquared(ChiSquared::new(p[0]).map_err(bad)?),
+            "FisherF" => Dist::FisherF(FisherSnedecor::new(p[0], p[1]).map_err(bad)?),
+            "Gamma" => Dist::Gamma(Gamma::new(p[0], p[1]).map_err(bad)?),
+            "Beta" => Dist::Beta(Beta::new(p[0], p[1]).map_err(bad)?),
+            _ => return Err(format!("unknown distribution '{}'", name)),
+        })
+    }
+
+    fn from_value(v: &Value) -> Result<Dist, String> {
+        let Value::Record(fields) = v else { return Err("expected a distribution".to_string()) };
+        let get = |key: &str| fields.iter().find(|(k, _)| k == key).map(|(_, v)| v);
+        let Some(Value::Str(name)) = get("dist") else { return Err("expected a distribution".to_string()) };
+        let names: &[&str] = PARAM_NAMES.iter().find(|(n, _)| n == name).map(|(_, ps)| *ps)
+            .ok_or_else(|| format!("unknown distribution '{}'", name))?;
+        let params: Vec<f64> = names.iter().map(|key| match get(key) {
+            Some(Value::Number(x)) => Ok(*x),
+            _ => Err(format!("{}: missing parameter '{}'", name, key)),
+        }).collect::<Result<_, _>>()?;
+        Dist::new(name, &params)
+    }
+
+    fn to_value(&self, name: &str, params: &[f64]) -> Value {
+        let names: &[&str] = PARAM_NAMES.iter().find(|(n, _)| *n == name).map(|(_, ps)| *ps).unwrap_or(&[]);
+        let params: &[f64] = if matches!(self, Dist::Normal(_)) && params.is_empty() { &[0.0, 1.0] } else { params };
+        let mut fields: Vec<(String, Value)> = vec![("dist".to_string(), Value::Str(name.to_string()))];
+        fields.extend(names.iter().zip(params.iter()).map(|(k, v)| (k.to_string(), Value::Number(*v))));
+        Value::Record(fields)
+    }
+
+    fn pdf(&self, x: f64) -> f64 {
+        // probability mass for the discrete distributions (zero off the integers)
+        let pmf = |f: &dyn Fn(u64) -> f64| if x >= 0.0 && x.fract() == 0.0 { f(x as u64) } else { 0.0 };
+        match self {
+            Dist::Normal(d) => d.pdf(x),
+            Dist::Binomial(d) => pmf(&|k| d.pmf(k)),
+            Dist::Poisson(d) => pmf(&|k| d.pmf(k)),
+            Dist::StudentT(d) => d.pdf(x),
+            Dist::ChiSquared(d) => d.pdf(x),
+            Dist::FisherF(d) => d.pdf(x),
+            Dist::Gamma(d) => d.pdf(x),
+            Dist::Beta(d) => d.pdf(x),
+        }
+    }
+
+    fn cdf(&self, x: f64) -> f64 {
+        let discrete = |f: &dyn Fn(u64) -> f64| if x < 0.0 { 0.0 } else { f(x.floor().min(u64::MAX as f64) as u64) };
+        match self {
+            Dist::Normal(d) => d.cdf(x),
+            Dist::Binomial(d) => discrete(&|k| d.cdf(k)),
+            Dist::Poisson(d) => discrete(&|k| d.cdf(k)),
+            Dist::StudentT(d) => d.cdf(x),
+            Dist::ChiSquared(d) => d.cdf(x.max(0.0)),
+            Dist::FisherF(d) => d.cdf(x.max(0.0)),
+            Dist::Gamma(d) => d.cdf(x.max(0.0)),
+            Dist::Beta(d) => d.cdf(x.clamp(0.0, 1.0)),
+        }
+    }
+
+    fn quantile(&self, p: f64) -> Result<f64, String> {
+        if !(0.0..=1.0).contains(&p) {
+            return Err("quantile: p must be between 0 and 1".to_string());
+        }
+        Ok(match self {
+            Dist::Normal(d) => d.inverse_cdf(p),
+            Dist::StudentT(d) => d.inverse_cdf(p),
+            Dist::Binomial(_) | Dist::Poisson(_) => self.discrete_quantile(p),
+            // statrs' generic inverse only does a handful of bisection steps; refine it here
+            Dist::ChiSquared(_) | Dist::FisherF(_) | Dist::Gamma(_) => self.continuous_quantile(p, 0.0, f64::INFINITY),
+            Dist::Beta(_) => self.continuous_quantile(p, 0.0, 1.0),
+        })
+    }
+
+    fn continuous_quantile(&self, p: f64, min: f64, max: f64) -> f64 {
+        if p == 0.0 { return min; }
+        if p == 1.0 { return max; }
+        let mut lo: f64 = min;
+        let mut hi: f64 = if max.is_finite() { max } else { 1.0 };
+        while self.cdf(hi) < p { lo = hi; hi *= 2.0; }
+        for _ in 0..200 {
+            let mid: f64 = 0.5 * (lo + hi);
+            if mid <= lo || mid >= hi { break; }
+            if self.cdf(mid) < p { lo = mid; } else { hi = mid; }
+        }
+        0.5 * (lo + hi)
+    }
+
+    fn discrete_quantile(&self, p: f64) -> f64 {
+        // smallest k with cdf(k) >= p
+        if p == 1.0 {
+            return match self { Dist::Binomial(d) => d.n() as f64, _ => f64::INFINITY };
+        }
+        let mut hi: f64 = 1.0;
+        while self.cdf(hi) < p { hi *= 2.0; }
+        let mut lo: f64 = -1.0;
+        while hi - lo > 1.0 {
+            let mid: f64 = ((lo + hi) / 2.0).floor();
+            if self.cdf(mid) < p { lo = mid; } else { hi = mid; }
+        }
+        hi
+    }
+}
@@ -0,0 +1,204 @@
+// String built-ins: case, splitting and joining, substrings, formatting and conversions
+
+use crate::display::{show, NumberFormat};
+use crate::evaluator::{display_value, Env, Value};
+
+pub const NAMES: &[&str] = &["upper", "lower", "split", "join", "replace", "substr", "format", "number", "str"];
+
+/// Dispatch a string built-in. Returns `None` when `name` is not one of [`NAMES`].
+pub fn call(name: &str, args: &[Value], env: &mut Env) -> Option<Value> {
+    if !NAMES.contains(&name) { return None; }
+    Some(dispatch(name, args, env.shared.format.get()).unwrap_or_else(|msg| Value::Str(format!("ERROR: {}", msg))))
+}
+
+fn dispatch(name: &str, args: &[Value], format: NumberFormat) -> Result<Value, String> {
+    match name {
+        "upper" | "lower" => {
+            let [s] = args else { return Err(format!("{} expects 1 argument (string)", name)) };
+            let s: &str = text(name, s)?;
+            Ok(Value::Str(if name == "upper" { s.to_uppercase() } else { s.to_lowercase() }))
+        }
+        "split" => {
+            let (s, sep): (&str, Option<&str>) = match args {
+                [s] => (text(name, s)?, None),
+                [s, sep] => (text(name, s)?, Some(text(name, sep)?)),
+                _ => return Err("split expects 1 or 2 arguments (string, [separator])".to_string()),
+            };
+            // no separator splits on whitespace, an empty one into characters
+            let pieces: Vec<String> = match sep {
+                None => s.split_whitespace().map(str::to_string).collect(),
+                Some("") => s.chars().map(String::from).collect(),
+                Some(sep) => s.split(sep).map(str::to_string).collect(),
+            };
+            Ok(Value::Array(pieces.into_iter().map(Value::Str).collect()))
+        }
+        "join" => {
+            let (items, sep): (&[Value], &str) = match args {
+                [Value::Array(items)] => (items, ""),
+                [Value::Array(items), sep] => (items, text(name, sep)?),
+                _ => return Err("join expects 1 or 2 arguments (array, [separator])".to_string()),
+            };
+            Ok(Value::Str(items.iter().map(|v| show(v, format)).collect::<Vec<String>>().join(sep)))
+        }
+        "replace" => {
+            let [s, from, to] = args else { return Err("replace expects 3 arguments (string, from, to)".to_string()) };
+            let (s, from, to) = (text(name, s)?, text(name, from)?, text(name, to)?);
+            if from.is_empty() { return Err("replace: the text to replace must not be empty".to_string()); }
+            Ok(Value::Str(s.replace(from, to)))
+        }
+        "substr" => substr(args),
+        "format" => {
+            let Some((fmt, rest)) = args.split_first() else { return Err("format expects a format string".to_string()) };
+            Ok(Value::Str(format_values(text(name, fmt)?, rest, format)?))
+        }
+        "number" => {
+            let [s] = args else { return Err("number expects 1 argument (string)".to_string()) };
+            match s {
+                Value::Number(_) => Ok(s.clone()),
+                Value::Str(text) => text.trim().parse::<f64>().map(Value::Number)
+                    .map_err(|_| format!("number: cannot read \"{}\" as a number", text)),
+                other => Err(format!("number: expected a string, got {}", display_value(other))),
+            }
+        }
+        "str" => {
+            let [v] = args else { return Err("str expects 1 argument".to_string()) };
+            Ok(Value::Str(show(v, format)))
+        }
+        _ => Err(format!("function '{}' does not exist or is not implemented", name)),
+    }
+}
+
+/// `a + b` when either side is a string: the two joined as text, numbers written in `format`.
+pub fn concat(l: Value, r: Value, format: NumberFormat) -> Value {
+    for v in [&l, &r] {
+        if matches!(v, Value::Str(s) if s.starts_with("ERROR:")) { return v.clone(); }
+    }
+    Value::Str(show(&l, format) + &show(&r, format))
+}
+
+/// substr(s, start, [len]): characters from index `start` (0-based; negative counts from the end)
+fn substr(args: &[Value]) -> Result<Value, String> {
+    let (s, start, len) = match args {
+        [s, start] => (text("substr", s)?, start, None),
+        [s, start, len] => (text("substr", s)?, start, Some(len)),
+        _ => return Err("substr expects 2 or 3 arguments (string, start, [length])".to_string()),
+    };
+    let chars: Vec<char> = s.chars().collect();
+    let start: usize = match start {
+        Value::Number(i) if i.fract() == 0.0 && *i < 0.0 => chars.len().saturating_sub(i.abs() as usize),
+        Value::Number(i) if i.fract() == 0.0 => (*i as usize).min(chars.len()),
+        other => return Err(format!("substr: start must be a whole number, got {}", display_value(other))),
+    };
+    let end: usize = match len {
+        None => chars.len(),
+        Some(Value::Number(n)) if n.fract() == 0.0 && *n >= 0.0 => start.saturating_add(*n as usize).min(chars.len()),
+        Some(other) => return Err(format!("substr: length must be a non-negative whole number, got {}", display_value(other))),
+    };
+    Ok(Value::Str(chars[start..end].iter().collect()))
+}
+
+/// Fill the `{}` placeholders of `fmt` with `args` in order. A placeholder may carry a spec
+/// `{:[<|>][width][.precision][e]}`; `{{` and `}}` stand for literal braces. A bare `{}` writes
+/// numbers in `format`.
+pub fn format_values(fmt: &str, args: &[Value], format: NumberFormat) -> Result<String, String> {
+    let mut out: String = String::new();
+    let mut next: usize = 0;
+    let mut chars = fmt.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '{' if chars.peek() == Some(&'{') => { chars.next(); out.push('{'); }
+            '}' if chars.peek() == Some(&'}') => { chars.next(); out.push('}'); }
+            '{' => {
+                let mut spec: String = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => spec.push(c),
+                        None => return Err(format!("format: unclosed placeholder in \"{}\"", fmt)),
+                    }
+                }
+                let spec: Spec = Spec::parse(&spec).ok_or_else(|| format!("format: unknown placeholder {{{}}}", spec))?;
+                let arg: &Value = args.get(next).ok_or_else(|| format!("format: not enough arguments for \"{}\"", fmt))?;
+                out.push_str(&spec.apply(arg, format)?);
+                next += 1;
+            }
+            _ => out.push(ch),
+        }
+    }
+    if next < args.len() {
+        return Err(format!("format: {} arguments given for the {} placeholders in \"{}\"", args.len(), next, fmt));
+    }
+    Ok(out)
+}
+
+struct Spec {
+    // None aligns numbers right and everything else left
+    right: Option<bool>,
+    width: usize,
+    precision: Option<usize>,
+    exponent: bool,
+}
+
+impl Spec {
+    fn parse(spec: &str) -> Option<Spec> {
+        let mut rest: &str = match spec {
+            "" => return Some(Spec { right: None, width: 0, precision: None, exponent: false }),
+            _ => spec.strip_prefix(':')?,
+        };
+        let right: Option<bool> = match rest.chars().next() {
+            Some('<') => Some(false),
+            Some('>') => Some(true),
+            _ => None,
+        };
+        if right.is_some() { rest = &rest[1..]; }
+        let exponent: bool = rest.ends_with('e');
+        if exponent { rest = &rest[..rest.len() - 1]; }
+        let (width, precision) = match rest.split_once('.') {
+            Some((w, p)) => (w, Some(p.parse::<usize>().ok()?)),
+            None => (rest, None),
+        };
+        let width: usize = if width.is_empty() { 0 } else { width.parse::<usize>().ok()? };
+        Some(Spec { right, width, precision, exponent })
+    }
+
+    fn apply(&self, v: &Value, format: NumberFormat) -> Result<String, String> {
+        let number: bool = matches!(v, Value::Number(_) | Value::Complex(_) | Value::Array(_));
+        let body: String = if self.precision.is_some() || self.exponent {
+            self.numeric(v)?
+        } else {
+            show(v, format)
+        };
+        let pad: String = " ".repeat(self.width.saturating_sub(body.chars().count()));
+        Ok(if self.right.unwrap_or(number) { pad + &body } else { body + &pad })
+    }
+
+    fn numeric(&self, v: &Value) -> Result<String, String> {
+        let num = |x: f64| -> String {
+            match (self.precision, self.exponent) {
+                (Some(p), true) => format!("{:.*e}", p, x),
+                (None, true) => format!("{:e}", x),
+                (Some(p), false) => format!("{:.*}", p, x),
+                (None, false) => x.to_string(),
+            }
+        };
+        match v {
+            Value::Number(x) => Ok(num(*x)),
+            Value::Complex(c) => {
+                let sign: char = if c.im < 0.0 { '-' } else { '+' };
+                Ok(format!("{}{}{}i", num(c.re), sign, num(c.im.abs())))
+            }
+            Value::Array(items) => {
+                let parts: Vec<String> = items.iter().map(|item| self.numeric(item)).collect::<Result<_, _>>()?;
+                Ok(format!("[{}]", parts.join(", ")))
+            }
+            other => Err(format!("format: a precision or exponent needs a number, got {}", display_value(other))),
+        }
+    }
+}
+
+fn text<'a>(name: &str, v: &'a Value) -> Result<&'a str, String> {
+    match v {
+        Value::Str(s) => Ok(s),
+        other => Err(format!("{}: expected a string, got {}", name, display_value(other))),
+    }
+}
@@ -0,0 +1,239 @@
+// Symbolic differentiation and simplification of expression trees
+
+use crate::ast::{AstNode, BinaryOpKind, UnaryOpKind};
+use crate::evaluator::{Env, Value};
+
+// user functions are inlined when differentiated; this bounds mutual recursion
+const MAX_INLINE_DEPTH: usize = 16;
+
+/// d(node)/d(var), simplified. `None` when some part has no known derivative rule
+/// (e.g. factorial, conditions on `var`, recursive functions).
+pub fn differentiate(node: &AstNode, var: &str, env: &Env) -> Option<AstNode> {
+    diff(node, var, env, 0).map(|d| simplify(&d))
+}
+
+fn diff(node: &AstNode, var: &str, env: &Env, depth: usize) -> Option<AstNode> {
+    if !mentions(node, var) {
+        return Some(num(0.0));
+    }
+    Some(match node {
+        AstNode::Variable(name) if name == var => num(1.0),
+        AstNode::UnaryOp { op: UnaryOpKind::Negate, expr } => neg(diff(expr, var, env, depth)?),
+        AstNode::BinaryOp { op, left, right } => {
+            let (f, g) = (&**left, &**right);
+            match op {
+                BinaryOpKind::Add => add(diff(f, var, env, depth)?, diff(g, var, env, depth)?),
+                BinaryOpKind::Sub => sub(diff(f, var, env, depth)?, diff(g, var, env, depth)?),
+                // (fg)' = f'g + fg'
+                BinaryOpKind::Mul => add(mul(diff(f, var, env, depth)?, g.clone()), mul(f.clone(), diff(g, var, env, depth)?)),
+                // (f/g)' = (f'g - fg') / g^2
+                BinaryOpKind::Div => div(
+                    sub(mul(diff(f, var, env, depth)?, g.clone()), mul(f.clone(), diff(g, var, env, depth)?)),
+                    pow(g.clone(), num(2.0)),
+                ),
+                BinaryOpKind::Pow => {
+                    if !mentions(g, var) {
+                        // (f^c)' = c f^(c-1) f'
+                        mul(mul(g.clone(), pow(f.clone(), sub(g.clone(), num(1.0)))), diff(f, var, env, depth)?)
+                    } else if !mentions(f, var) {
+                        // (c^g)' = c^g ln(c) g'
+                        mul(mul(node.clone(), ln_of(f, env)), diff(g, var, env, depth)?)
+                    } else {
+                        // (f^g)' = f^g (g' ln f + g f' / f)
+                        let inner: AstNode = add(
+                            mul(diff(g, var, env, depth)?, call("ln", f.clone())),
+                            div(mul(g.clone(), diff(f, var, env, depth)?), f.clone()),
+                        );
+                        mul(node.clone(), inner)
+                    }
+                }
+                _ => return None,
+            }
+        }
+        AstNode::FunctionCall { name, args } => {
+            if let Some(f) = env.funcs.get(name) {
+                // chain rule through a user function by inlining its body
+                if depth >= MAX_INLINE_DEPTH || f.params.len() != args.len() { return None; }
+                let mut body: AstNode = f.body.clone();
+                for (p, a) in f.params.iter().zip(args.iter()) {
+                    body = substitute(&body, p, a);
+                }
+                return diff(&body, var, env, depth + 1);
+            }
+            let [u] = args.as_slice() else { return None };
+            let du: AstNode = diff(u, var, env, depth)?;
+            let outer: AstNode = builtin_derivative(name, u)?;
+            mul(outer, du)
+        }
+        AstNode::Conditional { condition, body } => {
+            // the derivative only exists where the guard holds, and only if the guard ignores var
+            if mentions(condition, var) { return None; }
+            AstNode::Conditional { condition: condition.clone(), body: Box::new(diff(body, var, env, depth)?) }
+        }
+        _ => return None,
+    })
+}
+
+/// f'(u) for a built-in f, as an expression in u.
+fn builtin_derivative(name: &str, u: &AstNode) -> Option<AstNode> {
+    let u = || u.clone();
+    let one_minus_u2 = || sub(num(1.0), pow(u(), num(2.0)));
+    let one_plus_u2 = || add(num(1.0), pow(u(), num(2.0)));
+    Some(match name {
+        "sin" => call("cos", u()),
+        "cos" => neg(call("sin", u())),
+        "tan" => pow(call("sec", u()), num(2.0)),
+        "sec" => mul(call("sec", u()), call("tan", u())),
+        "csc" => neg(mul(call("csc", u()), call("cot", u()))),
+        "cot" => neg(pow(call("csc", u()), num(2.0))),
+        // square roots are written as powers so they evaluate without a sqrt built-in
+        "asin" => pow(one_minus_u2(), num(-0.5)),
+        "acos" => neg(pow(one_minus_u2(), num(-0.5))),
+        "atan" => div(num(1.0), one_plus_u2()),
+        "sinh" => call("cosh", u()),
+        "cosh" => call("sinh", u()),
+        "tanh" => pow(call("sech", u()), num(2.0)),
+        "sech" => neg(mul(call("sech", u()), call("tanh", u()))),
+        "asinh" => pow(one_plus_u2(), num(-0.5)),
+        "acosh" => pow(sub(pow(u(), num(2.0)), num(1.0)), num(-0.5)),
+        "atanh" => div(num(1.0), one_minus_u2()),
+        "ln" => div(num(1.0), u()),
+        "log" => div(num(1.0), mul(u(), num(std::f64::consts::LN_10))),
+        "exp" => call("exp", u()),
+        "sqrt" => mul(num(0.5), pow(u(), num(-0.5))),
+        // erf'(u) = 2/sqrt(pi) e^(-u^2)
+        "erf" => mul(num(std::f64::consts::FRAC_2_SQRT_PI), pow(num(std::f64::consts::E), neg(pow(u(), num(2.0))))),
+        "erfc" => neg(mul(num(std::f64::consts::FRAC_2_SQRT_PI), pow(num(std::f64::consts::E), neg(pow(u(), num(2.0)))))),
+        _ => return None,
+    })
+}
+
+fn ln_of(c: &AstNode, env: &Env) -> AstNode {
+    // the constant e is common enough to fold here
+    match c {
+        AstNode::Variable(name) if name == "e" && matches!(env.vars.get("e"), Some(Value::Number(x)) if *x == std::f64::consts::E) => num(1.0),
+        AstNode::Number(x) => num(x.ln()),
+        other => call("ln", other.clone()),
+    }
+}
+
+/// Replace every free occurrence of `var` in `node` with `with`.
+pub fn substitute(node: &AstNode, var: &str, with: &AstNode) -> AstNode {
+    let sub = |n: &AstNode| Box::new(substitute(n, var, with));
+    match node {
+        AstNode::Variable(name) if name == var => with.clone(),
+        AstNode::UnaryOp { op, expr } => AstNode::UnaryOp { op: op.clone(), expr: sub(expr) },
+        AstNode::BinaryOp { op, left, right } => AstNode::BinaryOp { op: op.clone(), left: sub(left), right: sub(right) },
+        AstNode::FunctionCall { name, args } => AstNode::FunctionCall { name: name.clone(), args: args.iter().map(|a| substitute(a, var, with)).collect() },
+        AstNode::DerivativeCall { name, args, var: v, order } => AstNode::DerivativeCall {
+            name: name.clone(), args: args.iter().map(|a| substitute(a, var, with)).collect(), var: v.clone(), order: *order,
+        },
+        AstNode::Array(items) => AstNode::Array(items.iter().map(|a| substitute(a, var, with)).collect()),
+        AstNode::Conditional { condition, body } => AstNode::Conditional { condition: sub(condition), body: sub(body) },
+        // inner bindings of the same name shadow var
+        AstNode::Lambda { params, .. } if params.iter().any(|p| p == var) => node.clone(),
+        AstNode::Lambda { params, body } => AstNode::Lambda { params: params.clone(), body: sub(body) },
+        AstNode::DerivativeExpr { var: v, .. } if v == var => node.clone(),
+        AstNode::Lim { var: v, to, side, expr } if v == var => AstNode::Lim { var: v.clone(), to: sub(to), side: *side, expr: expr.clone() },
+        AstNode::DerivativeExpr { var: v, order, expr } => AstNode::DerivativeExpr { var: v.clone(), order: *order, expr: sub(expr) },
+        AstNode::Lim { var: v, to, side, expr } => AstNode::Lim { var: v.clone(), to: sub(to), side: *side, expr: sub(expr) },
+        AstNode::Series { var: v, at, order, expr } => AstNode::Series {
+            var: v.clone(), at: sub(at), order: order.as_deref().map(sub), expr: if v == var { expr.clone() } else { sub(expr) },
+        },
+        other => other.clone(),
+    }
+}
+
+/// Whether `var` occurs free in `node`.
+pub fn mentions(node: &AstNode, var: &str) -> bool {
+    match node {
+        AstNode::Variable(name) => name == var,
+        AstNode::UnaryOp { expr, .. } => mentions(expr, var),
+        AstNode::BinaryOp { left, right, .. } => mentions(left, var) || mentions(right, var),
+        AstNode::FunctionCall { args, .. } | AstNode::DerivativeCall { args, .. } | AstNode::Array(args) => args.iter().any(|a| mentions(a, var)),
+        AstNode::Conditional { condition, body } => mentions(condition, var) || mentions(body, var),
+        AstNode::Lambda { params, body } => !params.iter().any(|p| p == var) && mentions(body, var),
+        AstNode::DerivativeExpr { var: v, expr, .. } => v != var && mentions(expr, var),
+        AstNode::Lim { var: v, to, expr, .. } => mentions(to, var) || (v != var && mentions(expr, var)),
+        AstNode::Series { var: v, at, order, expr } => mentions(at, var) || order.as_deref().is_some_and(|o| mentions(o, var)) || (v != var && mentions(expr, var)),
+        _ => false,
+    }
+}
+
+/// Number of nodes in the tree, used to give up on derivatives that blow up.
+pub fn size(node: &AstNode) -> usize {
+    1 + match node {
+        AstNode::UnaryOp { expr, .. } => size(expr),
+        AstNode::BinaryOp { left, right, .. } => size(left) + size(right),
+        AstNode::FunctionCall { args, .. } | AstNode::Array(args) => args.iter().map(size).sum(),
+        AstNode::Conditional { condition, body } => size(condition) + size(body),
+        _ => 0,
+    }
+}
+
+/// Algebraic clean-up: constant folding and the identities for 0 and 1.
+pub fn simplify(node: &AstNode) -> AstNode {
+    match node {
+        AstNode::UnaryOp { op: UnaryOpKind::Negate, expr } => match simplify(expr) {
+            AstNode::Number(x) => num(-x),
+            AstNode::UnaryOp { op: UnaryOpKind::Negate, expr } => *expr,
+            e => neg(e),
+        },
+        AstNode::BinaryOp { op, left, right } => {
+            let (l, r) = (simplify(left), simplify(right));
+            if let (AstNode::Number(a), AstNode::Number(b)) = (&l, &r) {
+                let folded: Option<f64> = match op {
+                    BinaryOpKind::Add => Some(a + b),
+                    BinaryOpKind::Sub => Some(a - b),
+                    BinaryOpKind::Mul => Some(a * b),
+                    BinaryOpKind::Div if *b != 0.0 => Some(a / b),
+                    BinaryOpKind::Pow => Some(a.powf(*b)),
+                    _ => None,
+                };
+                if let Some(v) = folded { return num(v); }
+            }
+            let is = |n: &AstNode, v: f64| matches!(n, AstNode::Number(x) if *x == v);
+            match op {
+                BinaryOpKind::Add if is(&l, 0.0) => r,
+                BinaryOpKind::Add | BinaryOpKind::Sub if is(&r, 0.0) => l,
+                BinaryOpKind::Sub if is(&l, 0.0) => simplify(&neg(r)),
+                BinaryOpKind::Add => match r {
+                    AstNode::UnaryOp { op: UnaryOpKind::Negate, expr } => sub(l, *expr),
+                    r => add(l, r),
+                },
+                BinaryOpKind::Mul if is(&l, 0.0) || is(&r, 0.0) => num(0.0),
+                BinaryOpKind::Mul if is(&l, 1.0) => r,
+                BinaryOpKind::Mul if is(&r, 1.0) => l,
+                BinaryOpKind::Mul if is(&l, -1.0) => simplify(&neg(r)),
+                BinaryOpKind::Mul if is(&r, -1.0) => simplify(&neg(l)),
+                BinaryOpKind::Mul => match (l, r) {
+                    // keep numbers in front and merge them: (2 * (3 * f)) -> 6 * f
+                    (AstNode::Number(a), AstNode::BinaryOp { op: BinaryOpKind::Mul, left, right }) if matches!(*left, AstNode::Number(_)) => {
+                        let AstNode::Number(b) = *left else { unreachable!() };
+                        mul(num(a * b), *right)
+                    }
+                    (l, r @ AstNode::Number(_)) => mul(r, l),
+                    (l, r) => mul(l, r),
+                },
+                BinaryOpKind::Div if is(&l, 0.0) && !is(&r, 0.0) => num(0.0),
+                BinaryOpKind::Div if is(&r, 1.0) => l,
+                BinaryOpKind::Pow if is(&r, 0.0) => num(1.0),
+                BinaryOpKind::Pow if is(&r, 1.0) => l,
+                _ => AstNode::BinaryOp { op: op.clone(), left: Box::new(l), right: Box::new(r) },
+            }
+        }
+        AstNode::FunctionCall { name, args } => AstNode::FunctionCall { name: name.clone(), args: args.iter().map(simplify).collect() },
+        AstNode::Conditional { condition, body } => AstNode::Conditional { condition: condition.clone(), body: Box::new(simplify(body)) },
+        other => other.clone(),
+    }
+}
+
+pub fn num(x: f64) -> AstNode { AstNode::Number(x) }
+fn neg(a: AstNode) -> AstNode { AstNode::UnaryOp { op: UnaryOpKind::Negate, expr: Box::new(a) } }
+fn bin(op: BinaryOpKind, a: AstNode, b: AstNode) -> AstNode { AstNode::BinaryOp { op, left: Box::new(a), right: Box::new(b) } }
+pub fn add(a: AstNode, b: AstNode) -> AstNode { bin(BinaryOpKind::Add, a, b) }
+pub fn sub(a: AstNode, b: AstNode) -> AstNode { bin(BinaryOpKind::Sub, a, b) }
+pub fn mul(a: AstNode, b: AstNode) -> AstNode { bin(BinaryOpKind::Mul, a, b) }
+pub fn div(a: AstNode, b: AstNode) -> AstNode { bin(BinaryOpKind::Div, a, b) }
+pub fn pow(a: AstNode, b: AstNode) -> AstNode { bin(BinaryOpKind::Pow, a, b) }
+fn call(name: &str, a: AstNode) -> AstNode { AstNode::FunctionCall { name: name.to_string(), args: vec![a] } }
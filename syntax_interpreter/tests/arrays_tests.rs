@@ -0,0 +1,62 @@
+// Tests for the higher-order array built-ins
+use syntax_interpreter::evaluator::{Env, eval, display_value};
+
+fn eval_display(src: &str) -> String {
+    let mut env = Env::with_builtins();
+    let tokens = syntax_interpreter::lexer::tokenize(src);
+    let ast = syntax_interpreter::parser::parse(&tokens);
+    display_value(&eval(&ast, &mut env))
+}
+
+#[test]
+fn test_map_user_and_builtin() {
+    assert_eq!(eval_display("sq(x) = x^2; map(sq, [1, 2, 3])"), "[1, 4, 9]");
+    assert_eq!(eval_display("map(floor, [1.5, [2.5, 3.5]])"), "[1, [2, 3]]");
+}
+
+#[test]
+fn test_map_broadcast_and_shape() {
+    assert_eq!(eval_display("map((x, y) => x * y, [[1, 2], [3, 4]], 2)"), "[[2, 4], [6, 8]]");
+    assert!(eval_display("map((x, y) => x + y, [1, 2], [1])").starts_with("ERROR"));
+}
+
+#[test]
+fn test_filter_reduce() {
+    assert_eq!(eval_display("filter((x) => x mod 2 == 0, range(7))"), "[0, 2, 4, 6]");
+    assert_eq!(eval_display("reduce((a, b) => a * b, [1, 2, 3, 4])"), "24");
+    assert_eq!(eval_display("reduce((a, b) => a + b, [], 5)"), "5");
+}
+
+#[test]
+fn test_lambda_captures_variables() {
+    assert_eq!(eval_display("k = 10; map((x) => k + x, [1, 2])"), "[11, 12]");
+}
+
+#[test]
+fn test_zip_enumerate() {
+    assert_eq!(eval_display("zip([1, 2], [3, 4])"), "[[1, 3], [2, 4]]");
+    assert_eq!(eval_display("enumerate([5, 6])"), "[[0, 5], [1, 6]]");
+}
+
+#[test]
+fn test_sort_unique_reverse() {
+    assert_eq!(eval_display("sort([3, 1, 2])"), "[1, 2, 3]");
+    assert_eq!(eval_display("sort([3, 1, 2], (x) => -x)"), "[3, 2, 1]");
+    assert_eq!(eval_display("unique([1, 2, 1, 3])"), "[1, 2, 3]");
+    assert_eq!(eval_display("reverse([1, 2, 3])"), "[3, 2, 1]");
+}
+
+#[test]
+fn test_constructors() {
+    assert_eq!(eval_display("range(3)"), "[0, 1, 2]");
+    assert_eq!(eval_display("range(1, 2, 0.5)"), "[1, 1.5]");
+    assert_eq!(eval_display("linspace(0, 1, 3)"), "[0, 0.5, 1]");
+    assert_eq!(eval_display("len(linspace(0, 1, 11))"), "11");
+}
+
+#[test]
+fn test_concat_flatten() {
+    assert_eq!(eval_display("concat([1], [2, 3], 4)"), "[1, 2, 3, 4]");
+    assert_eq!(eval_display("flatten([1, [2, [3]]])"), "[1, 2, 3]");
+    assert_eq!(eval_display("flatten([1, [2, [3]]], 1)"), "[1, 2, [3]]");
+}
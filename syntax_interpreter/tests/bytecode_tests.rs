@@ -0,0 +1,112 @@
+// Tests for the bytecode machine: compiled function bodies give the same results as walking the tree
+use syntax_interpreter::evaluator::display_value;
+use syntax_interpreter::interpreter::Interpreter;
+use syntax_interpreter::sandbox::Limits;
+
+// the result (or error) and printed output of `src`, with or without the bytecode machine
+fn run(src: &str, vm: bool) -> String {
+    let mut ms = Interpreter::new();
+    ms.env().shared.vm.set_enabled(vm);
+    let result: String = match ms.eval_str(src) {
+        Ok(v) => display_value(&v),
+        Err(msg) => format!("error: {}", msg),
+    };
+    format!("{}\n{}", ms.take_output(), result)
+}
+
+fn same(src: &str) -> String {
+    let compiled: String = run(src, true);
+    assert_eq!(compiled, run(src, false), "{}", src);
+    compiled
+}
+
+#[test]
+fn test_arithmetic_and_logic() {
+    assert!(same("f(x, y) = x^2 + 3*x*y - y / 2\nf(2, 4)").ends_with("\n26"));
+    assert!(same("f(x) = 0 < x <= 1\nprint(f(0.5), f(2), f(0))").starts_with("true false false"));
+    assert!(same("even(n) = n == 0 or odd(n - 1)\nodd(n) = n != 0 and even(n - 1)\nprint(even(10), odd(7), even(7))").starts_with("true true false"));
+    same("f(x) = x and true\nf(1)");
+    same("f(x) = not x xor x\nf(true)");
+    assert!(same("f(x) = |x| + x!\nf(3)").ends_with("\n9"));
+    same("f(x) = |x| + x!\nf(-3)");
+    same("f(x) = (x + 2i) * i\nf(1)");
+    same("f(x) = \"n = \" + x\nf(3)");
+}
+
+#[test]
+fn test_conditionals_and_variables() {
+    same("f(x) = x^2 {x > 0}\nprint(f(2), f(-2))");
+    same("f(x) = x {x}\nf(1)");
+    // parameters shadow constants; other names are looked up when the function is called
+    same("f(e) = e + pi\nf(1)");
+    same("f(x) = x + y\nprint(f(1))\ny = 2\nf(1)");
+}
+
+#[test]
+fn test_calls() {
+    same("sq(x) = x^2\nf(x) = sq(x) + sq(x + 1) + sin(0)\nf(2)");
+    same("apply(g, x) = g(g(x))\napply((t) => t * 3, 2)");
+    assert!(same("k = 10\nadd = (x) => x + k\nf(g, x) = g(x) + max([1, x])\nf(add, 5)").ends_with("\n20"));
+    assert!(same("f(p, x) = p(x)\nf(poly([1, 0, 1]), 2)").ends_with("\n5"));
+    assert!(same("f(g, x) = g(x) + g(x)\nf(sqrt, 16)").ends_with("\n8"));
+    assert!(same("f(x) = {a: x, b: 2}.a + 1\nf(3)").ends_with("\n4"));
+    same("f(x) = (x, x + 1)\nf(1)");
+    same("f(x) = missing(x)\nf(1)");
+    same("f(x) = nothing + x\nf(1)");
+    same("f(x, y) = x + y\ng(x) = f(x)\ng(1)");
+    // built-ins that look names up still see the function's variables
+    assert!(same("cube(x) = x^3\nf(y) = cube^-1(y)\nround(f(8))").ends_with("\n2"));
+    same("f(x) = sum(map((t) => t * x, 1..4))\nf(2)");
+}
+
+#[test]
+fn test_modules() {
+    assert!(same("import \"std/math\"\nf(x) = hypot(x, 4) + lerp(0, 10, 0.5)\nf(3)").ends_with("\n10"));
+    assert!(same("import \"std/geometry\" as geo\nf(g, x) = g.midpoint(x, 3)\nf(geo, 1)").ends_with("\n2"));
+    // the standard library is found without an import
+    assert!(same("f(x) = gcd(x, 12)\nf(18)").ends_with("\n6"));
+}
+
+#[test]
+fn test_numeric_loops() {
+    same("f(x) = exp(-x^2)\nint(f, -3, 3, 2000)");
+    same("c = 2\nint((x) => c * x, 0, 1)");
+    same("int(sin, 0, pi)");
+    same("a = 3\nlim {x -> 0} sin(a * x) / x");
+    same("lim {x -> 1} (x^2 - 1) / (x - 1)");
+}
+
+#[test]
+fn test_limits_still_apply() {
+    for vm in [true, false] {
+        let mut ms = Interpreter::new();
+        ms.env().shared.vm.set_enabled(vm);
+        ms.set_limits(Limits { max_depth: Some(5), ..Limits::default() });
+        assert_eq!(ms.eval_str("f(n) = f(n + 1)\nf(1)").unwrap_err(), "recursion limit exceeded (depth 5)");
+        ms.set_limits(Limits { max_steps: Some(100), ..Limits::default() });
+        assert_eq!(ms.eval_str("f(n) = n > 0 and f(n - 1)\nf(50)").unwrap_err(), "step limit exceeded (100 steps)");
+        ms.set_limits(Limits { max_array_len: Some(10), ..Limits::default() });
+        assert_eq!(ms.eval_str("f(n) = 1..n\nf(100)").unwrap_err(), "array of 100 elements exceeds the limit of 10");
+    }
+}
+
+#[test]
+fn test_natives() {
+    let mut ms = Interpreter::new();
+    ms.register_fn("twice", 1, |args| Ok((2.0 * f64::try_from(&args[0])?).into()));
+    ms.register_fn("sin", 1, |_| Ok(0.5.into()));
+    assert_eq!(display_value(&ms.eval_str("f(x) = twice(x) + sin(x)\nf(3)").unwrap()), "6.5");
+}
+
+#[test]
+fn test_switch_belongs_to_one_interpreter() {
+    let mut walked = Interpreter::new();
+    let mut compiled = Interpreter::new();
+    walked.env().shared.vm.set_enabled(false);
+    for ms in [&mut walked, &mut compiled] {
+        assert_eq!(display_value(&ms.eval_str("sq(x) = x * x\nsq(3)").unwrap()), "9");
+    }
+    let state = |ms: &Interpreter| format!("{:?}", ms.env().funcs["sq"].code);
+    assert_eq!(state(&walked), "CodeCache(not compiled)");
+    assert_eq!(state(&compiled), "CodeCache(compiled)");
+}
@@ -0,0 +1,49 @@
+// Tests for derivative syntax and the vector calculus built-ins
+use syntax_interpreter::evaluator::{Env, eval, display_value};
+
+fn eval_display(src: &str) -> String {
+    let mut env = Env::with_builtins();
+    let tokens = syntax_interpreter::lexer::tokenize(src);
+    let ast = syntax_interpreter::parser::parse(&tokens);
+    display_value(&eval(&ast, &mut env))
+}
+
+#[test]
+fn test_prime_notation_any_order() {
+    assert_eq!(eval_display("f(x) = x^3; f'(2)"), "12");
+    assert_eq!(eval_display("f(x) = x^3; f'''(2)"), "6");
+    assert_eq!(eval_display("f(x) = x^3; f[4]'(2)"), "0");
+}
+
+#[test]
+fn test_derivative_operator() {
+    assert_eq!(eval_display("x = 2; d/dx x^3"), "12");
+    assert_eq!(eval_display("x = 2; d^2/dx^2 x^3"), "12");
+    // a plain division of two variables is still a division
+    assert_eq!(eval_display("dy = 6; dx = 2; dy/dx"), "3");
+    assert_eq!(eval_display("dy = 6; dx = 2; dy/dx - 1"), "2");
+    assert_eq!(eval_display("dist = 10; dt = 2; dist/dt - 1"), "4");
+    assert_eq!(eval_display("f(dy, dx) = dy/dx - 1; f(6, 2)"), "2");
+}
+
+#[test]
+fn test_partial_derivatives() {
+    assert_eq!(eval_display("g(x, y) = x^2 * y + y^3; x = 2; y = 3; dy/dx g(x, y)"), "12");
+    assert_eq!(eval_display("g(x, y) = x^2 * y + y^3; d/dy g(1, 2)"), "13");
+    assert!(eval_display("g(x, y) = x * y; g'(1, 2)").starts_with("ERROR"));
+}
+
+#[test]
+fn test_grad_hessian() {
+    assert_eq!(eval_display("g(x, y) = x^2 * y + y^3; grad(g, [1, 2])"), "[4, 13]");
+    assert_eq!(eval_display("g(x, y) = x^2 * y + y^3; hessian(g, [1, 2])"), "[[4, 2], [2, 12]]");
+    assert!(eval_display("grad((x, y) => x * y, [1])").starts_with("ERROR"));
+}
+
+#[test]
+fn test_jacobian_div_curl() {
+    assert_eq!(eval_display("F(x, y, z) = [y*z, x*z, x*y]; jacobian(F, [1, 2, 3])"), "[[0, 3, 2], [3, 0, 1], [2, 1, 0]]");
+    assert_eq!(eval_display("F(x, y, z) = [x, 2y, 3z]; div(F, [1, 1, 1])"), "6");
+    assert_eq!(eval_display("V(x, y, z) = [-y, x, 0]; curl(V, [1, 1, 1])"), "[0, 0, 2]");
+    assert_eq!(eval_display("curl([(x, y) => -y, (x, y) => x], [0, 0])"), "2");
+}
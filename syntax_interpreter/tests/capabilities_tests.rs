@@ -0,0 +1,95 @@
+// Tests for capabilities: which files imports may read, and access to the clock
+use std::path::{Path, PathBuf};
+
+use syntax_interpreter::evaluator::display_value;
+use syntax_interpreter::interpreter::Interpreter;
+use syntax_interpreter::output::Stream;
+use syntax_interpreter::sandbox::{Access, Capabilities};
+
+// a temporary directory, removed when the test ends
+struct Scratch(PathBuf);
+
+impl Scratch {
+    fn new(name: &str) -> Scratch {
+        let dir: PathBuf = std::env::temp_dir().join(format!("{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        Scratch(dir)
+    }
+}
+
+impl std::ops::Deref for Scratch {
+    type Target = Path;
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+// a directory holding lib/m.ms and secret/s.ms
+fn project(test: &str) -> Scratch {
+    let dir: Scratch = Scratch::new(&format!("ms_caps_{}", test));
+    std::fs::create_dir_all(dir.join("lib")).unwrap();
+    std::fs::create_dir_all(dir.join("secret")).unwrap();
+    std::fs::write(dir.join("lib").join("m.ms"), "sq(x) = x^2\n").unwrap();
+    std::fs::write(dir.join("secret").join("s.ms"), "k = 42\n").unwrap();
+    dir
+}
+
+// an interpreter running as the script main.ms in `dir`
+fn interpreter(dir: &Path, capabilities: Capabilities) -> Interpreter {
+    let mut ms = Interpreter::new();
+    ms.env_mut().shared.modules.enter_script(&dir.join("main.ms"));
+    ms.set_capabilities(capabilities);
+    ms
+}
+
+#[test]
+fn test_reads_are_limited_to_allowed_paths() {
+    let dir = project("read");
+    let mut read = Access::none();
+    read.allow(&dir.join("lib"));
+    let mut ms = interpreter(&dir, Capabilities { read, ..Capabilities::default() });
+    assert_eq!(display_value(&ms.eval_str("import \"lib/m\"\nsq(3)").unwrap()), "9");
+    let secret = dir.join("secret").join("s.ms");
+    assert_eq!(ms.eval_str("import \"secret/s\"").unwrap_err(), format!("import: reading '{}' is not allowed", secret.display()));
+    // a file that does not exist is denied the same way, so a script cannot probe for files
+    let missing = dir.join("secret").join("missing.ms");
+    assert_eq!(ms.eval_str("import \"secret/missing\"").unwrap_err(), format!("import: reading '{}' is not allowed", missing.display()));
+    // .. cannot step out of an allowed directory
+    assert!(ms.eval_str("import \"lib/../secret/s.ms\"").unwrap_err().contains("is not allowed"));
+}
+
+#[test]
+fn test_nothing_allowed() {
+    let dir = project("none");
+    let mut ms = interpreter(&dir, Capabilities::none());
+    assert!(ms.eval_str("import \"lib/m\"").unwrap_err().ends_with("is not allowed"));
+    assert_eq!(ms.take_messages()[0].0, Stream::Error);
+    // the bundled standard library needs no file access
+    assert_eq!(display_value(&ms.eval_str("hypot(3, 4)").unwrap()), "5");
+    // without the clock, log messages have no time
+    ms.eval_str("log(\"INFO\", \"hi\")").unwrap();
+    let messages = ms.take_messages();
+    assert!(matches!(&messages[..], [(Stream::Log, text)] if text == "[INFO] hi"), "{:?}", messages);
+}
+
+#[test]
+fn test_write_access() {
+    let dir = project("write");
+    let mut write = Access::none();
+    write.allow(&dir.join("lib"));
+    let caps = Capabilities { write, ..Capabilities::default() };
+    assert!(caps.check_write(&dir.join("lib").join("out.log")).is_ok());
+    assert!(caps.check_write(&dir.join("out.log")).is_err());
+    // a file that does not exist yet cannot climb out of the allowed directory
+    assert!(caps.check_write(&dir.join("lib").join("..").join("secret").join("out.log")).is_err());
+    assert!(caps.check_write(&dir.join("lib").join("new").join("..").join("..").join("out.log")).is_err());
+    assert!(caps.check_write(&dir.join("secret").join("..").join("lib").join("new.log")).is_ok());
+    assert!(Capabilities::default().check_write(&dir.join("out.log")).is_ok());
+}
@@ -0,0 +1,72 @@
+// Tests for number formatting modes, snapping, fractions and matrix layout
+use syntax_interpreter::display::{pretty, show, NumberFormat};
+use syntax_interpreter::evaluator::{Env, eval};
+use syntax_interpreter::interpreter::Interpreter;
+
+// the value of `src`, shown in the format it leaves in effect
+fn eval_display(src: &str) -> String {
+    let mut env = Env::with_builtins();
+    let tokens = syntax_interpreter::lexer::tokenize(src);
+    let ast = syntax_interpreter::parser::parse(&tokens);
+    let v = eval(&ast, &mut env);
+    show(&v, env.shared.format.get())
+}
+
+#[test]
+fn test_default_format() {
+    assert_eq!(eval_display("0.1 + 0.2"), "0.30000000000000004");
+    assert_eq!(eval_display("10^-7"), "1e-7");
+    assert_eq!(eval_display("10^20"), "1e20");
+    assert_eq!(eval_display("1/4"), "0.25");
+    assert_eq!(eval_display("1 - 2*i"), "1-2i");
+}
+
+#[test]
+fn test_notation_modes() {
+    assert_eq!(eval_display("format fixed 3; [pi, 2]"), "[3.142, 2.000]");
+    assert_eq!(eval_display("format sci 4; 12345.678"), "1.235e4");
+    assert_eq!(eval_display("format eng 3; [12345.678, 0.000123, 999.96]"), "[12.3e3, 123e-6, 1.00e3]");
+    assert_eq!(eval_display("format sig 4; [pi, 1/3, 123456789, 0.5]"), "[3.142, 0.3333, 1.235e8, 0.5]");
+    assert_eq!(eval_display("format fixed 2; {x: pi, z: 1 - i}"), "{x: 3.14, z: 1.00-1.00i}");
+    assert_eq!(eval_display("format fixed 2; format auto; 1/4"), "0.25");
+}
+
+#[test]
+fn test_snap_and_fractions() {
+    assert_eq!(eval_display("format snap; 0.1 + 0.2"), "0.3");
+    assert_eq!(eval_display("format snap; sin(pi)"), "0");
+    assert_eq!(eval_display("format snap; format snap off; 0.1 + 0.2"), "0.30000000000000004");
+    assert_eq!(eval_display("format fraction; [1/3, 0.75, -2/3, 5, pi]"), "[1/3, 3/4, -2/3, 5, 3.141592653589793]");
+    assert_eq!(eval_display("format fraction; format reset; 1/4"), "0.25");
+}
+
+#[test]
+fn test_format_errors() {
+    assert_eq!(eval_display("format bogus"), "ERROR: format: unknown setting 'bogus' (expected auto, fixed, sci, eng, sig, snap, fraction or reset)");
+    assert_eq!(eval_display("format fixed 2.5"), "ERROR: format fixed: the number of digits must be a whole number from 0 to 17");
+    // format(...) is still the string function
+    assert_eq!(eval_display("format(\"{:.1}\", 2)"), "2.0");
+}
+
+#[test]
+fn test_matrix_layout() {
+    let mut env = Env::with_builtins();
+    let ast = syntax_interpreter::parser::parse(&syntax_interpreter::lexer::tokenize("[[1, 2.5], [10, -3]]"));
+    let m = eval(&ast, &mut env);
+    assert_eq!(pretty(&m, NumberFormat::default()), "[[ 1, 2.5],\n [10,  -3]]");
+    let ragged = eval(&syntax_interpreter::parser::parse(&syntax_interpreter::lexer::tokenize("[[1, 2], [3]]")), &mut env);
+    assert_eq!(pretty(&ragged, NumberFormat::default()), "[[1, 2], [3]]");
+}
+
+#[test]
+fn test_format_belongs_to_one_interpreter() {
+    let mut fixed = Interpreter::new();
+    let mut plain = Interpreter::new();
+    fixed.eval_str("format fixed 2\nf(x) = print(x, \"x = \" + x)").unwrap();
+    fixed.eval_str("f(pi)").unwrap();
+    plain.eval_str("print(pi / 4)").unwrap();
+    assert_eq!(fixed.take_output(), "3.14 x = 3.14\n");
+    assert_eq!(plain.take_output(), "0.7853981633974483\n");
+    let v = fixed.eval_str("pi").unwrap();
+    assert_eq!((fixed.display(&v), plain.display(&v)), ("3.14".to_string(), "3.141592653589793".to_string()));
+}
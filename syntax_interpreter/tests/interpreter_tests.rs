@@ -0,0 +1,58 @@
+// Tests for the embedding API: evaluation, variables, native functions, conversions and output
+use num_complex::Complex64;
+use syntax_interpreter::evaluator::{display_value, Value};
+use syntax_interpreter::interpreter::Interpreter;
+
+#[test]
+fn test_eval_str_keeps_state() {
+    let mut ms = Interpreter::new();
+    assert_eq!(ms.eval_str("sq(x) = x^2\na = 3").map(|v| display_value(&v)), Ok("3".to_string()));
+    assert_eq!(ms.eval_str("sq(a) + 1").map(|v| display_value(&v)), Ok("10".to_string()));
+    assert_eq!(ms.eval_str("nothing + 1").err(), Some("variable 'nothing' does not exist".to_string()));
+    assert_eq!(ms.eval_str("a = ) 2").err(), Some("invalid syntax".to_string()));
+    // a variable from an earlier call makes dy/dx a division
+    ms.eval_str("dy = 6\ndx = 2").unwrap();
+    assert_eq!(ms.eval_str("dy/dx - 1").map(|v| display_value(&v)), Ok("2".to_string()));
+}
+
+#[test]
+fn test_variables() {
+    let mut ms = Interpreter::new();
+    ms.set_var("xs", vec![1.0, 2.0, 3.0]);
+    ms.set_var("name", "ms");
+    ms.eval_str("total = sum(xs)\ngreeting = \"hi \" + name").unwrap();
+    assert_eq!(ms.get_var("total").map(f64::try_from), Some(Ok(6.0)));
+    assert_eq!(ms.get_var("greeting").map(String::try_from), Some(Ok("hi ms".to_string())));
+    assert!(ms.get_var("missing").is_none());
+}
+
+#[test]
+fn test_native_functions() {
+    let mut ms = Interpreter::new();
+    ms.register_fn("scale", 2, |args| {
+        let v: Vec<f64> = Vec::try_from(&args[0])?;
+        let k: f64 = f64::try_from(&args[1])?;
+        Ok(v.into_iter().map(|x| x * k).collect::<Vec<f64>>().into())
+    });
+    assert_eq!(display_value(&ms.eval_str("scale([1, 2], 3)").unwrap()), "[3, 6]");
+    // natives are values and are visible inside functions
+    assert_eq!(display_value(&ms.eval_str("f(v) = scale(v, 2)\nmap((x) => x + 1, f([1]))").unwrap()), "[3]");
+    assert_eq!(ms.eval_str("scale(1)").err(), Some("scale expects 2 arguments, got 1".to_string()));
+    assert_eq!(ms.eval_str("scale(\"a\", 2)").err(), Some("scale: expected an array of numbers, got a".to_string()));
+}
+
+#[test]
+fn test_conversions() {
+    assert_eq!(bool::try_from(Value::from(true)), Ok(true));
+    assert_eq!(Complex64::try_from(Value::from(2.0)), Ok(Complex64::new(2.0, 0.0)));
+    assert_eq!(display_value(&Value::from(vec![Value::from(1_i64), Value::from("x")])), "[1, x]");
+    assert_eq!(f64::try_from(Value::from("x")), Err("expected a number, got x".to_string()));
+}
+
+#[test]
+fn test_output_is_captured() {
+    let mut ms = Interpreter::new();
+    ms.eval_str("print(1 + 1)\nf(x) = print(\"in f\", x)\nf(3)").unwrap();
+    assert_eq!(ms.take_output(), "2\nin f 3\n");
+    assert_eq!(ms.take_output(), "");
+}
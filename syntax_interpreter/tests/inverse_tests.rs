@@ -0,0 +1,37 @@
+// Tests for numeric inverses f^-1(y)
+use syntax_interpreter::evaluator::{Env, eval, display_value};
+
+fn eval_display(src: &str) -> String {
+    let mut env = Env::with_builtins();
+    let tokens = syntax_interpreter::lexer::tokenize(src);
+    let ast = syntax_interpreter::parser::parse(&tokens);
+    display_value(&eval(&ast, &mut env))
+}
+
+#[test]
+fn test_inverse_of_monotonic_function() {
+    assert_eq!(eval_display("f(x) = x^3 + 1; f^-1(9)"), "2");
+    assert_eq!(eval_display("f(x) = e^x; f^-1(1)"), "0");
+}
+
+#[test]
+fn test_condition_restricts_domain() {
+    assert_eq!(eval_display("g(x) = x^2 {x >= 0}; g^-1(9)"), "3");
+    assert_eq!(eval_display("g(x) = x^2 {x <= 0}; g^-1(9)"), "-3");
+}
+
+#[test]
+fn test_multiple_preimages_and_range() {
+    assert_eq!(
+        eval_display("h(x) = x^2; h^-1(4)"),
+        "ERROR: h^-1: h is not invertible here, h(x) = 4 has 2 solutions (x = -2, x = 2); restrict its domain with a {condition}"
+    );
+    assert_eq!(eval_display("h(x) = x^2; h^-1(-1)"), "ERROR: h^-1: -1 is not a value of h on its domain");
+}
+
+#[test]
+fn test_negative_powers_are_not_inverses() {
+    assert_eq!(eval_display("x = 4; x^-1"), "0.25");
+    assert_eq!(eval_display("x = 2; x^-2"), "0.25");
+    assert_eq!(eval_display("sin^-1(0)"), "0");
+}
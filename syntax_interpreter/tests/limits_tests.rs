@@ -0,0 +1,42 @@
+// Tests for lim: two-sided, one-sided, infinite and divergent limits
+use syntax_interpreter::evaluator::{Env, eval, display_value};
+
+fn eval_display(src: &str) -> String {
+    let mut env = Env::with_builtins();
+    let tokens = syntax_interpreter::lexer::tokenize(src);
+    let ast = syntax_interpreter::parser::parse(&tokens);
+    display_value(&eval(&ast, &mut env))
+}
+
+#[test]
+fn test_removable_singularities() {
+    assert_eq!(eval_display("lim {x -> 0} sin(x)/x"), "1");
+    // L'Hôpital twice; the numbers alone lose this to cancellation
+    assert_eq!(eval_display("lim {x -> 0} (1 - cos(x))/x^2"), "0.5");
+    assert_eq!(eval_display("lim {x -> 1} (x^2 - 1)/(x - 1)"), "2");
+}
+
+#[test]
+fn test_one_sided_limits() {
+    assert_eq!(eval_display("lim {x -> 0+} 1/x"), "inf");
+    assert_eq!(eval_display("lim {x -> 0-} 1/x"), "-inf");
+    assert_eq!(eval_display("lim {x -> 0+} |x|/x"), "1");
+    assert_eq!(eval_display("lim {x -> 0+} ln(x)"), "-inf");
+}
+
+#[test]
+fn test_limits_at_infinity() {
+    assert_eq!(eval_display("lim {x -> inf} (2x^2 + 1)/(x^2 - 3)"), "2");
+    assert_eq!(eval_display("lim {x -> -inf} 1/x"), "0");
+    assert_eq!(eval_display("lim {x -> inf} x^2"), "inf");
+    let e: f64 = eval_display("lim {x -> inf} (1 + 1/x)^x").parse().unwrap();
+    assert!((e - std::f64::consts::E).abs() < 1e-6);
+}
+
+#[test]
+fn test_limits_that_do_not_exist() {
+    assert_eq!(eval_display("lim {x -> 0} 1/x"), "ERROR: lim: the limit does not exist (limit from below -inf, from above inf)");
+    assert_eq!(eval_display("lim {x -> 0} |x|/x"), "ERROR: lim: the limit does not exist (limit from below -1, from above 1)");
+    assert!(eval_display("lim {x -> 0} sin(1/x)").starts_with("ERROR"));
+    assert_eq!(eval_display("lim {x -> 0} 1/x^2"), "inf");
+}
@@ -0,0 +1,70 @@
+// Tests for leveled logging: levels and filtering, record formats, log files and log(x) as a logarithm
+use std::time::{Duration, UNIX_EPOCH};
+
+use syntax_interpreter::evaluator::display_value;
+use syntax_interpreter::interpreter::Interpreter;
+use syntax_interpreter::logging::{timestamp, Level, LogFormat};
+use syntax_interpreter::output::Stream;
+
+// the log records written by `src`, without their timestamps
+fn logged(ms: &mut Interpreter, src: &str) -> Vec<String> {
+    ms.eval_str(src).unwrap();
+    ms.take_messages().into_iter()
+        .filter(|(stream, _)| *stream == Stream::Log)
+        .map(|(_, text)| text.split_once(' ').map(|(_, rest)| rest.to_string()).unwrap_or(text))
+        .collect()
+}
+
+#[test]
+fn test_levels_and_filtering() {
+    let mut ms = Interpreter::new();
+    let src = "log(\"INFO\", \"x is\", 1 + 1)\nlog(\"debug\", \"hidden\")\nlog(\"WARN\", \"w\")\nlog(\"no level\")";
+    assert_eq!(logged(&mut ms, src), vec!["[INFO] x is 2", "[WARN] w", "[INFO] no level"]);
+    ms.env().shared.logger.set_level(Some(Level::Debug));
+    assert_eq!(logged(&mut ms, "log(\"DEBUG\", \"shown\")"), vec!["[DEBUG] shown"]);
+    ms.env().shared.logger.set_level(Some(Level::Error));
+    assert_eq!(logged(&mut ms, "log(\"WARN\", \"w\")\nlog(\"ERROR\", \"e\")"), vec!["[ERROR] e"]);
+    ms.env().shared.logger.set_level(None);
+    assert!(logged(&mut ms, "log(\"ERROR\", \"e\")").is_empty());
+}
+
+#[test]
+fn test_logging_from_functions() {
+    let mut ms = Interpreter::new();
+    let src = "f(x) = log(\"INFO\", \"x is\", x)\nf(3)\nf(4)\nmap((t) => log(\"WARN\", t), [1, 2])";
+    assert_eq!(logged(&mut ms, src), vec!["[INFO] x is 3", "[INFO] x is 4", "[WARN] 1", "[WARN] 2"]);
+    assert_eq!(display_value(&ms.eval_str("g(x) = log(x)\ng(100)").unwrap()), "2");
+    assert!(ms.eval_str("@memo\nh(x) = log(\"INFO\", x)\nh(1)").unwrap_err().contains("may call log"));
+}
+
+#[test]
+fn test_log_of_a_number_is_a_logarithm() {
+    let mut ms = Interpreter::new();
+    assert_eq!(display_value(&ms.eval_str("log(100)").unwrap()), "2");
+    assert_eq!(display_value(&ms.eval_str("log(1000) + 1").unwrap()), "4");
+    assert!(ms.take_messages().is_empty());
+}
+
+#[test]
+fn test_json_records_and_files() {
+    let mut ms = Interpreter::new();
+    ms.env().shared.logger.set_format(LogFormat::Json);
+    ms.eval_str("log(\"WARN\", \"say \\\"hi\\\"\")").unwrap();
+    let (_, record) = ms.take_messages().remove(0);
+    assert!(record.starts_with("{\"time\": \"") && record.ends_with("\"level\": \"WARN\", \"message\": \"say \\\"hi\\\"\"}"), "{}", record);
+
+    let path = std::env::temp_dir().join(format!("ms_log_{}.log", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    ms.env().shared.logger.set_format(LogFormat::Text);
+    ms.env().shared.logger.set_file(path.to_str().unwrap()).unwrap();
+    ms.eval_str("log(\"INFO\", \"to file\")").unwrap();
+    assert!(ms.take_messages().is_empty());
+    assert!(std::fs::read_to_string(&path).unwrap().ends_with("[INFO] to file\n"));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_timestamps() {
+    assert_eq!(timestamp(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+    assert_eq!(timestamp(UNIX_EPOCH + Duration::from_millis(951_827_696_789)), "2000-02-29T12:34:56.789Z");
+}
@@ -0,0 +1,70 @@
+// Tests for booleans, logical operators and chained comparisons
+use syntax_interpreter::evaluator::{Env, eval, display_value};
+
+fn eval_display(src: &str) -> String {
+    let mut env = Env::with_builtins();
+    let tokens = syntax_interpreter::lexer::tokenize(src);
+    let ast = syntax_interpreter::parser::parse(&tokens);
+    display_value(&eval(&ast, &mut env))
+}
+
+#[test]
+fn test_boolean_literals_and_comparisons() {
+    assert_eq!(eval_display("true"), "true");
+    assert_eq!(eval_display("3 > 2"), "true");
+    assert_eq!(eval_display("\"a\" == \"b\""), "false");
+    assert_eq!(eval_display("[1, 2] == [1, 2]"), "true");
+    assert_eq!(eval_display("(1 > 0) == (2 > 0)"), "true");
+}
+
+#[test]
+fn test_logical_operators() {
+    assert_eq!(eval_display("true and false"), "false");
+    assert_eq!(eval_display("false or true"), "true");
+    assert_eq!(eval_display("true && true"), "true");
+    assert_eq!(eval_display("false || false"), "false");
+    assert_eq!(eval_display("true xor true"), "false");
+    assert_eq!(eval_display("not 3 > 4"), "true");
+    assert_eq!(eval_display("!false"), "true");
+    // and binds tighter than or
+    assert_eq!(eval_display("true or false and false"), "true");
+    assert_eq!(eval_display("|-3| > 2 || false"), "true");
+}
+
+#[test]
+fn test_short_circuit() {
+    // the right side would be an error if it were evaluated
+    assert_eq!(eval_display("false and 1"), "false");
+    assert_eq!(eval_display("true || 1"), "true");
+    assert_eq!(eval_display("true and 1"), "ERROR: right side of 'and' must be true or false, got 1");
+}
+
+#[test]
+fn test_chained_comparisons() {
+    assert_eq!(eval_display("x = 0.5; 0 < x <= 1"), "true");
+    assert_eq!(eval_display("x = 2; 0 < x <= 1"), "false");
+    assert_eq!(eval_display("1 < 2 < 3 < 4"), "true");
+    assert_eq!(eval_display("f(x) = x^2 {0 < x < 2}; f(1)"), "1");
+    // the middle operand is evaluated once
+    assert_eq!(eval_display("seed(3); a = rand(); seed(3); 0 <= rand() == a"), "true");
+}
+
+#[test]
+fn test_conditions_need_booleans() {
+    assert_eq!(eval_display("f(x) = 1 {x}; f(2)"), "ERROR: condition must be true or false, got 2");
+    assert_eq!(eval_display("1 and true"), "ERROR: left side of 'and' must be true or false, got 1");
+    assert_eq!(eval_display("filter((x) => x, [1, 2])"), "ERROR: filter: the predicate's result must be true or false, got 1");
+}
+
+#[test]
+fn test_errors_and_bools_in_arithmetic() {
+    // an error reaches the result instead of becoming ()
+    assert_eq!(eval_display("x > 0"), "ERROR: variable 'x' does not exist");
+    assert_eq!(eval_display("{x > 0}"), "ERROR: variable 'x' does not exist");
+    assert_eq!(eval_display("1 < x < 2"), "ERROR: variable 'x' does not exist");
+    // bools are not numbers
+    assert_eq!(eval_display("3 * (2 > 1)"), "ERROR: cannot use true in arithmetic");
+    assert_eq!(eval_display("[1, false] + 1"), "ERROR: cannot use [1, false] in arithmetic");
+    assert_eq!(eval_display("true < 2"), "ERROR: cannot order true");
+    assert_eq!(eval_display("true == (1 > 0)"), "true");
+}
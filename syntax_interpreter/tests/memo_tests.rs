@@ -0,0 +1,89 @@
+// Tests for memoisation: @memo, --memo, purity and the cache limit
+use syntax_interpreter::evaluator::display_value;
+use syntax_interpreter::interpreter::Interpreter;
+use syntax_interpreter::sandbox::Limits;
+
+// exponential without memoisation
+const TREE: &str = "tree(n) = n < 2 or (tree(n - 1) and tree(n - 2))";
+// numbers need a base case without `and`/`or`: the filters pick one branch
+const FIB: &str = "fib(n) = sum(map((m) => fib(m - 1) + fib(m - 2), filter((m) => m > 1, [n]))) + sum(filter((m) => m < 2, [n]))";
+
+fn eval(ms: &mut Interpreter, src: &str) -> String {
+    match ms.eval_str(src) {
+        Ok(v) => display_value(&v),
+        Err(msg) => format!("error: {}", msg),
+    }
+}
+
+#[test]
+fn test_memo() {
+    let mut ms = Interpreter::new();
+    ms.set_limits(Limits { max_steps: Some(100_000), ..Limits::default() });
+    assert_eq!(eval(&mut ms, &format!("@memo {}\ntree(60)", TREE)), "true");
+    assert_eq!(eval(&mut ms, &format!("@memo\n{}\nfib(10)", FIB)), "55");
+    // without it the same calls run out of steps
+    assert_eq!(eval(&mut ms, &format!("{}\ntree(60)", TREE)), "error: step limit exceeded (100000 steps)");
+}
+
+#[test]
+fn test_impure_functions() {
+    let mut ms = Interpreter::new();
+    assert_eq!(eval(&mut ms, "@memo r(x) = x + rand()\nr(1)"), "error: r: @memo needs a pure function, but it may call rand");
+    assert_eq!(eval(&mut ms, "@memo p(x) = print(x)\np(1)"), "error: p: @memo needs a pure function, but it may call print");
+    assert_eq!(eval(&mut ms, "noise(x) = x * randn()\n@memo f(x) = sum(map((t) => noise(t), [x]))\nf(1)"),
+        "error: f: @memo needs a pure function, but it may call randn");
+    assert_eq!(eval(&mut ms, "@memo s(x) = sample(Normal(), x)\ns(3)"), "error: s: @memo needs a pure function, but it may call sample");
+    // seed draws nothing, but a remembered call would not restart the stream
+    assert_eq!(eval(&mut ms, "@memo z(x) = seed(x)\nz(1)"), "error: z: @memo needs a pure function, but it may call seed");
+    ms.register_fn("twice", 1, |args| Ok((2.0 * f64::try_from(&args[0])?).into()));
+    assert_eq!(eval(&mut ms, "@memo t(x) = twice(x)\nt(1)"), "error: t: @memo needs a pure function, but it may call twice");
+    // pure functions of the standard library and functions passed in are fine
+    assert_eq!(eval(&mut ms, "@memo g(x) = gcd(x, 12)\ng(18)"), "6");
+    assert_eq!(eval(&mut ms, "@memo h(op, x) = op(x)\nh(sqrt, 16)"), "4");
+    assert_eq!(eval(&mut ms, "@memo 3"), "error: @memo must come before a function definition");
+    assert_eq!(eval(&mut ms, "@cache f(x) = x"), "error: unknown annotation @cache");
+}
+
+#[test]
+fn test_auto() {
+    let mut ms = Interpreter::new();
+    ms.env().shared.memo.set_auto(true);
+    ms.set_limits(Limits { max_steps: Some(100_000), ..Limits::default() });
+    let tree: String = eval(&mut ms, &format!("{}\ntree(60)", TREE));
+    // impure functions are called every time
+    let printed: String = eval(&mut ms, "p(x) = print(x)\np(1)\np(1)");
+    let sampled: String = eval(&mut ms, "s(n) = sample(Normal(), n)\ns(3) == s(3)");
+    assert_eq!(tree, "true");
+    assert_eq!(printed, "()");
+    assert_eq!(sampled, "false");
+    assert_eq!(ms.take_output(), "1\n1\n");
+}
+
+#[test]
+fn test_cache_limit_and_invalidation() {
+    let mut ms = Interpreter::new();
+    ms.set_limits(Limits { max_steps: Some(100_000), max_memo: Some(0), ..Limits::default() });
+    assert_eq!(eval(&mut ms, &format!("@memo {}\ntree(60)", TREE)), "error: step limit exceeded (100000 steps)");
+    // results that depended on a replaced function are forgotten
+    assert_eq!(eval(&mut ms, "@memo f(x) = g(x)\ng(x) = x + 1\nf(1)"), "2");
+    ms.set_limits(Limits::default());
+    assert_eq!(eval(&mut ms, "f(1)"), "2");
+    assert_eq!(eval(&mut ms, "g(x) = x + 2\nf(1)"), "3");
+    // errors, such as a limit that was exceeded, are not remembered
+    ms.set_limits(Limits { max_depth: Some(5), ..Limits::default() });
+    assert_eq!(eval(&mut ms, &format!("@memo {}\ntree(10)", TREE)), "error: recursion limit exceeded (depth 5)");
+    ms.set_limits(Limits::default());
+    assert_eq!(eval(&mut ms, "tree(10)"), "true");
+}
+
+#[test]
+fn test_memo_settings_belong_to_one_interpreter() {
+    let mut memoised = Interpreter::new();
+    let mut plain = Interpreter::new();
+    memoised.env().shared.memo.set_auto(true);
+    for ms in [&mut memoised, &mut plain] {
+        ms.set_limits(Limits { max_steps: Some(100_000), ..Limits::default() });
+    }
+    assert_eq!(eval(&mut memoised, &format!("{}\ntree(60)", TREE)), "true");
+    assert_eq!(eval(&mut plain, &format!("{}\ntree(60)", TREE)), "error: step limit exceeded (100000 steps)");
+}
@@ -0,0 +1,102 @@
+// Tests for imports: relative paths, the search path, namespaces, selective imports, caching and cycles
+use std::path::{Path, PathBuf};
+
+use syntax_interpreter::evaluator::{Env, eval, display_value};
+
+// a temporary directory, removed when the test ends
+struct Scratch(PathBuf);
+
+impl Scratch {
+    fn new(name: &str) -> Scratch {
+        let dir: PathBuf = std::env::temp_dir().join(format!("{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        Scratch(dir)
+    }
+}
+
+impl std::ops::Deref for Scratch {
+    type Target = Path;
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+// a fresh directory holding the given files
+fn write_files(test: &str, files: &[(&str, &str)]) -> Scratch {
+    let dir: Scratch = Scratch::new(&format!("ms_modules_{}", test));
+    for (name, src) in files {
+        let path: PathBuf = dir.join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, src).unwrap();
+    }
+    dir
+}
+
+// run `src` as if it were the script main.ms in `dir`
+fn run_in(dir: &Path, src: &str) -> String {
+    run_with(dir, src, &[])
+}
+
+// the same, searching `search_path` for imports instead of MS_PATH
+fn run_with(dir: &Path, src: &str, search_path: &[PathBuf]) -> String {
+    let mut env = Env::with_builtins();
+    env.shared.modules.enter_script(&dir.join("main.ms"));
+    env.shared.modules.set_search_path(search_path.to_vec());
+    let tokens = syntax_interpreter::lexer::tokenize(src);
+    let ast = syntax_interpreter::parser::parse(&tokens);
+    display_value(&eval(&ast, &mut env))
+}
+
+const GEOM: &str = "sq(x) = x^2\narea(r) = pi * sq(r)\nunit = 2\n";
+
+#[test]
+fn test_import_everything() {
+    let dir = write_files("all", &[("geom.ms", GEOM)]);
+    assert_eq!(run_in(&dir, "import \"geom.ms\"; area(1) / pi + unit"), "3");
+    // the extension may be left out
+    assert_eq!(run_in(&dir, "import \"geom\"; sq(4)"), "16");
+}
+
+#[test]
+fn test_namespaced_and_selective_imports() {
+    let dir = write_files("ns", &[("geom.ms", GEOM)]);
+    assert_eq!(run_in(&dir, "import \"geom.ms\" as g; g.area(2) / pi + g.unit"), "6");
+    assert_eq!(run_in(&dir, "import \"geom.ms\" as g; sq(2)"), "ERROR: function 'sq' does not exist or is not implemented");
+    // area still reaches sq, which was not imported
+    assert_eq!(run_in(&dir, "from \"geom.ms\" import area; area(1) / pi"), "1");
+    assert_eq!(run_in(&dir, "from \"geom.ms\" import area; sq(2)"), "ERROR: function 'sq' does not exist or is not implemented");
+    assert_eq!(run_in(&dir, "from \"geom.ms\" import volume"), "ERROR: import: 'geom.ms' does not define 'volume'");
+}
+
+#[test]
+fn test_relative_paths_and_search_path() {
+    let dir = write_files("paths", &[
+        ("lib/shapes.ms", "import \"util/twice.ms\"\nquad(x) = twice(twice(x))\n"),
+        ("lib/util/twice.ms", "double(x) = x + x\ntwice(x) = double(x)\n"),
+        ("elsewhere/extra_ms_path_module.ms", "triple(x) = 3x\n"),
+    ]);
+    // nested imports resolve next to the file that imports them
+    assert_eq!(run_in(&dir, "import \"lib/shapes.ms\"; quad(1)"), "4");
+    assert!(run_in(&dir, "import \"extra_ms_path_module.ms\"").starts_with("ERROR: import: cannot find 'extra_ms_path_module.ms'"));
+    assert_eq!(run_with(&dir, "import \"extra_ms_path_module.ms\"; triple(2)", &[dir.join("elsewhere")]), "6");
+}
+
+#[test]
+fn test_modules_load_once() {
+    // the module draws a random number when it runs; a second import must not run it again
+    let dir = write_files("cache", &[("r.ms", "x = rand()\n")]);
+    assert_eq!(run_in(&dir, "import \"r.ms\" as a; import \"r.ms\" as b; a.x == b.x"), "true");
+}
+
+#[test]
+fn test_cyclic_imports() {
+    let dir = write_files("cycle", &[("a.ms", "import \"b.ms\"\n"), ("b.ms", "import \"c.ms\"\n"), ("c.ms", "import \"a.ms\"\n")]);
+    assert_eq!(run_in(&dir, "import \"a.ms\""), "ERROR: import: cyclic import a.ms -> b.ms -> c.ms -> a.ms");
+}
@@ -0,0 +1,80 @@
+// Tests for minimize, maximize and fit
+use syntax_interpreter::evaluator::{Env, eval, display_value, Value};
+
+fn eval_display(src: &str) -> String {
+    let mut env = Env::with_builtins();
+    let tokens = syntax_interpreter::lexer::tokenize(src);
+    let ast = syntax_interpreter::parser::parse(&tokens);
+    display_value(&eval(&ast, &mut env))
+}
+
+// the numbers in a field of the result record
+fn field(src: &str, name: &str) -> Vec<f64> {
+    let mut env = Env::with_builtins();
+    let tokens = syntax_interpreter::lexer::tokenize(src);
+    let ast = syntax_interpreter::parser::parse(&tokens);
+    let Value::Record(fields) = eval(&ast, &mut env) else { panic!("{} did not return a record", src) };
+    match fields.into_iter().find(|(k, _)| k == name).map(|(_, v)| v) {
+        Some(Value::Number(x)) => vec![x],
+        Some(Value::Array(items)) => items.iter().flat_map(|v| match v {
+            Value::Number(x) => vec![*x],
+            Value::Array(row) => row.iter().map(|x| if let Value::Number(x) = x { *x } else { f64::NAN }).collect(),
+            _ => vec![f64::NAN],
+        }).collect(),
+        other => panic!("field {} of {} is {:?}", name, src, other),
+    }
+}
+
+fn assert_close(actual: &[f64], expected: &[f64], tol: f64) {
+    assert_eq!(actual.len(), expected.len(), "{:?} vs {:?}", actual, expected);
+    for (a, e) in actual.iter().zip(expected) {
+        assert!((a - e).abs() <= tol, "{:?} vs {:?}", actual, expected);
+    }
+}
+
+#[test]
+fn test_minimize_one_dimension() {
+    assert_close(&field("f(x) = (x - 2)^2 + 1; minimize(f, 0)", "x"), &[2.0], 1e-6);
+    assert_close(&field("f(x) = (x - 2)^2 + 1; minimize(f, 0)", "value"), &[1.0], 1e-12);
+    assert_close(&field("minimize(cos, 3)", "x"), &[std::f64::consts::PI], 1e-6);
+    assert_close(&field("f(x) = sin(x); maximize(f, 1)", "value"), &[1.0], 1e-12);
+    assert_eq!(eval_display("f(x) = x; minimize(f, 0)"), "ERROR: minimize: no minimum found; the function keeps decreasing");
+}
+
+#[test]
+fn test_minimize_several_variables() {
+    let rosenbrock: &str = "f(x, y) = (1 - x)^2 + 100*(y - x^2)^2;";
+    assert_close(&field(&format!("{} minimize(f, [-1.2, 1])", rosenbrock), "x"), &[1.0, 1.0], 1e-6);
+    assert_close(&field(&format!("{} minimize(f, [-1.2, 1], \"nelder-mead\")", rosenbrock), "x"), &[1.0, 1.0], 1e-6);
+    assert_eq!(
+        eval_display("f(x, y) = x^2 + y^2; minimize(f, [1, 1], \"golden\")"),
+        "ERROR: minimize: golden-section search only works in one dimension"
+    );
+}
+
+#[test]
+fn test_minimize_with_bounds() {
+    assert_close(&field("f(x) = (x - 2)^2; minimize(f, 0, [-1, 1])", "x"), &[1.0], 1e-9);
+    assert_close(
+        &field("f(x, y) = (x - 3)^2 + (y + 1)^2; minimize(f, [0, 0], [[-inf, 1], [0, inf]])", "x"),
+        &[1.0, 0.0],
+        1e-9,
+    );
+}
+
+#[test]
+fn test_fit_exact_model() {
+    let src: &str = "m(x, a, b) = a * e^(b*x); fit(m, [0, 1, 2, 3], [2, 2*e^0.5, 2*e, 2*e^1.5], [1, 1])";
+    assert_close(&field(src, "params"), &[2.0, 0.5], 1e-9);
+    assert_close(&field(src, "residuals"), &[0.0; 4], 1e-9);
+}
+
+#[test]
+fn test_fit_covariance_of_noisy_line() {
+    // matches ordinary least squares for a straight line
+    let src: &str = "m(x, a, b) = a*x + b; fit(m, [0, 1, 2, 3], [1.1, 2.9, 5.2, 6.8], [0, 0])";
+    assert_close(&field(src, "params"), &[1.94, 1.09], 1e-8);
+    assert_close(&field(src, "covariance"), &[0.0082, -0.0123, -0.0123, 0.0287], 1e-8);
+    assert_close(&field(src, "errors"), &[0.0082f64.sqrt(), 0.0287f64.sqrt()], 1e-8);
+    assert_eq!(eval_display("m(x, a) = a*x; fit(m, [1, 2], [1], [0])"), "ERROR: fit: xs and ys must have the same length");
+}
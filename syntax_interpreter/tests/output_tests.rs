@@ -0,0 +1,51 @@
+// Tests for output sinks: buffered print, log and error lines, and JSON lines
+use syntax_interpreter::evaluator::{eval, Env};
+use syntax_interpreter::interpreter::Interpreter;
+use syntax_interpreter::output::{Buffer, JsonLines, Output, OutputSink, Stream};
+
+// run `src` and return everything it wrote
+fn run_captured(src: &str) -> Vec<(Stream, String)> {
+    let buffer = Buffer::default();
+    let mut env = Env::with_builtins();
+    env.shared.output = Output::new(buffer.clone());
+    let tokens = syntax_interpreter::lexer::tokenize(src);
+    let ast = syntax_interpreter::parser::parse(&tokens);
+    eval(&ast, &mut env);
+    buffer.take()
+}
+
+#[test]
+fn test_print_log_and_errors_are_captured() {
+    let lines = run_captured("print(1, [2, 3])\nlog(\"done\")\nx = )\nf(x) = print(x^2)\nf(4)");
+    let streams: Vec<Stream> = lines.iter().map(|(s, _)| *s).collect();
+    assert_eq!(streams, vec![Stream::Print, Stream::Log, Stream::Error, Stream::Print]);
+    assert_eq!(lines[0].1, "1 [2, 3]");
+    assert!(lines[1].1.ends_with("[INFO] done"));
+    assert_eq!(lines[2].1, "ERROR: invalid syntax");
+    assert_eq!(lines[3].1, "16");
+    assert_eq!(run_captured("import \"no/such/module\"")[0].0, Stream::Error);
+}
+
+#[test]
+fn test_buffer_separates_streams() {
+    let mut ms = Interpreter::new();
+    ms.eval_str("print(1)\nlog(\"two\")\nprint(3)").unwrap();
+    assert_eq!(ms.take_output(), "1\n3\n");
+    let messages = ms.take_messages();
+    assert!(matches!(&messages[..], [(Stream::Log, text)] if text.ends_with("[INFO] two")));
+    // another sink takes over
+    let other = Buffer::default();
+    ms.set_output(other.clone());
+    ms.eval_str("print(4)").unwrap();
+    assert_eq!(ms.take_output(), "");
+    assert_eq!(other.take_text(Stream::Print), "4\n");
+}
+
+#[test]
+fn test_json_lines() {
+    let mut sink = JsonLines(Vec::new());
+    sink.write(Stream::Print, "say \"hi\"\tnow");
+    sink.write(Stream::Error, "ERROR: x");
+    assert_eq!(String::from_utf8(sink.0).unwrap(),
+        "{\"stream\": \"print\", \"text\": \"say \\\"hi\\\"\\tnow\"}\n{\"stream\": \"error\", \"text\": \"ERROR: x\"}\n");
+}
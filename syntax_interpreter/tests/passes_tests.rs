@@ -0,0 +1,102 @@
+// Tests for the optimisation passes: optimised programs print the same as the originals
+use syntax_interpreter::ast::AstNode;
+use syntax_interpreter::evaluator::{eval, Env};
+use syntax_interpreter::output::{Buffer, Output, Stream};
+use syntax_interpreter::sandbox::Limits;
+use syntax_interpreter::{lexer, parser, passes};
+
+fn parse(src: &str) -> AstNode {
+    parser::parse(&lexer::tokenize(src))
+}
+
+// what `src` prints, optimised or not
+fn run(src: &str, optimized: bool) -> String {
+    let ast: AstNode = if optimized { passes::optimize(parse(src)) } else { parse(src) };
+    let buffer: Buffer = Buffer::default();
+    let mut env: Env = Env::with_builtins();
+    env.shared.output = Output::new(buffer.clone());
+    eval(&ast, &mut env);
+    buffer.take_text(Stream::Print)
+}
+
+fn same(src: &str) -> String {
+    let optimized: String = run(src, true);
+    assert_eq!(optimized, run(src, false), "{}", src);
+    optimized
+}
+
+fn statements(ast: AstNode) -> Vec<AstNode> {
+    match ast {
+        AstNode::Program(stmts) => stmts,
+        other => vec![other],
+    }
+}
+
+// the optimised statements of `src`
+fn optimized(src: &str) -> Vec<AstNode> {
+    statements(passes::optimize(parse(src)))
+}
+
+#[test]
+fn test_constant_folding() {
+    assert_eq!(optimized("x = 2*pi/3"), optimized(&format!("x = {:?}", 2.0 * std::f64::consts::PI / 3.0)));
+    assert_eq!(optimized("print(sqrt(16) + 1, 1 < 2 <= 2, \"a\" + \"b\", not true)"),
+        vec![AstNode::Print(vec![AstNode::Number(5.0), AstNode::Bool(true), AstNode::Str("ab".into()), AstNode::Bool(false)])]);
+    // errors, complex numbers and rebound constants are left to run
+    assert_eq!(optimized("x = 1 / \"a\""), statements(parse("x = 1 / \"a\"")));
+    assert_eq!(optimized("x = 2 * i"), statements(parse("x = 2 * i")));
+    assert_eq!(optimized("pi = 3\nx = 2 * pi"), statements(parse("pi = 3\nx = 2 * pi")));
+    assert_eq!(optimized("f(pi) = 2 * pi"), statements(parse("f(pi) = 2 * pi")));
+    // false and ... never evaluates the rest
+    assert_eq!(optimized("x = false and print(1)"), vec![statements(parse("x = false")).remove(0)]);
+    same("sqrt(x) = 3\nprint(sqrt(4), 2 * e, round(pi^2))");
+}
+
+#[test]
+fn test_dead_conditionals() {
+    assert_eq!(optimized("x = 1 {1 > 2}\ny = 2 {2 > 1}"), vec![
+        AstNode::Assignment { name: "x".into(), expr: Box::new(AstNode::Empty) },
+        AstNode::Assignment { name: "y".into(), expr: Box::new(AstNode::Number(2.0)) },
+    ]);
+    same("f(x) = x {pi < 3}\ng(x) = x {true}\nprint(f(1), g(2), 3 {false})");
+}
+
+#[test]
+fn test_inlining() {
+    assert_eq!(optimized("sq(x) = x * x\narea(r) = pi * sq(r)\nprint(area(2))")[2],
+        AstNode::Print(vec![AstNode::Number(std::f64::consts::PI * 4.0)]));
+    same("sq(x) = x * x\ny = 3\nprint(sq(y), sq(y + 1), sq(sin(y)), sq([1, 2]))");
+    // arguments with effects are evaluated once
+    assert!(same("sq(x) = x * x\nprint(sq(rand()) >= 0, sq(print(\"once\")))").starts_with("once\n"));
+    // later definitions, recursion and wrong argument counts are not inlined
+    same("f(x) = x + 1\nf(x) = x + 2\nprint(f(1))");
+    same("fact(n) = n < 2 or n * fact(n - 1)\nprint(fact(5))");
+    same("f(x, y) = x + y\nprint(f(1))");
+    same("c(x) = x * pi\ng(pi) = c(pi)\nprint(g(2))");
+    same("import \"std/math\"\nf(x) = x^2\nprint(f(hypot(3, 4)))");
+}
+
+#[test]
+fn test_hoisting() {
+    let stmts: Vec<AstNode> = optimized("k = 3\nprint(int((x) => sin(k * pi) * x, 0, 1))");
+    let AstNode::Print(args) = &stmts[1] else { panic!("{:?}", stmts[1]) };
+    let AstNode::FunctionCall { args, .. } = &args[0] else { panic!("{:?}", args[0]) };
+    assert!(matches!(&args[0], AstNode::CallExpr { args, .. } if args.len() == 1), "{:?}", args[0]);
+    same("k = 3\nprint(int((x) => sin(k * x) + sqrt(k), 0, 1))");
+    same("k = 2\nprint(map((t) => t * (k + 1), [1, 2, 3]), filter((t) => t > k * 1, 1..5), reduce((a, b) => a + b * k^2, [1, 2, 3]))");
+    same("k = 2\nprint(map((t) => map((u) => u * t + k * k, [1, 2]), [3, 4]))");
+    // a function of the same name is called as written
+    same("map(f, xs) = xs\nk = 2\nprint(map((t) => t + k * 2, [1]))");
+}
+
+#[test]
+fn test_ranges_are_built_under_the_limits() {
+    // folding 1..10^9 would build it before the array limit is in place
+    assert!(matches!(&optimized("x = 1..10^9")[0], AstNode::Assignment { expr, .. } if matches!(**expr, AstNode::BinaryOp { .. })));
+    let buffer: Buffer = Buffer::default();
+    let mut env: Env = Env::with_builtins();
+    env.shared.output = Output::new(buffer.clone());
+    env.shared.sandbox.set_limits(Limits { max_array_len: Some(1000), ..Limits::default() });
+    eval(&passes::optimize(parse("x = 1..10^9\nprint(len(1..3))")), &mut env);
+    assert!(env.shared.sandbox.exceeded().is_some_and(|msg| msg.contains("array")), "{:?}", env.shared.sandbox.exceeded());
+}
@@ -0,0 +1,56 @@
+// Tests for polynomial values
+use syntax_interpreter::evaluator::{Env, eval, display_value};
+
+fn eval_display(src: &str) -> String {
+    let mut env = Env::with_builtins();
+    let tokens = syntax_interpreter::lexer::tokenize(src);
+    let ast = syntax_interpreter::parser::parse(&tokens);
+    display_value(&eval(&ast, &mut env))
+}
+
+#[test]
+fn test_construct_and_display() {
+    assert_eq!(eval_display("poly([1, -3, 2])"), "x^2 - 3x + 2");
+    assert_eq!(eval_display("poly([-1, 0, 0.5, 0])"), "-x^3 + 0.5x");
+    assert_eq!(eval_display("poly([0, 0])"), "0");
+    assert_eq!(eval_display("f(t) = (t - 1)^2 * (t + 4); poly(f)"), "t^3 + 2t^2 - 7t + 4");
+    assert!(eval_display("poly((x) => sin(x))").starts_with("ERROR"));
+}
+
+#[test]
+fn test_arithmetic() {
+    assert_eq!(eval_display("p = poly([1, -1]); p * p + 1"), "x^2 - 2x + 2");
+    assert_eq!(eval_display("p = poly([1, -1]); p^3 - p / 2"), "x^3 - 3x^2 + 2.5x - 0.5");
+    assert_eq!(eval_display("poly([1, 0, -1]) mod poly([1, 1])"), "0");
+    // powers are expanded up to the same limit as in poly(f)
+    assert_eq!(eval_display("p = poly([1, 1]); len(coeffs(p^64))"), "65");
+    assert_eq!(eval_display("poly([1, 1])^4000000000"), "ERROR: polynomial powers above 64 are not expanded");
+    assert_eq!(eval_display("poly([1, 1])^0.5"), "ERROR: polynomial powers must be non-negative integers");
+}
+
+#[test]
+fn test_division_and_gcd() {
+    assert_eq!(eval_display("polydiv(poly([1, 0, 0, -1]), poly([1, -1]))"), "[x^2 + x + 1, 0]");
+    assert_eq!(eval_display("polydiv(poly([1, 0, 1]), poly([1, 1]))"), "[x - 1, 2]");
+    assert_eq!(eval_display("polygcd(poly([1, -3, 2]), poly([1, 0, -1]))"), "x - 1");
+}
+
+#[test]
+fn test_evaluation_and_composition() {
+    assert_eq!(eval_display("p = poly([2, 0, 1]); p(3)"), "19");
+    assert_eq!(eval_display("polyval(poly([1, 1]), [1, 2])"), "[2, 3]");
+    assert_eq!(eval_display("compose(poly([1, 0, 0]), poly([1, 1]))"), "x^2 + 2x + 1");
+}
+
+#[test]
+fn test_calculus() {
+    assert_eq!(eval_display("polyder(poly([1, 1, 1, 1]))"), "3x^2 + 2x + 1");
+    assert_eq!(eval_display("polyint(poly([3, 0]), 1)"), "1.5x^2 + 1");
+}
+
+#[test]
+fn test_roots() {
+    assert_eq!(eval_display("roots(poly([1, -10, 35, -50, 24]))"), "[1, 2, 3, 4]");
+    assert_eq!(eval_display("roots(poly([1, -4, 6, -4, 1]))"), "[1, 1, 1, 1]");
+    assert_eq!(eval_display("len(roots(poly([1, 0, 1])))"), "2");
+}
@@ -0,0 +1,44 @@
+// Tests for records, field access and tuples
+use syntax_interpreter::evaluator::{Env, eval, display_value};
+
+fn eval_display(src: &str) -> String {
+    let mut env = Env::with_builtins();
+    let tokens = syntax_interpreter::lexer::tokenize(src);
+    let ast = syntax_interpreter::parser::parse(&tokens);
+    display_value(&eval(&ast, &mut env))
+}
+
+#[test]
+fn test_record_literals_and_fields() {
+    assert_eq!(eval_display("p = {x: 1, y: 2}; p"), "{x: 1, y: 2}");
+    assert_eq!(eval_display("p = {x: 1, y: 2}; p.x + p.y"), "3");
+    assert_eq!(eval_display("p = {pos: {x: 3, y: 4}, m: 2}; p.m * p.pos.y"), "8");
+    assert_eq!(eval_display("{x: 1, y: 2} == {x: 1, y: 2}"), "true");
+    // braces without field names are still sets
+    assert_eq!(eval_display("{2, 1}"), "{1, 2}");
+}
+
+#[test]
+fn test_records_in_functions() {
+    assert_eq!(eval_display("norm2(p) = p.x^2 + p.y^2; norm2({x: 3, y: 4})"), "25");
+    assert_eq!(eval_display("k = 2; scale = (v) => {x: k * v, y: v}; scale(3).x"), "6");
+    assert_eq!(eval_display("minimize((x) => (x - 1)^2 + 5, 0).value"), "5");
+}
+
+#[test]
+fn test_record_errors() {
+    assert_eq!(eval_display("p = {x: 1}; p.z"), "ERROR: record has no field 'z'");
+    assert_eq!(eval_display("{x: 1, x: 2}"), "ERROR: field 'x' appears twice in the record");
+    assert_eq!(eval_display("n = 4; n.x"), "ERROR: cannot read field 'x' of 4");
+}
+
+#[test]
+fn test_tuples_and_destructuring() {
+    assert_eq!(eval_display("(1, \"a\", [2])"), "(1, a, [2])");
+    assert_eq!(eval_display("divmod(7, 2)"), "(3, 1)");
+    assert_eq!(eval_display("(q, r) = divmod(17, 5); 5q + r"), "17");
+    assert_eq!(eval_display("(a, b) = [1, 2]; b"), "2");
+    assert_eq!(eval_display("a = 1; b = 2; (a, b) = (b, a); [a, b]"), "[2, 1]");
+    assert_eq!(eval_display("(a, b) = (1, 2, 3)"), "ERROR: cannot unpack 3 values into 2 names");
+    assert_eq!(eval_display("(1, 2) == (1, 2)"), "true");
+}
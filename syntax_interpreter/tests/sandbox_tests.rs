@@ -0,0 +1,76 @@
+// Tests for resource limits: call depth, evaluation steps, time and array sizes
+use std::time::Duration;
+
+use syntax_interpreter::evaluator::display_value;
+use syntax_interpreter::interpreter::Interpreter;
+use syntax_interpreter::sandbox::Limits;
+
+fn limited(limits: Limits) -> Interpreter {
+    let mut ms = Interpreter::new();
+    ms.set_limits(limits);
+    ms
+}
+
+#[test]
+fn test_runaway_recursion_is_an_error() {
+    // the default limits keep this within the 2 MiB stack of a test thread
+    let mut ms = Interpreter::new();
+    let err = ms.eval_str("f(x) = f(x)\nprint(f(1))").unwrap_err();
+    assert!(err.starts_with("recursion limit exceeded"), "{}", err);
+    assert_eq!(ms.take_output(), "");
+
+    let mut ms = limited(Limits { max_depth: Some(5), ..Limits::default() });
+    assert_eq!(ms.eval_str("f(n) = f(n + 1)\nf(1)").unwrap_err(), "recursion limit exceeded (depth 5)");
+    // the next run starts afresh
+    assert_eq!(display_value(&ms.eval_str("g(x) = x + 1\ng(g(1))").unwrap()), "3");
+}
+
+#[test]
+fn test_step_limit() {
+    let mut ms = limited(Limits { max_steps: Some(500), ..Limits::default() });
+    assert_eq!(ms.eval_str("sum(map((x) => x^2, 1..1000))").unwrap_err(), "step limit exceeded (500 steps)");
+    assert_eq!(display_value(&ms.eval_str("sum(map((x) => x^2, 1..10))").unwrap()), "385");
+}
+
+#[test]
+fn test_timeout() {
+    let mut ms = limited(Limits { timeout: Some(Duration::from_millis(20)), ..Limits::default() });
+    assert_eq!(ms.eval_str("a = 1\nsum(map((x) => x^2, 1..1000000))").unwrap_err(), "time limit exceeded (0.02s)");
+}
+
+#[test]
+fn test_array_limit() {
+    let mut ms = limited(Limits { max_array_len: Some(100), ..Limits::default() });
+    let too_big = "array of 1000 elements exceeds the limit of 100";
+    assert_eq!(ms.eval_str("len(1..1000)").unwrap_err(), too_big);
+    assert_eq!(ms.eval_str("range(1000)").unwrap_err(), too_big);
+    assert_eq!(ms.eval_str("randn(1000)").unwrap_err(), too_big);
+    assert_eq!(ms.eval_str("xs = 1..60\nconcat(xs, xs)").unwrap_err(), "array of 120 elements exceeds the limit of 100");
+    assert_eq!(display_value(&ms.eval_str("len(linspace(0, 1, 100))").unwrap()), "100");
+    // built-ins that build an array of a requested length check it before allocating
+    assert_eq!(ms.eval_str("hann(300000000)").unwrap_err(), "array of 300000000 elements exceeds the limit of 100");
+    assert_eq!(ms.eval_str("hamming(1000)").unwrap_err(), too_big);
+    assert_eq!(ms.eval_str("blackman(1000)").unwrap_err(), too_big);
+    assert_eq!(ms.eval_str("freqs(1000, 0.1)").unwrap_err(), too_big);
+    assert_eq!(ms.eval_str("sample(Normal(), 1000)").unwrap_err(), too_big);
+    assert_eq!(ms.eval_str("p = poly([1, 1, 1])\np^60").unwrap_err(), "array of 121 elements exceeds the limit of 100");
+    assert_eq!(display_value(&ms.eval_str("len(hann(100))").unwrap()), "100");
+}
+
+#[test]
+fn test_default_array_limit() {
+    // a huge range is an error under the default limits, not an allocation that aborts the process
+    let mut ms = Interpreter::new();
+    assert_eq!(ms.eval_str("range(1000000000)").unwrap_err(), "array of 1000000000 elements exceeds the limit of 10000000");
+    assert_eq!(ms.eval_str("len(1..10^9)").unwrap_err(), "array of 1000000000 elements exceeds the limit of 10000000");
+    assert_eq!(display_value(&ms.eval_str("len(range(1000))").unwrap()), "1000");
+}
+
+#[test]
+fn test_set_limit() {
+    let mut ms = limited(Limits { max_array_len: Some(1000), ..Limits::default() });
+    // 2^20 subsets are refused before any is built
+    assert_eq!(ms.eval_str("powerset(1..20)").unwrap_err(), "array of 1048576 elements exceeds the limit of 1000");
+    assert_eq!(ms.eval_str("s = set(1..600)\nt = set(601..1200)\ns union t").unwrap_err(), "array of 1200 elements exceeds the limit of 1000");
+    assert_eq!(display_value(&ms.eval_str("len(powerset(1..3))").unwrap()), "8");
+}
@@ -0,0 +1,49 @@
+// Tests for Taylor series, the series{} syntax and Padé approximants
+use syntax_interpreter::evaluator::{Env, eval, display_value};
+
+fn eval_display(src: &str) -> String {
+    let mut env = Env::with_builtins();
+    let tokens = syntax_interpreter::lexer::tokenize(src);
+    let ast = syntax_interpreter::parser::parse(&tokens);
+    display_value(&eval(&ast, &mut env))
+}
+
+fn eval_number(src: &str) -> f64 {
+    eval_display(src).parse().unwrap_or_else(|_| panic!("not a number: {}", src))
+}
+
+#[test]
+fn test_taylor_symbolic_coefficients() {
+    assert_eq!(eval_display("taylor(sin, 0, 3)"), "-0.16666666666666666x^3 + x");
+    assert_eq!(eval_display("taylor((x) => e^x, 0, 2)"), "0.5x^2 + x + 1");
+    assert_eq!(eval_display("f(t) = t^3; taylor(f, 1, 1)"), "3t - 2");
+}
+
+#[test]
+fn test_taylor_through_user_functions() {
+    // g is inlined into f before differentiating
+    assert_eq!(eval_display("g(x) = x^2; f(x) = g(x) + 1; taylor(f, 0, 4)"), "x^2 + 1");
+}
+
+#[test]
+fn test_taylor_numeric_fallback() {
+    // sin(x)/x has no finite symbolic derivatives at 0
+    let c2 = eval_number("p = taylor((x) => sin(x)/x, 0, 4); q = polyder(p, 2); q(0) / 2");
+    assert!((c2 + 1.0 / 6.0).abs() < 1e-8);
+    assert!(eval_display("taylor((x) => 1/x, 0, 2)").starts_with("ERROR"));
+}
+
+#[test]
+fn test_series_syntax() {
+    assert_eq!(eval_display("series{x -> 0} cos(x)"), "0.041666666666666664x^4 - 0.5x^2 + 1");
+    assert_eq!(eval_display("series{x -> 0, 3} 1/(1-x)"), "x^3 + x^2 + x + 1");
+    assert_eq!(eval_display("a = 2; series{t -> a, 1} t^2"), "4t - 4");
+}
+
+#[test]
+fn test_pade() {
+    // [2/2] approximant of e^x at 1 is 19/7
+    let r = eval_number("r = pade((x) => e^x, 0, 2, 2); r(1)");
+    assert!((r - 19.0 / 7.0).abs() < 1e-12);
+    assert!(eval_display("pade(cos, 0, 1, 1)").starts_with("ERROR"));
+}
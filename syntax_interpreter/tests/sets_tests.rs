@@ -0,0 +1,55 @@
+// Tests for sets, set-builder notation and the logical operators
+use syntax_interpreter::evaluator::{Env, eval, display_value};
+
+fn eval_display(src: &str) -> String {
+    let mut env = Env::with_builtins();
+    let tokens = syntax_interpreter::lexer::tokenize(src);
+    let ast = syntax_interpreter::parser::parse(&tokens);
+    display_value(&eval(&ast, &mut env))
+}
+
+#[test]
+fn test_set_literals() {
+    assert_eq!(eval_display("{3, 1, 2, 1}"), "{1, 2, 3}");
+    assert_eq!(eval_display("{}"), "{}");
+    assert_eq!(eval_display("|{1, 2, 2, 3}|"), "3");
+    assert_eq!(eval_display("len(set([3, 3, 1]))"), "2");
+    assert_eq!(eval_display("{1, 2} == {2, 1}"), "true");
+}
+
+#[test]
+fn test_set_algebra() {
+    let defs: &str = "A = {1, 2, 3}; B = {2, 3, 4};";
+    assert_eq!(eval_display(&format!("{} A union B", defs)), "{1, 2, 3, 4}");
+    assert_eq!(eval_display(&format!("{} A intersect B", defs)), "{2, 3}");
+    assert_eq!(eval_display(&format!("{} A \\ B", defs)), "{1}");
+    assert_eq!(eval_display(&format!("{} A - B", defs)), "{1}");
+    assert_eq!(eval_display("powerset({1, 2})"), "{{}, {1}, {2}, {1, 2}}");
+}
+
+#[test]
+fn test_membership() {
+    assert_eq!(eval_display("2 in {1, 2, 3}"), "true");
+    assert_eq!(eval_display("5 in {1, 2, 3}"), "false");
+    assert_eq!(eval_display("3 ∈ [1, 2, 3]"), "true");
+    assert_eq!(eval_display("f(x) = x^2 {x in {1, 2}}; f(2)"), "4");
+}
+
+#[test]
+fn test_set_builder() {
+    assert_eq!(eval_display("{x in 1..20 | x mod 3 = 0}"), "{3, 6, 9, 12, 15, 18}");
+    assert_eq!(eval_display("{x in 1..10 | x > 3 and x < 7}"), "{4, 5, 6}");
+    // an absolute value right after the bar is not read as ||
+    assert_eq!(eval_display("{x in -3..3 | |x| > 1}"), "{-3, -2, 2, 3}");
+    assert_eq!(eval_display("{x in -3..3 | abs(x) > 1 || x = 0}"), "{-3, -2, 0, 2, 3}");
+    // the bound variable does not leak
+    assert_eq!(eval_display("x = 5; {x in 1..3 | x > 1}; x"), "5");
+    assert_eq!(eval_display("1..4"), "[1, 2, 3, 4]");
+}
+
+#[test]
+fn test_operator_precedence() {
+    assert_eq!(eval_display("{1, 2} ∪ {3} ∩ {3, 4}"), "{1, 2, 3}");
+    assert_eq!(eval_display("2 in {1, 2} and 3 in 1..5"), "true");
+    assert_eq!(eval_display("-2^2 + 2*3"), "2");
+}
@@ -0,0 +1,79 @@
+// Tests for FFTs, convolution, windows and signal filters
+use syntax_interpreter::evaluator::{Env, eval, display_value, Value};
+
+fn eval_display(src: &str) -> String {
+    let mut env = Env::with_builtins();
+    let tokens = syntax_interpreter::lexer::tokenize(src);
+    let ast = syntax_interpreter::parser::parse(&tokens);
+    display_value(&eval(&ast, &mut env))
+}
+
+// (re, im) of every element of the resulting array
+fn complex_array(src: &str) -> Vec<(f64, f64)> {
+    let mut env = Env::with_builtins();
+    let tokens = syntax_interpreter::lexer::tokenize(src);
+    let ast = syntax_interpreter::parser::parse(&tokens);
+    let Value::Array(items) = eval(&ast, &mut env) else { panic!("{} did not return an array", src) };
+    items.iter().map(|v| match v {
+        Value::Complex(c) => (c.re, c.im),
+        Value::Number(x) => (*x, 0.0),
+        other => panic!("{} contains {:?}", src, other),
+    }).collect()
+}
+
+fn assert_close(actual: &[(f64, f64)], expected: &[(f64, f64)]) {
+    assert_eq!(actual.len(), expected.len(), "{:?} vs {:?}", actual, expected);
+    for (a, e) in actual.iter().zip(expected) {
+        assert!((a.0 - e.0).abs() <= 1e-9 && (a.1 - e.1).abs() <= 1e-9, "{:?} vs {:?}", actual, expected);
+    }
+}
+
+#[test]
+fn test_fft_power_of_two() {
+    assert_eq!(eval_display("fft([1, 2, 3, 4])"), "[10+0i, -2+2i, -2+0i, -2-2i]");
+    assert_eq!(eval_display("ifft(fft([1, 2, 3, 4]))"), "[1+0i, 2+0i, 3+0i, 4+0i]");
+    assert_eq!(eval_display("|fft([1, 1, 1, 1])|"), "[4, 0, 0, 0]");
+}
+
+#[test]
+fn test_fft_arbitrary_length() {
+    let h: f64 = 3f64.sqrt() / 2.0;
+    assert_close(&complex_array("fft([1, 2, 3])"), &[(6.0, 0.0), (-1.5, h), (-1.5, -h)]);
+    assert_close(&complex_array("ifft(fft([1, 2, 3, 4, 5]))"), &[(1.0, 0.0), (2.0, 0.0), (3.0, 0.0), (4.0, 0.0), (5.0, 0.0)]);
+    assert_close(&complex_array("rfft([1, 0, 0, 0, 0])"), &[(1.0, 0.0); 3]);
+    assert_eq!(eval_display("freqs(5, 0.1)"), "[0, 2, 4, -4, -2]");
+    assert_eq!(eval_display("freqs(4)"), "[0, 0.25, -0.5, -0.25]");
+}
+
+#[test]
+fn test_convolve_and_correlate() {
+    assert_eq!(eval_display("convolve([1, 2, 3], [0, 1, 0.5])"), "[0, 1, 2.5, 4, 1.5]");
+    assert_eq!(eval_display("correlate([1, 2, 3], [0, 1, 0.5])"), "[0.5, 2, 3.5, 3, 0]");
+    // long inputs go through the FFT and must agree with the direct sum
+    let long: Vec<(f64, f64)> = complex_array("convolve(range(100), range(100))");
+    assert_eq!(long.len(), 199);
+    assert!((long[99].0 - (0..100).map(|k| (k * (99 - k)) as f64).sum::<f64>()).abs() < 1e-6);
+}
+
+#[test]
+fn test_windows() {
+    assert_close(&complex_array("hann(5)"), &[(0.0, 0.0), (0.5, 0.0), (1.0, 0.0), (0.5, 0.0), (0.0, 0.0)]);
+    assert_close(&complex_array("hamming(3)"), &[(0.08, 0.0), (1.0, 0.0), (0.08, 0.0)]);
+    assert_close(&complex_array("blackman(3)"), &[(0.0, 0.0), (1.0, 0.0), (0.0, 0.0)]);
+}
+
+#[test]
+fn test_signal_filter() {
+    assert_eq!(eval_display("filter([0.5, 0.5], [1], [2, 4, 6])"), "[1, 3, 5]");
+    assert_eq!(eval_display("filter([1], [1, -0.5], [1, 0, 0, 0])"), "[1, 0.5, 0.25, 0.125]");
+    assert_eq!(eval_display("filter([1], [0], [1])"), "ERROR: filter: the first denominator coefficient must be nonzero");
+    // the array filter is unaffected
+    assert_eq!(eval_display("filter((x) => x > 1, [1, 2, 3])"), "[2, 3]");
+}
+
+#[test]
+fn test_complex_arithmetic() {
+    assert_eq!(eval_display("(1 + 2*i) * (3 - i)"), "5+5i");
+    assert_eq!(eval_display("i^2"), "-1+0i");
+    assert_eq!(eval_display("[1, i] * 2"), "[2+0i, 0+2i]");
+}
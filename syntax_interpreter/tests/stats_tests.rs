@@ -0,0 +1,69 @@
+// Tests for the statistics built-ins
+use syntax_interpreter::evaluator::{Env, eval, display_value, Value};
+
+fn eval_value(src: &str) -> Value {
+    let mut env = Env::with_builtins();
+    let tokens = syntax_interpreter::lexer::tokenize(src);
+    let ast = syntax_interpreter::parser::parse(&tokens);
+    eval(&ast, &mut env)
+}
+
+fn eval_number(src: &str) -> f64 {
+    match eval_value(src) {
+        Value::Number(n) => n,
+        other => panic!("Expected number result, got {}", display_value(&other)),
+    }
+}
+
+fn field(v: &Value, name: &str) -> f64 {
+    match v {
+        Value::Record(fields) => match fields.iter().find(|(k, _)| k == name) {
+            Some((_, Value::Number(n))) => *n,
+            _ => panic!("missing field {}", name),
+        },
+        other => panic!("Expected record, got {}", display_value(other)),
+    }
+}
+
+#[test]
+fn test_descriptive() {
+    let a = "[2, 4, 4, 4, 5, 5, 7, 9]";
+    assert_eq!(eval_number(&format!("mean({})", a)), 5.0);
+    assert_eq!(eval_number(&format!("median({})", a)), 4.5);
+    assert_eq!(eval_number(&format!("mode({})", a)), 4.0);
+    assert_eq!(eval_number(&format!("std({}, 0)", a)), 2.0);
+    assert!((eval_number(&format!("var({})", a)) - 32.0 / 7.0).abs() < 1e-12);
+    assert_eq!(eval_number("quantile([1, 2, 3, 4, 5], 0.25)"), 2.0);
+}
+
+#[test]
+fn test_cov_corr() {
+    assert_eq!(eval_number("cov([1, 2, 3], [2, 4, 7])"), 2.5);
+    assert!((eval_number("corr([1, 2, 3], [3, 2, 1])") + 1.0).abs() < 1e-12);
+}
+
+#[test]
+fn test_distributions() {
+    assert!((eval_number("cdf(Normal(0, 1), 1.96)") - 0.975).abs() < 1e-4);
+    assert!((eval_number("quantile(Normal(0, 1), 0.975)") - 1.959964).abs() < 1e-5);
+    assert!((eval_number("quantile(ChiSquared(3), 0.95)") - 7.814728).abs() < 1e-5);
+    assert!((eval_number("pmf(Binomial(4, 0.5), 2)") - 0.375).abs() < 1e-12);
+    assert_eq!(eval_number("quantile(Poisson(3), 0.5)"), 3.0);
+    assert_eq!(eval_number("len(sample(Beta(2, 3), 4))"), 4.0);
+}
+
+#[test]
+fn test_hypothesis_tests() {
+    let r = eval_value("t_test([1, 2, 3, 4], [3, 4, 5, 6])");
+    assert!((field(&r, "t") + 2.19089).abs() < 1e-4);
+    assert_eq!(field(&r, "df"), 6.0);
+    let r = eval_value("chi2_test([10, 20, 30])");
+    assert_eq!(field(&r, "chi2"), 10.0);
+    assert!((field(&r, "p_value") - (-5.0f64).exp()).abs() < 1e-9);
+}
+
+#[test]
+fn test_invalid_input_is_an_error() {
+    assert!(display_value(&eval_value("mean([])")).starts_with("ERROR"));
+    assert!(display_value(&eval_value("Binomial(2.5, 0.1)")).starts_with("ERROR"));
+}
@@ -0,0 +1,95 @@
+// Tests for the bundled standard library and the math primitives it is built on
+use std::path::PathBuf;
+
+use syntax_interpreter::evaluator::{Env, eval, display_value};
+use syntax_interpreter::output::{Buffer, Output, Stream};
+
+fn eval_display(src: &str) -> String {
+    let mut env = Env::with_builtins();
+    let tokens = syntax_interpreter::lexer::tokenize(src);
+    let ast = syntax_interpreter::parser::parse(&tokens);
+    display_value(&eval(&ast, &mut env))
+}
+
+#[test]
+fn test_primitives() {
+    assert_eq!(eval_display("sqrt(16)"), "4");
+    assert_eq!(eval_display("sqrt(-4)"), "0+2i");
+    assert_eq!(eval_display("exp(0)"), "1");
+    assert_eq!(eval_display("abs(-3)"), "3");
+    assert_eq!(eval_display("re(3 + 4i) + im(3 + 4i)"), "7");
+    assert_eq!(eval_display("sum([1, 2, 3]) + prod([2, 3])"), "12");
+    assert_eq!(eval_display("min(4, 2, 9) + max([4, 2, 9])"), "11");
+    assert_eq!(eval_display("min([])"), "ERROR: min of nothing");
+}
+
+#[test]
+fn test_functions_load_on_first_use() {
+    assert_eq!(eval_display("hypot(3, 4)"), "5");
+    assert_eq!(eval_display("gcd(12, 18) + lcm(4, 6)"), "18");
+    assert_eq!(eval_display("binom(5, 2)"), "10");
+    assert_eq!(eval_display("[binom(3, 5), binom(3, -1), binom(0, 0)]"), "[0, 0, 1]");
+    assert_eq!(eval_display("heron(3, 4, 5)"), "6");
+    assert_eq!(eval_display("round(compound(1000, 0.05, 12, 1) * 100) / 100"), "1051.16");
+    assert_eq!(eval_display("round(irr([-100, 60, 60]) * 10000) / 10000"), "0.1307");
+    assert_eq!(eval_display("binom(52, 5) + binom(5, 0)"), "2598961");
+    // a product of fractions rather than factorials, which overflow
+    assert_eq!(eval_display("round(binom(200, 100) / 10^54)"), "90549");
+    // a function defined by the script wins over the library
+    assert_eq!(eval_display("hypot(x, y) = x + y\nhypot(3, 4)"), "7");
+}
+
+#[test]
+fn test_gcd_lcm() {
+    assert_eq!(eval_display("gcd(10^8, 6)"), "2");
+    assert_eq!(eval_display("gcd(-12, 18) + gcd(0, 5)"), "11");
+    assert_eq!(eval_display("lcm(10^12, 6)"), "3000000000000");
+    assert_eq!(eval_display("gcd(2.5, 5)"), "ERROR: gcd expects whole numbers");
+    assert_eq!(eval_display("lcm(4, 0.5)"), "ERROR: lcm expects whole numbers");
+}
+
+#[test]
+fn test_explicit_imports() {
+    assert_eq!(eval_display("import \"std/physics\" as phys\nphys.g0"), "9.80665");
+    assert_eq!(eval_display("from \"std/geometry\" import rect_area\nrect_area(2, 3)"), "6");
+    assert_eq!(eval_display("import \"std/nope\""), "ERROR: import: cannot find 'std/nope' (searched .)");
+}
+
+#[test]
+fn test_std_can_be_overridden_on_disk() {
+    let dir: PathBuf = std::env::temp_dir().join(format!("ms_std_{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("std")).unwrap();
+    std::fs::write(dir.join("std").join("math.ms"), "hypot(x, y) = 42\n").unwrap();
+    let mut env = Env::with_builtins();
+    env.shared.modules.enter_script(&dir.join("main.ms"));
+    let ast = syntax_interpreter::parser::parse(&syntax_interpreter::lexer::tokenize("hypot(3, 4)"));
+    assert_eq!(display_value(&eval(&ast, &mut env)), "42");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_bundled_sources_are_readable() {
+    for (name, _) in syntax_interpreter::modules::STD {
+        assert!(syntax_interpreter::modules::std_source(name).is_some_and(|s| !s.is_empty()));
+    }
+    // comments end at the line break and a docstring is a statement of its own
+    assert_eq!(eval_display("a = 1 # one\nb = 2\na + b"), "3");
+    assert_eq!(eval_display("\"\"\"notes\"\"\"\n2 + 2"), "4");
+}
+
+#[test]
+fn test_broken_std_module_is_reported_once() {
+    let dir: PathBuf = std::env::temp_dir().join(format!("ms_std_broken_{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("std")).unwrap();
+    std::fs::write(dir.join("std").join("math.ms"), "import \"missing\"\nhypot(x, y) = 42\n").unwrap();
+    let buffer = Buffer::default();
+    let mut env = Env::with_builtins();
+    env.shared.output = Output::new(buffer.clone());
+    env.shared.modules.enter_script(&dir.join("main.ms"));
+    let ast = syntax_interpreter::parser::parse(&syntax_interpreter::lexer::tokenize("hypot(3, 4)\nhypot(3, 4)"));
+    eval(&ast, &mut env);
+    let errors = buffer.take_text(Stream::Error);
+    assert_eq!(errors.lines().count(), 1);
+    assert!(errors.starts_with("ERROR: std/math: "), "{}", errors);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,66 @@
+// Tests for string escapes, concatenation, interpolation and string built-ins
+use syntax_interpreter::evaluator::{Env, eval, display_value};
+
+fn eval_display(src: &str) -> String {
+    let mut env = Env::with_builtins();
+    let tokens = syntax_interpreter::lexer::tokenize(src);
+    let ast = syntax_interpreter::parser::parse(&tokens);
+    display_value(&eval(&ast, &mut env))
+}
+
+#[test]
+fn test_escapes_and_concatenation() {
+    assert_eq!(eval_display(r#""a\tb\nc""#), "a\tb\nc");
+    assert_eq!(eval_display(r#""say \"hi\" \\ \{x\}""#), "say \"hi\" \\ {x}");
+    assert_eq!(eval_display(r#""x = " + 2"#), "x = 2");
+    assert_eq!(eval_display(r#""a" + "b" + [1, 2]"#), "ab[1, 2]");
+    assert_eq!(eval_display(r#""a" + nothing"#), "ERROR: variable 'nothing' does not exist");
+}
+
+#[test]
+fn test_interpolation() {
+    assert_eq!(eval_display(r#"f(x) = x^2; "f(2) = {f(2)}""#), "f(2) = 4");
+    assert_eq!(eval_display(r#"p = {x: 1, y: 2}; "({p.x}, {p.y})""#), "(1, 2)");
+    assert_eq!(eval_display(r#""{upper("a")}{|-2|}""#), "A2");
+    // format placeholders are left alone
+    assert_eq!(eval_display(r#""{} {:.2} {{x}}""#), "{} {:.2} {{x}}");
+    assert_eq!(eval_display(r#""{missing}""#), "ERROR: variable 'missing' does not exist");
+    // a brace that is not closed on its line is text, and the statements after it still run
+    assert_eq!(eval_display("y = \"a{b\"\ny + \"c\""), "a{bc");
+    assert_eq!(eval_display("s = \"{\"\nt = 2\ns + t"), "{2");
+}
+
+#[test]
+fn test_string_functions() {
+    assert_eq!(eval_display(r#"len("héllo")"#), "5");
+    assert_eq!(eval_display(r#"upper("abc") + lower("DEF")"#), "ABCdef");
+    assert_eq!(eval_display(r#"split("a,b,,c", ",")"#), "[a, b, , c]");
+    assert_eq!(eval_display(r#"split("  a b  c ")"#), "[a, b, c]");
+    assert_eq!(eval_display(r#"len(split("abc", ""))"#), "3");
+    assert_eq!(eval_display(r#"join(["x", 1, 2], "-")"#), "x-1-2");
+    assert_eq!(eval_display(r#"replace("hello", "l", "L")"#), "heLLo");
+    assert_eq!(eval_display(r#"substr("hello", 1, 3)"#), "ell");
+    assert_eq!(eval_display(r#"substr("hello", -3)"#), "llo");
+    assert_eq!(eval_display(r#"substr("hi", 1, 10)"#), "i");
+    assert_eq!(eval_display(r#"substr("hello", 1, 10^30)"#), "ello");
+}
+
+#[test]
+fn test_format() {
+    assert_eq!(eval_display(r#"format("{:.3}", pi)"#), "3.142");
+    assert_eq!(eval_display(r#"format("{} and {}", 1, "two")"#), "1 and two");
+    assert_eq!(eval_display(r#"format("[{:6.1}] [{:<4}] [{:>4}]", 2.25, "ab", "cd")"#), "[   2.2] [ab  ] [  cd]");
+    assert_eq!(eval_display(r#"format("{:.2e}", 12345)"#), "1.23e4");
+    assert_eq!(eval_display(r#"format("{:.1}", [1, 2.25])"#), "[1.0, 2.2]");
+    assert_eq!(eval_display(r#"format("{{}}")"#), "{}");
+    assert_eq!(eval_display(r#"format("{} {}", 1)"#), "ERROR: format: not enough arguments for \"{} {}\"");
+    assert_eq!(eval_display(r#"format("{:.2}", "a")"#), "ERROR: format: a precision or exponent needs a number, got a");
+}
+
+#[test]
+fn test_number_conversion() {
+    assert_eq!(eval_display(r#"number("3.5") + 1"#), "4.5");
+    assert_eq!(eval_display(r#"number(" 1e3 ")"#), "1000");
+    assert_eq!(eval_display(r#"number("abc")"#), "ERROR: number: cannot read \"abc\" as a number");
+    assert_eq!(eval_display("str(2) + str(3)"), "23");
+}
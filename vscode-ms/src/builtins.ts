@@ -10,6 +10,7 @@ export type Builtin = {
 export const BUILTINS: Builtin[] = [
   { kind: 'constant', name: 'pi', detail: 'constant: number', documentation: 'π = 3.141592653589793', returnType: 'number' },
   { kind: 'constant', name: 'e', detail: 'constant: number', documentation: 'Euler’s number e ≈ 2.718281828', returnType: 'number' },
+  { kind: 'constant', name: 'inf', detail: 'constant: number', documentation: 'Positive infinity; limits may use x -> inf or x -> -inf', returnType: 'number' },
   { kind: 'constant', name: 'i', detail: 'constant: complex', documentation: 'Imaginary unit i (√-1), 1D complex', returnType: 'complex' },
   { kind: 'constant', name: 'j', detail: 'constant: complex', documentation: 'Imaginary unit j (2D imaginary)', returnType: 'complex' },
   { kind: 'constant', name: 'k', detail: 'constant: complex', documentation: 'Imaginary unit k (3D imaginary)', returnType: 'complex' },
@@ -54,10 +55,131 @@ export const BUILTINS: Builtin[] = [
   { kind: 'function', name: 'acsch', detail: 'fn(x: number): number', documentation: 'Inverse hyperbolic cosecant (arc hyperbolic cosecant)', parameters: ['x: number'], returnType: 'number' },
   { kind: 'function', name: 'acoth', detail: 'fn(x: number): number', documentation: 'Inverse hyperbolic cotangent (arc hyperbolic cotangent)', parameters: ['x: number'], returnType: 'number' },
   { kind: 'function', name: 'ln', detail: 'fn(x: number): number', documentation: 'Natural logarithm', parameters: ['x: number'], returnType: 'number' },
-  { kind: 'function', name: 'log', detail: 'fn(level: string, ...args): unit', documentation: 'Log a message with a level: INFO, WARN, ERROR, DEBUG. Example: log("INFO", "message")', parameters: ['level: string', '...args'], returnType: 'unit' },
+  { kind: 'function', name: 'log', detail: 'fn(level: string, ...args): unit', documentation: 'As a statement starting with a string, log a message with a level: INFO, WARN, ERROR, DEBUG. Example: log("INFO", "message"). log(x) with a number is the base 10 logarithm.', parameters: ['level: string', '...args'], returnType: 'unit' },
   { kind: 'function', name: 'erf', detail: 'fn(x: number): number', documentation: 'Error function', parameters: ['x: number'], returnType: 'number' },
   { kind: 'function', name: 'erfc', detail: 'fn(x: number): number', documentation: 'Complementary error function', parameters: ['x: number'], returnType: 'number' },
+  { kind: 'function', name: 'len', detail: 'fn(a: array|string): number', documentation: 'Number of elements in an array or characters in a string', parameters: ['a: array|string'], returnType: 'number' },
+  { kind: 'function', name: 'range', detail: 'fn(start: number, stop?: number, step?: number): number[]', documentation: 'range(n) gives [0, ..., n-1]; range(a, b[, step]) counts from a up to (not including) b', parameters: ['start: number', 'stop?: number', 'step?: number'], returnType: 'number[]' },
+  { kind: 'function', name: 'linspace', detail: 'fn(a: number, b: number, n: number): number[]', documentation: 'n evenly spaced numbers from a to b (inclusive)', parameters: ['a: number', 'b: number', 'n: number'], returnType: 'number[]' },
+  { kind: 'function', name: 'map', detail: 'fn(f: function, a: array, ...more: array[]): array', documentation: 'Apply f to every element, recursing into nested arrays. Extra arrays must have the same shape; numbers are broadcast', parameters: ['f: function', 'a: array', '...more: array[]'], returnType: 'array' },
+  { kind: 'function', name: 'filter', detail: 'fn(pred: function, a: array): array', documentation: 'Elements of a for which pred(x) is true. filter(b, a, x) with three arrays applies an FIR/IIR filter to x instead.', parameters: ['pred: function', 'a: array'], returnType: 'array' },
+  { kind: 'function', name: 'reduce', detail: 'fn(f: function, a: array, init?: any): any', documentation: 'Left fold of a with f(acc, x); without init the first element is used', parameters: ['f: function', 'a: array', 'init?: any'], returnType: 'any' },
+  { kind: 'function', name: 'zip', detail: 'fn(...arrays: array[]): array', documentation: 'Pair up elements of equally long arrays', parameters: ['...arrays: array[]'], returnType: 'array' },
+  { kind: 'function', name: 'enumerate', detail: 'fn(a: array): array', documentation: 'Pairs [index, element] for each element', parameters: ['a: array'], returnType: 'array' },
+  { kind: 'function', name: 'sort', detail: 'fn(a: array, key?: function): array', documentation: 'Stable ascending sort, optionally by key(x)', parameters: ['a: array', 'key?: function'], returnType: 'array' },
+  { kind: 'function', name: 'unique', detail: 'fn(a: array): array', documentation: 'Elements of a without duplicates, in first-seen order', parameters: ['a: array'], returnType: 'array' },
+  { kind: 'function', name: 'reverse', detail: 'fn(a: array): array', documentation: 'Elements of a in reverse order', parameters: ['a: array'], returnType: 'array' },
+  { kind: 'function', name: 'concat', detail: 'fn(...args: any[]): array', documentation: 'Concatenate arrays; non-array arguments are appended as elements', parameters: ['...args: any[]'], returnType: 'array' },
+  { kind: 'function', name: 'flatten', detail: 'fn(a: array, depth?: number): array', documentation: 'Flatten nested arrays, fully or up to depth levels', parameters: ['a: array', 'depth?: number'], returnType: 'array' },
+  { kind: 'function', name: 'mean', detail: 'fn(a: number[]): number', documentation: 'Arithmetic mean', parameters: ['a: number[]'], returnType: 'number' },
+  { kind: 'function', name: 'median', detail: 'fn(a: number[]): number', documentation: 'Median', parameters: ['a: number[]'], returnType: 'number' },
+  { kind: 'function', name: 'mode', detail: 'fn(a: number[]): number', documentation: 'Most frequent value (smallest on ties)', parameters: ['a: number[]'], returnType: 'number' },
+  { kind: 'function', name: 'var', detail: 'fn(a: number[], ddof?: number): number', documentation: 'Sample variance (ddof = 0 for the population variance)', parameters: ['a: number[]', 'ddof?: number'], returnType: 'number' },
+  { kind: 'function', name: 'std', detail: 'fn(a: number[], ddof?: number): number', documentation: 'Sample standard deviation (ddof = 0 for the population)', parameters: ['a: number[]', 'ddof?: number'], returnType: 'number' },
+  { kind: 'function', name: 'quantile', detail: 'fn(a: number[]|distribution, p: number|number[]): number', documentation: 'Sample quantile (linear interpolation) or inverse CDF of a distribution', parameters: ['a: number[]|distribution', 'p: number|number[]'], returnType: 'number' },
+  { kind: 'function', name: 'cov', detail: 'fn(a: number[], b: number[]): number', documentation: 'Sample covariance', parameters: ['a: number[]', 'b: number[]'], returnType: 'number' },
+  { kind: 'function', name: 'corr', detail: 'fn(a: number[], b: number[]): number', documentation: 'Pearson correlation coefficient', parameters: ['a: number[]', 'b: number[]'], returnType: 'number' },
+  { kind: 'function', name: 'Normal', detail: 'fn(mean?: number, std?: number): distribution', documentation: 'Normal distribution (standard normal without arguments)', parameters: ['mean?: number', 'std?: number'], returnType: 'distribution' },
+  { kind: 'function', name: 'Binomial', detail: 'fn(n: number, p: number): distribution', documentation: 'Binomial distribution', parameters: ['n: number', 'p: number'], returnType: 'distribution' },
+  { kind: 'function', name: 'Poisson', detail: 'fn(lambda: number): distribution', documentation: 'Poisson distribution', parameters: ['lambda: number'], returnType: 'distribution' },
+  { kind: 'function', name: 'StudentT', detail: 'fn(df: number): distribution', documentation: 'Student\'s t distribution', parameters: ['df: number'], returnType: 'distribution' },
+  { kind: 'function', name: 'ChiSquared', detail: 'fn(df: number): distribution', documentation: 'Chi-squared distribution', parameters: ['df: number'], returnType: 'distribution' },
+  { kind: 'function', name: 'FisherF', detail: 'fn(df1: number, df2: number): distribution', documentation: 'F distribution', parameters: ['df1: number', 'df2: number'], returnType: 'distribution' },
+  { kind: 'function', name: 'Gamma', detail: 'fn(shape: number, rate: number): distribution', documentation: 'Gamma distribution', parameters: ['shape: number', 'rate: number'], returnType: 'distribution' },
+  { kind: 'function', name: 'Beta', detail: 'fn(alpha: number, beta: number): distribution', documentation: 'Beta distribution', parameters: ['alpha: number', 'beta: number'], returnType: 'distribution' },
+  { kind: 'function', name: 'pdf', detail: 'fn(d: distribution, x: number): number', documentation: 'Probability density (or mass) of d at x', parameters: ['d: distribution', 'x: number'], returnType: 'number' },
+  { kind: 'function', name: 'pmf', detail: 'fn(d: distribution, x: number): number', documentation: 'Probability mass of a discrete distribution at x', parameters: ['d: distribution', 'x: number'], returnType: 'number' },
+  { kind: 'function', name: 'cdf', detail: 'fn(d: distribution, x: number): number', documentation: 'Cumulative distribution function of d at x', parameters: ['d: distribution', 'x: number'], returnType: 'number' },
+  { kind: 'function', name: 'sample', detail: 'fn(d: distribution, n?: number): number|number[]', documentation: 'Draw one value, or n values, from d', parameters: ['d: distribution', 'n?: number'], returnType: 'number|number[]' },
+  { kind: 'function', name: 't_test', detail: 'fn(a: number[], b: number|number[]): record', documentation: 'One-sample (against a mean) or Welch two-sample t-test; returns {t, df, p_value}', parameters: ['a: number[]', 'b: number|number[]'], returnType: 'record' },
+  { kind: 'function', name: 'chi2_test', detail: 'fn(observed: number[]|number[][], expected?: number[]): record', documentation: 'Chi-squared goodness-of-fit or independence test; returns {chi2, df, p_value}', parameters: ['observed: number[]|number[][]', 'expected?: number[]'], returnType: 'record' },
+  { kind: 'function', name: 'rand', detail: 'fn(min?: number, max?: number): number', documentation: 'Uniform random number in [0, 1) or [min, max)', parameters: ['min?: number', 'max?: number'], returnType: 'number' },
+  { kind: 'function', name: 'seed', detail: 'fn(n: number): unit', documentation: 'Seed the random number generator so that later draws are reproducible', parameters: ['n: number'], returnType: 'unit' },
+  { kind: 'function', name: 'randint', detail: 'fn(lo: number, hi: number, n?: number): number|number[]', documentation: 'Uniform random integer in [lo, hi] (both included), or n of them', parameters: ['lo: number', 'hi: number', 'n?: number'], returnType: 'number|number[]' },
+  { kind: 'function', name: 'randn', detail: 'fn(mean?: number, std?: number, n?: number): number|number[]', documentation: 'Normally distributed random number (standard normal by default), or n of them', parameters: ['mean?: number', 'std?: number', 'n?: number'], returnType: 'number|number[]' },
+  { kind: 'function', name: 'choice', detail: 'fn(a: array, n?: number): any', documentation: 'Random element of a, or n elements drawn with replacement', parameters: ['a: array', 'n?: number'], returnType: 'any' },
+  { kind: 'function', name: 'shuffle', detail: 'fn(a: array): array', documentation: 'Copy of a in random order', parameters: ['a: array'], returnType: 'array' },
+  { kind: 'function', name: 'poly', detail: 'fn(c: number[]|function): polynomial', documentation: 'Polynomial from coefficients (highest power first), e.g. poly([1, -3, 2]) is x^2 - 3x + 2, or from a polynomial function of one variable', parameters: ['c: number[]|function'], returnType: 'polynomial' },
+  { kind: 'function', name: 'coeffs', detail: 'fn(p: polynomial): number[]', documentation: 'Coefficients of p, highest power first', parameters: ['p: polynomial'], returnType: 'number[]' },
+  { kind: 'function', name: 'degree', detail: 'fn(p: polynomial): number', documentation: 'Degree of p', parameters: ['p: polynomial'], returnType: 'number' },
+  { kind: 'function', name: 'polyval', detail: 'fn(p: polynomial, x: number|complex|array): number', documentation: 'Evaluate p at x (Horner scheme); same as p(x)', parameters: ['p: polynomial', 'x: number|complex|array'], returnType: 'number' },
+  { kind: 'function', name: 'polyder', detail: 'fn(p: polynomial, n?: number): polynomial', documentation: 'n-th derivative of p (default 1)', parameters: ['p: polynomial', 'n?: number'], returnType: 'polynomial' },
+  { kind: 'function', name: 'polyint', detail: 'fn(p: polynomial, c?: number): polynomial', documentation: 'Antiderivative of p with constant term c (default 0)', parameters: ['p: polynomial', 'c?: number'], returnType: 'polynomial' },
+  { kind: 'function', name: 'polydiv', detail: 'fn(p: polynomial, q: polynomial): array', documentation: 'Quotient and remainder of p / q', parameters: ['p: polynomial', 'q: polynomial'], returnType: 'array' },
+  { kind: 'function', name: 'polygcd', detail: 'fn(p: polynomial, q: polynomial): polynomial', documentation: 'Monic greatest common divisor', parameters: ['p: polynomial', 'q: polynomial'], returnType: 'polynomial' },
+  { kind: 'function', name: 'compose', detail: 'fn(p: polynomial, q: polynomial): polynomial', documentation: 'Composition p(q(x))', parameters: ['p: polynomial', 'q: polynomial'], returnType: 'polynomial' },
+  { kind: 'function', name: 'roots', detail: 'fn(p: polynomial): array', documentation: 'All complex roots of p with multiplicity', parameters: ['p: polynomial'], returnType: 'array' },
+  { kind: 'function', name: 'taylor', detail: 'taylor(f, x0, n)', documentation: 'Taylor polynomial of f about x0 up to order n.', parameters: ['f', 'x0', 'n'], returnType: 'polynomial' },
+  { kind: 'function', name: 'pade', detail: 'pade(f, x0, m, n)', documentation: 'Padé approximant of f about x0 with numerator degree m and denominator degree n.', parameters: ['f', 'x0', 'm', 'n'], returnType: 'function' },
+  { kind: 'function', name: 'grad', detail: 'grad(f, point)', documentation: 'Gradient of f at point.', parameters: ['f', 'point'], returnType: 'array' },
+  { kind: 'function', name: 'jacobian', detail: 'jacobian(F, point)', documentation: 'Jacobian matrix of the vector field F at point.', parameters: ['F', 'point'], returnType: 'array' },
+  { kind: 'function', name: 'hessian', detail: 'hessian(f, point)', documentation: 'Hessian matrix of f at point.', parameters: ['f', 'point'], returnType: 'array' },
+  { kind: 'function', name: 'div', detail: 'div(F, point)', documentation: 'Divergence of the vector field F at point.', parameters: ['F', 'point'], returnType: 'number' },
+  { kind: 'function', name: 'curl', detail: 'curl(F, point)', documentation: 'Curl of a 3D vector field at point (a number for a 2D field).', parameters: ['F', 'point'], returnType: 'any' },
+  { kind: 'function', name: 'minimize', detail: 'minimize(f, x0, bounds?, method?)', documentation: 'Local minimum of f from x0; returns {x, value, iterations, method}. Methods: golden, bfgs, nelder-mead.', parameters: ['f', 'x0', 'bounds?', 'method?'], returnType: 'record' },
+  { kind: 'function', name: 'maximize', detail: 'maximize(f, x0, bounds?, method?)', documentation: 'Local maximum of f from x0; returns {x, value, iterations, method}.', parameters: ['f', 'x0', 'bounds?', 'method?'], returnType: 'record' },
+  { kind: 'function', name: 'fit', detail: 'fit(model, xs, ys, p0)', documentation: 'Levenberg-Marquardt least-squares fit of model(x, a, b, ...); returns {params, errors, residuals, covariance}.', parameters: ['model', 'xs', 'ys', 'p0'], returnType: 'record' },
+  { kind: 'function', name: 'deriv', detail: 'deriv(f, x, h?)', documentation: 'Numeric derivative of f at x by central differences (step h, default 1e-6).', parameters: ['f', 'x', 'h?'], returnType: 'number' },
+  { kind: 'function', name: 'fft', detail: 'fft(x)', documentation: 'Discrete Fourier transform of x (any length); returns an array of complex numbers.', parameters: ['x'], returnType: 'array' },
+  { kind: 'function', name: 'ifft', detail: 'ifft(X)', documentation: 'Inverse discrete Fourier transform.', parameters: ['X'], returnType: 'array' },
+  { kind: 'function', name: 'rfft', detail: 'rfft(x)', documentation: 'Fourier transform of a real signal, non-negative frequencies only (n/2 + 1 bins).', parameters: ['x'], returnType: 'array' },
+  { kind: 'function', name: 'freqs', detail: 'freqs(n, dt?)', documentation: 'Frequency of each FFT bin for n samples spaced dt apart.', parameters: ['n', 'dt?'], returnType: 'array' },
+  { kind: 'function', name: 'convolve', detail: 'convolve(a, b)', documentation: 'Full linear convolution of a and b.', parameters: ['a', 'b'], returnType: 'array' },
+  { kind: 'function', name: 'correlate', detail: 'correlate(a, b)', documentation: 'Full cross-correlation of a and b.', parameters: ['a', 'b'], returnType: 'array' },
+  { kind: 'function', name: 'hann', detail: 'hann(n)', documentation: 'Hann window of length n.', parameters: ['n'], returnType: 'array' },
+  { kind: 'function', name: 'hamming', detail: 'hamming(n)', documentation: 'Hamming window of length n.', parameters: ['n'], returnType: 'array' },
+  { kind: 'function', name: 'blackman', detail: 'blackman(n)', documentation: 'Blackman window of length n.', parameters: ['n'], returnType: 'array' },
+  { kind: 'function', name: 'set', detail: 'set(a)', documentation: 'The set of the elements of array a.', parameters: ['a'], returnType: 'set' },
+  { kind: 'function', name: 'powerset', detail: 'powerset(S)', documentation: 'The set of all subsets of S.', parameters: ['S'], returnType: 'set' },
+  { kind: 'function', name: 'divmod', detail: 'divmod(a, b)', documentation: 'The tuple (q, r) with a = q*b + r, where r = a mod b.', parameters: ['a', 'b'], returnType: 'tuple' },
+  { kind: 'function', name: 'gcd', detail: 'fn(a, b): number', documentation: 'Greatest common divisor of two whole numbers', parameters: ['a', 'b'], returnType: 'number' },
+  { kind: 'function', name: 'lcm', detail: 'fn(a, b): number', documentation: 'Least common multiple of two whole numbers', parameters: ['a', 'b'], returnType: 'number' },
+  { kind: 'function', name: 'upper', detail: 'upper(s)', documentation: 's in upper case.', parameters: ['s'], returnType: 'string' },
+  { kind: 'function', name: 'lower', detail: 'lower(s)', documentation: 's in lower case.', parameters: ['s'], returnType: 'string' },
+  { kind: 'function', name: 'split', detail: 'split(s, [sep])', documentation: 'Array of the pieces of s between sep (whitespace by default, characters for "").', parameters: ['s', 'sep'], returnType: 'array' },
+  { kind: 'function', name: 'join', detail: 'join(a, [sep])', documentation: 'The elements of a joined with sep.', parameters: ['a', 'sep'], returnType: 'string' },
+  { kind: 'function', name: 'replace', detail: 'replace(s, from, to)', documentation: 's with every occurrence of from replaced by to.', parameters: ['s', 'from', 'to'], returnType: 'string' },
+  { kind: 'function', name: 'substr', detail: 'substr(s, start, [len])', documentation: 'len characters of s from index start (0-based; negative counts from the end).', parameters: ['s', 'start', 'len'], returnType: 'string' },
+  { kind: 'function', name: 'format', detail: 'format(fmt, ...)', documentation: 'Fill the {} placeholders of fmt in order; {:.3} sets the precision, {:8} the width, {:<8}/{:>8} the alignment and {:e} scientific notation. As a statement, format fixed|sci|eng|sig N, format snap, format fraction or format reset sets how numbers are displayed.', parameters: ['fmt', '...'], returnType: 'string' },
+  { kind: 'function', name: 'number', detail: 'number(s)', documentation: 'The number written in the string s.', parameters: ['s'], returnType: 'number' },
+  { kind: 'function', name: 'str', detail: 'str(x)', documentation: 'x as a string.', parameters: ['x'], returnType: 'string' },
+  { kind: 'function', name: 'hypot', detail: 'fn(x, y): number', documentation: 'std/math: √(x² + y²)', parameters: ['x', 'y'], returnType: 'number' },
+  { kind: 'function', name: 'norm', detail: 'fn(v): number', documentation: 'std/math: Euclidean length of a vector', parameters: ['v'], returnType: 'number' },
+  { kind: 'function', name: 'logb', detail: 'fn(x, b): number', documentation: 'std/math: logarithm of x in base b', parameters: ['x', 'b'], returnType: 'number' },
+  { kind: 'function', name: 'log2', detail: 'fn(x): number', documentation: 'std/math: base 2 logarithm', parameters: ['x'], returnType: 'number' },
+  { kind: 'function', name: 'deg', detail: 'fn(x): number', documentation: 'std/math: radians to degrees', parameters: ['x'], returnType: 'number' },
+  { kind: 'function', name: 'rad', detail: 'fn(x): number', documentation: 'std/math: degrees to radians', parameters: ['x'], returnType: 'number' },
+  { kind: 'function', name: 'lerp', detail: 'fn(a, b, t): number', documentation: 'std/math: a + (b - a)·t', parameters: ['a', 'b', 't'], returnType: 'number' },
+  { kind: 'function', name: 'binom', detail: 'fn(n, k): number', documentation: 'std/math: binomial coefficient n choose k, 0 when k < 0 or k > n', parameters: ['n', 'k'], returnType: 'number' },
+  { kind: 'function', name: 'circle_area', detail: 'fn(r): number', documentation: 'std/geometry: area of a circle', parameters: ['r'], returnType: 'number' },
+  { kind: 'function', name: 'circumference', detail: 'fn(r): number', documentation: 'std/geometry: circumference of a circle', parameters: ['r'], returnType: 'number' },
+  { kind: 'function', name: 'sphere_area', detail: 'fn(r): number', documentation: 'std/geometry: surface area of a sphere', parameters: ['r'], returnType: 'number' },
+  { kind: 'function', name: 'sphere_volume', detail: 'fn(r): number', documentation: 'std/geometry: volume of a sphere', parameters: ['r'], returnType: 'number' },
+  { kind: 'function', name: 'cylinder_volume', detail: 'fn(r, h): number', documentation: 'std/geometry: volume of a cylinder', parameters: ['r', 'h'], returnType: 'number' },
+  { kind: 'function', name: 'cone_volume', detail: 'fn(r, h): number', documentation: 'std/geometry: volume of a cone', parameters: ['r', 'h'], returnType: 'number' },
+  { kind: 'function', name: 'rect_area', detail: 'fn(w, h): number', documentation: 'std/geometry: area of a rectangle', parameters: ['w', 'h'], returnType: 'number' },
+  { kind: 'function', name: 'triangle_area', detail: 'fn(b, h): number', documentation: 'std/geometry: area of a triangle from base and height', parameters: ['b', 'h'], returnType: 'number' },
+  { kind: 'function', name: 'heron', detail: 'fn(a, b, c): number', documentation: 'std/geometry: area of a triangle from its sides', parameters: ['a', 'b', 'c'], returnType: 'number' },
+  { kind: 'function', name: 'distance', detail: 'fn(p, q): number', documentation: 'std/geometry: distance between two points', parameters: ['p', 'q'], returnType: 'number' },
+  { kind: 'function', name: 'midpoint', detail: 'fn(p, q): number[]', documentation: 'std/geometry: midpoint of two points', parameters: ['p', 'q'], returnType: 'number[]' },
+  { kind: 'function', name: 'simple_interest', detail: 'fn(principal, rate, periods): number', documentation: 'std/finance: simple interest earned', parameters: ['principal', 'rate', 'periods'], returnType: 'number' },
+  { kind: 'function', name: 'compound', detail: 'fn(principal, rate, n, t): number', documentation: 'std/finance: balance compounded n times per period for t periods', parameters: ['principal', 'rate', 'n', 't'], returnType: 'number' },
+  { kind: 'function', name: 'continuous', detail: 'fn(principal, rate, t): number', documentation: 'std/finance: balance compounded continuously', parameters: ['principal', 'rate', 't'], returnType: 'number' },
+  { kind: 'function', name: 'future_value', detail: 'fn(amount, rate, periods): number', documentation: 'std/finance: value of an amount after compounding', parameters: ['amount', 'rate', 'periods'], returnType: 'number' },
+  { kind: 'function', name: 'present_value', detail: 'fn(amount, rate, periods): number', documentation: 'std/finance: discounted value of a future amount', parameters: ['amount', 'rate', 'periods'], returnType: 'number' },
+  { kind: 'function', name: 'annuity_payment', detail: 'fn(principal, rate, periods): number', documentation: 'std/finance: payment per period repaying a loan', parameters: ['principal', 'rate', 'periods'], returnType: 'number' },
+  { kind: 'function', name: 'npv', detail: 'fn(rate, flows): number', documentation: 'std/finance: net present value of cash flows, the first at time 0', parameters: ['rate', 'flows'], returnType: 'number' },
+  { kind: 'function', name: 'irr', detail: 'fn(flows): number', documentation: 'std/finance: internal rate of return of cash flows', parameters: ['flows'], returnType: 'number' },
+  { kind: 'function', name: 'kinetic_energy', detail: 'fn(m, v): number', documentation: 'std/physics: m·v²/2', parameters: ['m', 'v'], returnType: 'number' },
+  { kind: 'function', name: 'potential_energy', detail: 'fn(m, h): number', documentation: 'std/physics: m·g0·h', parameters: ['m', 'h'], returnType: 'number' },
+  { kind: 'function', name: 'momentum', detail: 'fn(m, v): number', documentation: 'std/physics: m·v', parameters: ['m', 'v'], returnType: 'number' },
+  { kind: 'function', name: 'position', detail: 'fn(x0, v0, a, t): number', documentation: 'std/physics: position under constant acceleration', parameters: ['x0', 'v0', 'a', 't'], returnType: 'number' },
+  { kind: 'function', name: 'fall_time', detail: 'fn(h): number', documentation: 'std/physics: time to fall h metres from rest', parameters: ['h'], returnType: 'number' },
+  { kind: 'function', name: 'projectile_range', detail: 'fn(v, theta): number', documentation: 'std/physics: range of a projectile on level ground', parameters: ['v', 'theta'], returnType: 'number' },
+  { kind: 'function', name: 'gravity', detail: 'fn(m1, m2, r): number', documentation: 'std/physics: gravitational force between two masses', parameters: ['m1', 'm2', 'r'], returnType: 'number' },
+  { kind: 'function', name: 'pendulum_period', detail: 'fn(l): number', documentation: 'std/physics: period of a simple pendulum', parameters: ['l'], returnType: 'number' },
+  { kind: 'function', name: 'spring_period', detail: 'fn(m, k): number', documentation: 'std/physics: period of a mass on a spring', parameters: ['m', 'k'], returnType: 'number' },
   { kind: 'function', name: 'print', detail: 'fn(...args): unit', documentation: 'Print values to standard output', parameters: ['...args'], returnType: 'unit' },
 ];
 
-export const KEYWORDS = ['import', 'print', 'log', 'd'];
\ No newline at end of file
+export const KEYWORDS = ['import', 'from', 'as', 'print', 'log', 'd', 'true', 'false', 'and', 'or', 'not', 'xor', 'in', 'union', 'intersect'];
\ No newline at end of file
@@ -23,7 +23,7 @@ export function activate(context: vscode.ExtensionContext) {
       const tmp = os.tmpdir();
       const tmpFile = path.join(tmp, `ms_diag_${Date.now()}_${Math.random().toString(36).slice(2)}.ms`);
       fs.writeFileSync(tmpFile, doc.getText());
-      const result = spawnSync(exePath, [tmpFile], { encoding: 'utf8' });
+      const result = spawnSync(exePath, ['--timeout', '5', tmpFile], { encoding: 'utf8', timeout: 10000 });
       fs.unlinkSync(tmpFile);
       let output = (result.stderr || '').trim();
       if (!output) output = (result.stdout || '').trim();
@@ -250,7 +250,7 @@ export function activate(context: vscode.ExtensionContext) {
           const tmp = os.tmpdir();
           const tmpFile = path.join(tmp, `ms_inline_${Date.now()}_${Math.random().toString(36).slice(2)}.ms`);
           fs.writeFileSync(tmpFile, text);
-          const result = spawnSync(exePath, [tmpFile], { encoding: 'utf8' });
+          const result = spawnSync(exePath, ['--timeout', '5', tmpFile], { encoding: 'utf8', timeout: 10000 });
           fs.unlinkSync(tmpFile);
           let output = (result.stdout || '').trim();
           if (!output) output = (result.stderr || '').trim();